@@ -0,0 +1,541 @@
+//! The `unxml` command line: argument handling, subcommand dispatch and
+//! output, over the library modules.
+
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use glob::glob;
+
+use crate::canonical::{CanonicalOptions, KnownPrefixes, NsPrefixes};
+use crate::cli::{Cli, DiffCli, MergeCli, SiteCli};
+use crate::config::{Config, Settings};
+use crate::document::detect_mode_from_ext;
+use crate::highlight::Css;
+use crate::model::{Collapse, FormatOpts};
+use crate::parse::{detect_format, read_file_lenient, read_stdin_lenient};
+use crate::process::{ProcessOptions, emit, process_file, process_stdin};
+use crate::{
+    batch, c14n, diff, highlight, infer, install, json_infer, lsp, mcp, merge, site, watch,
+};
+
+/// Run the CLI on the process arguments; `main.rs` is just this call.
+pub fn run() -> Result<()> {
+    run_from(std::env::args())
+}
+
+/// Run the CLI on `args`, the first of which is the program name.
+pub fn run_from(args: impl IntoIterator<Item = String>) -> Result<()> {
+    let args: Vec<String> = args.into_iter().collect();
+
+    // `unxml git <args>` is a thin passthrough to `git <args>` with the unxml
+    // textconv driver applied for just this invocation. It's intercepted
+    // ahead of the normal `Cli::parse_from()` below, since `files: Vec<String>`
+    // would otherwise swallow "git" and everything after it as filenames.
+    let rest = &args[1.min(args.len())..];
+    if rest.first().map(String::as_str) == Some("git") {
+        return install::git_passthrough(&rest[1..], &Config::discover()?);
+    }
+
+    // `unxml diff OLD NEW` has its own argument set, intercepted the same way.
+    // Like diff(1) it exits 0 when the documents match, 1 when they differ
+    // and 2 on error.
+    if rest.first().map(String::as_str) == Some("diff") {
+        let args = DiffCli::parse_from(
+            std::iter::once("unxml diff".to_string()).chain(rest[1..].iter().cloned()),
+        );
+        match diff::run_diff(&args) {
+            Ok(false) => return Ok(()),
+            Ok(true) => std::process::exit(1),
+            Err(e) => {
+                eprintln!("Error: {e:#}");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // `unxml merge-driver BASE OURS THEIRS` is git's merge-driver contract:
+    // write the result over OURS, exit non-zero when conflicts remain.
+    if rest.first().map(String::as_str) == Some("merge-driver") {
+        let args = MergeCli::parse_from(
+            std::iter::once("unxml merge-driver".to_string()).chain(rest[1..].iter().cloned()),
+        );
+        match merge::run_merge_driver(&args) {
+            Ok(false) => return Ok(()),
+            Ok(true) => std::process::exit(1),
+            Err(e) => {
+                eprintln!("Error: {e:#}");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // `unxml mcp` serves the Model Context Protocol on stdio until the
+    // client closes it.
+    if rest.first().map(String::as_str) == Some("mcp") {
+        return mcp::run_mcp();
+    }
+
+    // `unxml lsp` serves the Language Server Protocol on stdio.
+    if rest.first().map(String::as_str) == Some("lsp") {
+        return lsp::run_lsp();
+    }
+
+    // `unxml site SRC_DIR OUT_DIR` writes a static gallery of SRC_DIR.
+    if rest.first().map(String::as_str) == Some("site") {
+        let args = SiteCli::parse_from(
+            std::iter::once("unxml site".to_string()).chain(rest[1..].iter().cloned()),
+        );
+        return site::run_site(&args);
+    }
+
+    let cli = Cli::parse_from(&args);
+
+    // Side-channel action: install the bundled skill and exit before any
+    // input handling (no files required).
+    if cli.install_skills {
+        return install::install_skills();
+    }
+
+    // Side-channel action: register the .unxml grammar with bat and exit.
+    if cli.install_bat {
+        return install::install_bat();
+    }
+
+    // Side-channel action: print the --html stylesheet and exit.
+    if cli.html_css {
+        write_output(&cli, &highlight::html_css()?)?;
+        return Ok(());
+    }
+
+    // Project and user defaults (`.unxml.toml`), under the command line.
+    let mut config = if cli.no_config {
+        Config::default()
+    } else {
+        Config::discover()?
+    };
+    for spec in &cli.map {
+        config
+            .add_map(spec, Path::new("."))
+            .with_context(|| format!("Invalid --map '{spec}'"))?;
+    }
+    let command_line = Settings::from_cli(&cli);
+
+    // Side-channel action: wire unxml in as the current repo's XML/HTML diff
+    // driver and exit (no input files required).
+    if cli.init_git {
+        return install::init_git(cli.merge, &config, &cli.map);
+    }
+
+    if cli.raw && !(cli.html || cli.cat) {
+        return Err(anyhow::anyhow!("--raw requires --html or --cat"));
+    }
+
+    // Plain XML rendering is the default. Suffix-based mode autodetection and
+    // document-type sniffing are opt-in via `--auto`, which `--bat`/`--html`/
+    // `--cat` also imply unless `--no-auto` cancels that implication (used
+    // when one of those wants native highlighting on the exact literal,
+    // non-auto output).
+    let auto = cli.auto || ((cli.bat || cli.html || cli.cat) && !cli.no_auto);
+
+    // Under --auto the document type is also sniffed, adding prefixes to hide
+    // (to each input's --hide-ns list) and wrapper chains to fold.
+    let sniff = auto;
+
+    // Handle stdin input
+    if cli.stdin {
+        // When using stdin, files should be empty
+        if !cli.files.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Cannot specify both --stdin and file arguments"
+            ));
+        }
+
+        // Schema inference treats stdin as the single instance document.
+        if cli.infer_xsd || cli.infer_json_schema {
+            let sources = [("stdin".to_string(), read_stdin_lenient()?)];
+            write_output(
+                &cli,
+                &run_infer(cli.infer_json_schema, &sources, cli.emit_schema)?,
+            )?;
+            return Ok(());
+        }
+
+        if cli.c14n || cli.exc_c14n {
+            let content = read_stdin_lenient()?;
+            write_output(
+                &cli,
+                &run_c14n(
+                    cli.exc_c14n,
+                    cli.with_comments,
+                    cli.select.as_deref(),
+                    &content,
+                )?,
+            )?;
+            return Ok(());
+        }
+
+        // --raw skips the unxml transform entirely: highlight the stdin
+        // text as-is (XML or HTML, same detection as normal processing).
+        if cli.raw {
+            let content = read_stdin_lenient()?;
+            let format = detect_format(&content, "stdin");
+            if cli.html {
+                write_output(
+                    &cli,
+                    &highlight::html_page_raw(
+                        &content,
+                        format.syntax_name(),
+                        &Css::new(cli.html_embed_css),
+                    )?,
+                )?;
+            } else {
+                write_output(&cli, &highlight::ansi_raw(&content, format.syntax_name())?)?;
+            }
+            return Ok(());
+        }
+
+        // Process stdin input (no path, so nothing to autodetect from).
+        let input =
+            InputOptions::resolve(config.settings_for(None).overlay(&command_line), None, auto)?;
+        match process_stdin(&input.opts, &input.process_options(&cli, sniff)) {
+            Ok(output) => write_rendered(&cli, &output, false)?,
+            Err(e) => {
+                eprintln!("Error processing stdin: {e}");
+                return Err(e);
+            }
+        }
+        return Ok(());
+    }
+
+    // Handle file input
+    if cli.files.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No files specified. Please provide at least one file or glob pattern, or use --stdin."
+        ));
+    }
+
+    let mut all_files = Vec::new();
+
+    // Expand glob patterns and collect all files
+    for pattern in &cli.files {
+        // An existing file takes precedence over glob interpretation: real
+        // filenames can contain glob metacharacters (e.g. `Invoice-[uuid].xml`),
+        // and an explicitly-passed file that exists should be read verbatim
+        // rather than treated as a (likely non-matching) pattern.
+        if std::path::Path::new(pattern).is_file() {
+            all_files.push(pattern.clone());
+        } else if pattern.contains('*') || pattern.contains('?') || pattern.contains('[') {
+            // This is a glob pattern
+            match glob(pattern) {
+                Ok(paths) => {
+                    for entry in paths {
+                        match entry {
+                            Ok(path) => {
+                                if let Some(path_str) = path.to_str() {
+                                    all_files.push(path_str.to_string());
+                                }
+                            }
+                            Err(e) => {
+                                eprintln!("Warning: Error reading glob entry: {e}");
+                            }
+                        }
+                    }
+                }
+                Err(e) => {
+                    return Err(anyhow::anyhow!("Invalid glob pattern '{}': {}", pattern, e));
+                }
+            }
+        } else {
+            // This is a regular file path
+            all_files.push(pattern.clone());
+        }
+    }
+
+    if all_files.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No files found matching the specified patterns."
+        ));
+    }
+
+    // Schema inference reads every file as an instance of one schema, so it
+    // consumes the whole list at once instead of rendering file by file.
+    if cli.infer_xsd || cli.infer_json_schema {
+        let mut sources = Vec::new();
+        for file_path in &all_files {
+            sources.push((file_path.clone(), read_file_lenient(file_path)?));
+        }
+        write_output(
+            &cli,
+            &run_infer(cli.infer_json_schema, &sources, cli.emit_schema)?,
+        )?;
+        return Ok(());
+    }
+
+    // Canonical XML is one byte stream for one document; there is no sensible
+    // way to concatenate several.
+    if cli.c14n || cli.exc_c14n {
+        let [file_path] = all_files.as_slice() else {
+            return Err(anyhow::anyhow!("--c14n/--exc-c14n take exactly one input"));
+        };
+        let content = read_file_lenient(file_path)?;
+        write_output(
+            &cli,
+            &run_c14n(
+                cli.exc_c14n,
+                cli.with_comments,
+                cli.select.as_deref(),
+                &content,
+            )?,
+        )?;
+        return Ok(());
+    }
+
+    // --raw skips the unxml transform entirely: read each file's original
+    // text and highlight it as-is (XML or HTML, picked from the first file).
+    if cli.raw {
+        let multiple = all_files.len() > 1;
+        let mut combined = String::new();
+        let mut syntax_name = "XML";
+        for (i, file_path) in all_files.iter().enumerate() {
+            if i > 0 {
+                combined.push('\n');
+            }
+            let content = read_file_lenient(file_path)?;
+            if i == 0 {
+                syntax_name = detect_format(&content, file_path).syntax_name();
+            }
+            if multiple {
+                combined.push_str(&format!("<!-- FILE: {file_path} -->\n"));
+            }
+            combined.push_str(&content);
+        }
+        if cli.html {
+            write_output(
+                &cli,
+                &highlight::html_page_raw(&combined, syntax_name, &Css::new(cli.html_embed_css))?,
+            )?;
+        } else {
+            write_output(&cli, &highlight::ansi_raw(&combined, syntax_name)?)?;
+        }
+        return Ok(());
+    }
+
+    // Render every file into one stream for the pager (or stdout), or with
+    // --out-dir/--suffix into one file each; under --watch, again on every
+    // change.
+    let layout = if cli.out_dir.is_some() || cli.suffix.is_some() {
+        Some(batch::Layout::new(
+            &all_files,
+            cli.out_dir.as_deref(),
+            cli.suffix.as_deref(),
+            cli.html,
+        )?)
+    } else {
+        None
+    };
+    let render = || {
+        if let Some(layout) = &layout {
+            let written =
+                batch::write_all(&all_files, layout, cli.html, cli.html_embed_css, |file| {
+                    render_file(file, &config, &command_line, &cli, auto, sniff)
+                })?;
+            eprintln!("Wrote {} of {} file(s)", written.len(), all_files.len());
+            return Ok(());
+        }
+        let combined = render_files(&all_files, &config, &command_line, &cli, auto, sniff)?;
+        write_rendered(&cli, &combined, cli.watch)
+    };
+    if cli.watch {
+        watch::watch(&all_files, cli.expand, render);
+    }
+    render()
+}
+
+/// Render each file, with a `// FILE:` header per file when there are
+/// several. A file that fails is reported and skipped.
+fn render_files(
+    files: &[String],
+    config: &Config,
+    command_line: &Settings,
+    cli: &Cli,
+    auto: bool,
+    sniff: bool,
+) -> Result<String> {
+    let multiple = files.len() > 1;
+    let mut combined = String::new();
+    for (i, file_path) in files.iter().enumerate() {
+        // Blank separator line between files (not before the first).
+        if i > 0 {
+            combined.push('\n');
+        }
+
+        // File header comment only when processing more than one file.
+        if multiple {
+            combined.push_str(&format!("// FILE: {file_path}\n"));
+        }
+
+        match render_file(file_path, config, command_line, cli, auto, sniff) {
+            Ok(output) => combined.push_str(&output),
+            Err(e) => {
+                eprintln!("Error processing file '{file_path}': {e:#}");
+                // Continue processing other files instead of stopping
+            }
+        }
+    }
+    Ok(combined)
+}
+
+/// Render one file under the settings the config gives its path, overlaid
+/// by the command line's.
+fn render_file(
+    file_path: &str,
+    config: &Config,
+    command_line: &Settings,
+    cli: &Cli,
+    auto: bool,
+    sniff: bool,
+) -> Result<String> {
+    let settings = config.settings_for(Some(file_path)).overlay(command_line);
+    let input = InputOptions::resolve(settings, Some(file_path), auto)?;
+    process_file(file_path, &input.opts, &input.process_options(cli, sniff))
+}
+
+/// Send rendered unxml text on: as an `--html` page or `--cat` escapes, to
+/// `--output` or stdout (paged through `bat` under `--bat`). With `redraw`
+/// (`--watch`) the terminal is cleared first, so each render replaces the
+/// last.
+fn write_rendered(cli: &Cli, rendered: &str, redraw: bool) -> Result<()> {
+    let text = if cli.html {
+        highlight::html_page(rendered, &Css::new(cli.html_embed_css))?
+    } else if cli.cat {
+        highlight::ansi(rendered)?
+    } else {
+        rendered.to_string()
+    };
+    if cli.output.is_none() && redraw {
+        print!("\x1b[2J\x1b[H");
+    }
+    if cli.output.is_none() && !cli.html && !cli.cat {
+        emit(&text, cli.bat);
+        return Ok(());
+    }
+    write_output(cli, &text)?;
+    if let Some(path) = &cli.output
+        && redraw
+    {
+        eprintln!("Wrote {path}");
+    }
+    Ok(())
+}
+
+/// Write `text` to `--output`, or print it.
+fn write_output(cli: &Cli, text: &str) -> Result<()> {
+    match &cli.output {
+        Some(path) => std::fs::write(path, text).with_context(|| format!("Failed to write {path}")),
+        None => {
+            print!("{text}");
+            std::io::stdout().flush()?;
+            Ok(())
+        }
+    }
+}
+
+/// What rendering one input takes from its settings (the config's for its
+/// path, under the command line's). The rest of `ProcessOptions` comes from
+/// the command line alone.
+struct InputOptions {
+    opts: FormatOpts,
+    format: Option<String>,
+    hide_ns: HashSet<String>,
+    canonical_options: CanonicalOptions,
+}
+
+impl InputOptions {
+    fn resolve(settings: Settings, path: Option<&str>, auto: bool) -> Result<Self> {
+        // Suffix-based mode autodetection only fills in a mode when none was
+        // set explicitly, by a flag or the config.
+        let mut opts = match (settings.mode, path) {
+            (Some(mode), _) => mode,
+            (None, Some(path)) if auto => detect_mode_from_ext(path),
+            (None, _) => FormatOpts::default(),
+        };
+
+        // `--collapse` is orthogonal to the processing mode, so it is applied
+        // after --auto picks one.
+        opts.collapse = match settings.collapse {
+            None => Collapse::Off,
+            Some(names) if names.is_empty() => Collapse::All,
+            Some(names) => Collapse::Only(names.into_iter().collect()),
+        };
+
+        let mut canonical_options = CanonicalOptions::parse(
+            &settings.key,
+            &settings.mask,
+            &settings.normalize,
+            settings.schema.as_deref(),
+        )?;
+        canonical_options.set_ignore(
+            &settings.ignore_elem,
+            &settings.ignore_attr,
+            settings.ignore_preset,
+        )?;
+        canonical_options.ns = NsPrefixes::new(
+            KnownPrefixes::load(&settings.ns_prefix)?,
+            settings.ns_map.as_deref(),
+            settings.ns_hash,
+        )?;
+
+        Ok(Self {
+            opts,
+            format: settings.format,
+            hide_ns: settings.hide_ns.into_iter().collect(),
+            canonical_options,
+        })
+    }
+
+    fn process_options<'a>(&'a self, cli: &'a Cli, sniff: bool) -> ProcessOptions<'a> {
+        ProcessOptions {
+            format_override: self.format.as_deref(),
+            hide_ns: &self.hide_ns,
+            sniff,
+            select: cli.select.as_deref(),
+            canonical: cli.canonical,
+            canonical_options: &self.canonical_options,
+            known_prefixes: &self.canonical_options.ns.known,
+            paths: cli.paths,
+            depth: cli.depth.unwrap_or(0),
+            no_attrs: cli.no_attrs,
+            fold: cli.fold,
+            expand: cli.expand,
+        }
+    }
+}
+
+/// Run `--infer-json-schema` (when `json_schema`) or `--infer-xsd` over the
+/// `(name, content)` sources.
+fn run_infer(json_schema: bool, sources: &[(String, String)], emit_schema: bool) -> Result<String> {
+    if json_schema {
+        json_infer::run_infer_json_schema(sources, emit_schema)
+    } else {
+        infer::run_infer_xsd(sources, emit_schema)
+    }
+}
+
+/// Run `--exc-c14n` (when `exclusive`) or `--c14n` over one document, or
+/// over its `--select` subtrees.
+fn run_c14n(
+    exclusive: bool,
+    with_comments: bool,
+    select: Option<&str>,
+    content: &str,
+) -> Result<String> {
+    let method = if exclusive {
+        c14n::Method::Exclusive
+    } else {
+        c14n::Method::Inclusive
+    };
+    c14n::canonical_xml(content, method, with_comments, select)
+}
@@ -0,0 +1,381 @@
+//! `--out-dir`/`--suffix`: write one output file per input instead of one
+//! concatenated stream — under `--out-dir` mirroring the input tree, else
+//! beside each input — and with `--html` an `index.html` at the tree's root
+//! listing the pages with how much smaller each rendering is than its source. `unxml site`
+//! builds on the same layout and index.
+
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::highlight::{self, Css, html_css};
+use crate::parse::read_file_lenient;
+use crate::write::escape_attr;
+
+/// Where each input's output goes.
+pub(crate) struct Layout {
+    out_dir: Option<PathBuf>,
+    suffix: String,
+    /// The deepest directory holding every input: the tree `out_dir`
+    /// mirrors, and where the index and stylesheet go without one.
+    base: PathBuf,
+}
+
+impl Layout {
+    /// Under `out_dir` the inputs' tree is mirrored below their deepest
+    /// common directory. The suffix defaults to `.html` for `--html` pages,
+    /// else `.unxml`. Fails when an output would overwrite an input: with
+    /// an empty suffix, an out dir inside the inputs' tree, or an `--html`
+    /// index or stylesheet at the root that is itself an input.
+    pub(crate) fn new(
+        files: &[String],
+        out_dir: Option<&str>,
+        suffix: Option<&str>,
+        html: bool,
+    ) -> Result<Self> {
+        let default = if html { ".html" } else { ".unxml" };
+        let suffix = suffix.unwrap_or(default);
+        anyhow::ensure!(!suffix.is_empty(), "--suffix must not be empty");
+        let layout = Self {
+            out_dir: out_dir.map(PathBuf::from),
+            suffix: suffix.to_string(),
+            base: common_dir(files),
+        };
+        let shared = ["index.html", "unxml.css"]
+            .into_iter()
+            .filter(|_| html)
+            .map(|name| (name, layout.root().join(name)));
+        refuse_overwriting_inputs(
+            files,
+            files
+                .iter()
+                .map(|file| (file.as_str(), layout.target(file)))
+                .chain(shared),
+        )?;
+        Ok(layout)
+    }
+
+    /// Mirror the tree under `base` into `out_dir` (`unxml site`).
+    pub(crate) fn mirror(base: &Path, out_dir: &Path, suffix: &str) -> Self {
+        Self {
+            out_dir: Some(out_dir.to_path_buf()),
+            suffix: suffix.to_string(),
+            base: absolute(base),
+        }
+    }
+
+    /// `a/b.xsl` → `OUT/a/b.xsl.unxml` (relative to the common base), or
+    /// `a/b.xsl.unxml` without an out dir.
+    pub(crate) fn target(&self, input: &str) -> PathBuf {
+        self.target_with(input, &self.suffix)
+    }
+
+    /// Like `target`, with another suffix: a site's raw and rendered pages
+    /// sit beside its viewer page.
+    pub(crate) fn target_with(&self, input: &str, suffix: &str) -> PathBuf {
+        let name = format!("{input}{suffix}");
+        match &self.out_dir {
+            None => PathBuf::from(name),
+            Some(dir) => {
+                let absolute = absolute(Path::new(&name));
+                dir.join(absolute.strip_prefix(&self.base).unwrap_or(&absolute))
+            }
+        }
+    }
+
+    /// The root of the written tree: the out dir, else the inputs' base.
+    pub(crate) fn root(&self) -> &Path {
+        self.out_dir.as_deref().unwrap_or(&self.base)
+    }
+}
+
+pub(crate) fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// The deepest directory that contains every one of `files`.
+fn common_dir(files: &[String]) -> PathBuf {
+    let mut dirs = files.iter().map(|file| {
+        let file = absolute(Path::new(file));
+        file.parent().map(Path::to_path_buf).unwrap_or(file)
+    });
+    let Some(mut common) = dirs.next() else {
+        return PathBuf::from(".");
+    };
+    for dir in dirs {
+        while !dir.starts_with(&common) {
+            if !common.pop() {
+                break;
+            }
+        }
+    }
+    common
+}
+
+/// A text's size, for the index's comparisons.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Size {
+    pub(crate) lines: usize,
+    pub(crate) bytes: usize,
+}
+
+impl Size {
+    pub(crate) fn of(text: &str) -> Self {
+        Self {
+            lines: text.lines().count(),
+            bytes: text.len(),
+        }
+    }
+}
+
+impl std::ops::Add for Size {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            lines: self.lines + other.lines,
+            bytes: self.bytes + other.bytes,
+        }
+    }
+}
+
+/// One written output, for the index.
+pub(crate) struct Written {
+    /// The name listed in the index.
+    pub(crate) input: String,
+    /// The page the index links to.
+    pub(crate) output: PathBuf,
+    pub(crate) original: Size,
+    pub(crate) rendered: Size,
+}
+
+/// Render each file with `render` and write it where `layout` says, as an
+/// `--html` page when `html`. A file that fails is reported and skipped. With
+/// `html` the pages share a root `unxml.css` (unless `embed_css`), and a root
+/// `index.html` lists them.
+pub(crate) fn write_all(
+    files: &[String],
+    layout: &Layout,
+    html: bool,
+    embed_css: bool,
+    render: impl Fn(&str) -> Result<String>,
+) -> Result<Vec<Written>> {
+    let mut written = Vec::new();
+    for input in files {
+        let result = read_file_lenient(input).and_then(|source| {
+            let rendered = render(input)?;
+            let output = layout.target(input);
+            let text = if html {
+                let href = relative_href(&output, &layout.root().join("unxml.css"), layout.root());
+                let css = if embed_css {
+                    Css::Embed
+                } else {
+                    Css::Link(&href)
+                };
+                highlight::html_page(&rendered, &css)?
+            } else {
+                rendered.clone()
+            };
+            write_file(&output, &text)?;
+            Ok(Written {
+                input: input.clone(),
+                output,
+                original: Size::of(&source),
+                rendered: Size::of(&rendered),
+            })
+        });
+        match result {
+            Ok(entry) => written.push(entry),
+            Err(e) => eprintln!("Error processing file '{input}': {e}"),
+        }
+    }
+
+    if html && !written.is_empty() {
+        if !embed_css {
+            write_file(&layout.root().join("unxml.css"), &html_css()?)?;
+        }
+        write_file(
+            &layout.root().join("index.html"),
+            &index_page(&written, layout.root()),
+        )?;
+    }
+    Ok(written)
+}
+
+/// Fail, before anything is written, when one of `outputs` (each paired with
+/// the input it is rendered from) is one of the `inputs`.
+pub(crate) fn refuse_overwriting_inputs<'a>(
+    inputs: &[String],
+    outputs: impl IntoIterator<Item = (&'a str, PathBuf)>,
+) -> Result<()> {
+    let key = |path: &Path| std::fs::canonicalize(path).unwrap_or_else(|_| absolute(path));
+    let keys: Vec<PathBuf> = inputs.iter().map(|input| key(Path::new(input))).collect();
+    for (from, output) in outputs {
+        if let Some(i) = keys.iter().position(|input| *input == key(&output)) {
+            anyhow::bail!(
+                "The output for '{from}' would overwrite the input '{}'",
+                inputs[i]
+            );
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn write_file(path: &Path, text: &str) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    std::fs::write(path, text).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// The URL of `target` from the page at `from`, both under `root`:
+/// `../../unxml.css` for a page two directories down.
+pub(crate) fn relative_href(from: &Path, target: &Path, root: &Path) -> String {
+    let from = absolute(from);
+    let target = absolute(target);
+    let root = absolute(root);
+    let depth = from
+        .parent()
+        .and_then(|dir| dir.strip_prefix(&root).ok())
+        .map_or(0, |dir| {
+            dir.components()
+                .filter(|c| matches!(c, Component::Normal(_)))
+                .count()
+        });
+    let below = target.strip_prefix(&root).unwrap_or(&target);
+    let mut href = "../".repeat(depth);
+    href.push_str(&below.to_string_lossy().replace('\\', "/"));
+    href
+}
+
+/// The rendering's size as a share of the source's: `38%`.
+pub(crate) fn ratio(rendered: usize, original: usize) -> String {
+    if original == 0 {
+        return "–".to_string();
+    }
+    format!("{:.0}%", rendered as f64 * 100.0 / original as f64)
+}
+
+/// Chrome for the index table, matching the pages' dark theme.
+const INDEX_CSS: &str = "
+body { margin: 0; background: #0d1117; color: #c9d1d9;
+  font: 14px/1.5 ui-sans-serif, system-ui, sans-serif; }
+main { padding: 1.5rem; }
+h1 { font-size: 1.25rem; margin: 0 0 1rem; }
+table { border-collapse: collapse; }
+th, td { padding: 0.25rem 1rem 0.25rem 0; text-align: right; }
+th[rowspan], td:first-child { text-align: left; }
+th { border-bottom: 1px solid #30363d; text-align: center; }
+a { color: #58a6ff; text-decoration: none; }
+a:hover { text-decoration: underline; }
+td.total { border-top: 1px solid #30363d; }
+";
+
+/// One index row's size cells: lines, then bytes, each as source, rendered
+/// and the rendering's share.
+fn size_cells(original: Size, rendered: Size, class: &str) -> String {
+    [
+        original.lines.to_string(),
+        rendered.lines.to_string(),
+        ratio(rendered.lines, original.lines),
+        original.bytes.to_string(),
+        rendered.bytes.to_string(),
+        ratio(rendered.bytes, original.bytes),
+    ]
+    .iter()
+    .map(|cell| format!("<td{class}>{cell}</td>"))
+    .collect()
+}
+
+/// The index of written pages at `root/index.html`, with source and
+/// rendered sizes.
+pub(crate) fn index_page(written: &[Written], root: &Path) -> String {
+    let index = root.join("index.html");
+    let mut rows = String::new();
+    for entry in written {
+        rows.push_str(&format!(
+            "<tr><td><a href=\"{}\">{}</a></td>{}</tr>\n",
+            escape_attr(&relative_href(&index, &entry.output, root)),
+            escape_attr(&entry.input),
+            size_cells(entry.original, entry.rendered, ""),
+        ));
+    }
+    let original = written
+        .iter()
+        .fold(Size::default(), |sum, e| sum + e.original);
+    let rendered = written
+        .iter()
+        .fold(Size::default(), |sum, e| sum + e.rendered);
+    let totals = size_cells(original, rendered, " class=\"total\"");
+    format!(
+        "<!doctype html>\n\
+         <html lang=\"en\">\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>unxml</title>\n\
+         <style>{INDEX_CSS}</style>\n\
+         </head>\n\
+         <body>\n\
+         <main>\n\
+         <h1>{} rendered files</h1>\n\
+         <table>\n\
+         <tr><th rowspan=\"2\">File</th><th colspan=\"3\">Lines</th><th colspan=\"3\">Bytes</th></tr>\n\
+         <tr><th>Source</th><th>Rendered</th><th>Size</th><th>Source</th><th>Rendered</th><th>Size</th></tr>\n\
+         {rows}\
+         <tr><td class=\"total\">Total</td>{totals}</tr>\n\
+         </table>\n\
+         </main>\n\
+         </body>\n\
+         </html>\n",
+        written.len(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{Layout, ratio, relative_href};
+
+    #[test]
+    fn out_dir_mirrors_the_inputs_below_their_common_directory() {
+        let files = ["src/a/x.xsl".to_string(), "src/b/c/y.xsd".to_string()];
+        let layout = Layout::new(&files, Some("out"), None, false).unwrap();
+        assert_eq!(layout.target("src/a/x.xsl"), Path::new("out/a/x.xsl.unxml"));
+        assert_eq!(
+            layout.target("src/b/c/y.xsd"),
+            Path::new("out/b/c/y.xsd.unxml")
+        );
+
+        let beside = Layout::new(&files, None, Some(".txt"), true).unwrap();
+        assert_eq!(beside.target("src/a/x.xsl"), Path::new("src/a/x.xsl.txt"));
+
+        assert!(Layout::new(&files, None, Some(""), false).is_err());
+
+        // An `--html` index at the root would replace an input page.
+        let pages = ["site/index.html".to_string(), "site/a/x.html".to_string()];
+        assert!(Layout::new(&pages, None, Some(".out.html"), true).is_err());
+        assert!(Layout::new(&pages, None, Some(".out"), false).is_ok());
+    }
+
+    #[test]
+    fn links_climb_to_the_root() {
+        let root = Path::new("/out");
+        let css = root.join("unxml.css");
+        assert_eq!(
+            relative_href(Path::new("/out/a/b/x.html"), &css, root),
+            "../../unxml.css"
+        );
+        assert_eq!(
+            relative_href(
+                Path::new("/out/index.html"),
+                Path::new("/out/a/x.html"),
+                root
+            ),
+            "a/x.html"
+        );
+        assert_eq!(ratio(38, 100), "38%");
+    }
+}
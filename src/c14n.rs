@@ -0,0 +1,462 @@
+//! W3C Canonical XML output (`--c14n`, `--exc-c14n`).
+//!
+//! `--canonical` normalises the *unxml rendering* for diffs; this module emits
+//! real canonical XML bytes, the form XML-DSig digests are computed over:
+//!
+//!  - `--c14n` is Canonical XML 1.1 (`http://www.w3.org/2006/12/xml-c14n11`).
+//!  - `--exc-c14n` is Exclusive XML Canonicalization 1.0
+//!    (`http://www.w3.org/2001/10/xml-exc-c14n#`), which renders only the
+//!    namespaces an element visibly uses, so a subtree canonicalises the same
+//!    wherever it is embedded.
+//!
+//! Both omit comments unless `--with-comments` selects the `#WithComments`
+//! variant. `--select` canonicalises the matching subtrees (a document
+//! subset) instead of the whole document; under `--c14n` they carry every
+//! in-scope namespace and the inherited `xml:lang` / `xml:space`.
+//!
+//! Canonical form depends on whitespace, CDATA, processing instructions and
+//! the exact namespace scope, all of which the rendering parser in `parse`
+//! trims or discards. The document is therefore re-read here into a small
+//! lossless tree. DTDs are not processed: default attributes and entities
+//! they declare are not expanded, and `xml:base` is inherited as is rather
+//! than fixed up.
+
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+use quick_xml::Reader;
+use quick_xml::escape::unescape;
+use quick_xml::events::Event;
+
+use crate::document::name_matches_select;
+
+const XML_NS: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// Which canonicalisation algorithm to apply.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Method {
+    /// Canonical XML 1.1.
+    Inclusive,
+    /// Exclusive XML Canonicalization 1.0.
+    Exclusive,
+}
+
+enum Node {
+    Element(Element),
+    Text(String),
+    Comment(String),
+    Pi(String),
+}
+
+struct Element {
+    name: String,
+    /// Attributes in source order, namespace declarations included.
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+/// Namespace bindings by prefix (`""` for the default namespace, bound to
+/// `""` when undeclared).
+type Scope = BTreeMap<String, String>;
+
+fn prefix_of(qname: &str) -> &str {
+    qname.split_once(':').map_or("", |(prefix, _)| prefix)
+}
+
+fn local_of(qname: &str) -> &str {
+    qname.split_once(':').map_or(qname, |(_, local)| local)
+}
+
+/// The prefix an attribute declares, if it is a namespace declaration.
+fn declared_prefix(name: &str) -> Option<&str> {
+    match name {
+        "xmlns" => Some(""),
+        _ => name.strip_prefix("xmlns:"),
+    }
+}
+
+/// Attribute-value normalisation (XML 1.0 §3.3.3) for CDATA attributes:
+/// literal whitespace characters become spaces before references expand, so
+/// `&#xA;` survives as a newline but a wrapped value does not.
+fn attribute_value(raw: &[u8]) -> Result<String> {
+    let raw = String::from_utf8_lossy(raw).replace(['\t', '\n'], " ");
+    Ok(unescape(&raw)
+        .context("Failed to unescape attribute value")?
+        .into_owned())
+}
+
+fn read_element(e: &quick_xml::events::BytesStart) -> Result<Element> {
+    let mut attributes = Vec::new();
+    for attr in e.attributes() {
+        let attr = attr.context("Failed to parse attribute")?;
+        let key = String::from_utf8_lossy(attr.key.as_ref()).to_string();
+        attributes.push((key, attribute_value(&attr.value)?));
+    }
+    Ok(Element {
+        name: String::from_utf8_lossy(e.name().as_ref()).to_string(),
+        attributes,
+        children: Vec::new(),
+    })
+}
+
+/// Parse a document into its top-level nodes without losing whitespace,
+/// CDATA sections or processing instructions.
+fn parse(content: &str) -> Result<Vec<Node>> {
+    // Line-end normalisation (XML 1.0 §2.11) happens before parsing proper.
+    let content = content.replace("\r\n", "\n").replace('\r', "\n");
+    let mut reader = Reader::from_str(&content);
+    let mut top = Vec::new();
+    let mut stack: Vec<Element> = Vec::new();
+    let push = |stack: &mut Vec<Element>, top: &mut Vec<Node>, node: Node| match stack.last_mut() {
+        Some(parent) => parent.children.push(node),
+        // Whitespace outside the document element is not part of the model.
+        None if matches!(node, Node::Text(_)) => {}
+        None => top.push(node),
+    };
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(ref e)) => stack.push(read_element(e)?),
+            Ok(Event::Empty(ref e)) => {
+                let element = read_element(e)?;
+                push(&mut stack, &mut top, Node::Element(element));
+            }
+            Ok(Event::End(_)) => {
+                if let Some(element) = stack.pop() {
+                    push(&mut stack, &mut top, Node::Element(element));
+                }
+            }
+            Ok(Event::Text(ref e)) => {
+                let text = e.unescape().context("Failed to unescape text")?;
+                push(&mut stack, &mut top, Node::Text(text.into_owned()));
+            }
+            Ok(Event::CData(ref e)) => {
+                let text = String::from_utf8_lossy(e.as_ref()).into_owned();
+                push(&mut stack, &mut top, Node::Text(text));
+            }
+            Ok(Event::Comment(ref e)) => {
+                let text = String::from_utf8_lossy(e.as_ref()).into_owned();
+                push(&mut stack, &mut top, Node::Comment(text));
+            }
+            Ok(Event::PI(ref e)) => {
+                let text = String::from_utf8_lossy(e.as_ref()).into_owned();
+                push(&mut stack, &mut top, Node::Pi(text));
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => {
+                return Err(anyhow::anyhow!(
+                    "Error at position {}: {:?}",
+                    reader.error_position(),
+                    e
+                ));
+            }
+            _ => {} // XML declaration and DOCTYPE are not part of canonical form
+        }
+    }
+    Ok(top)
+}
+
+fn escape_text(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '\r' => out.push_str("&#xD;"),
+            c => out.push(c),
+        }
+    }
+}
+
+fn escape_attribute(value: &str, out: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '"' => out.push_str("&quot;"),
+            '\t' => out.push_str("&#x9;"),
+            '\n' => out.push_str("&#xA;"),
+            '\r' => out.push_str("&#xD;"),
+            c => out.push(c),
+        }
+    }
+}
+
+fn write_pi(text: &str, out: &mut String) {
+    out.push_str("<?");
+    match text.split_once(char::is_whitespace) {
+        Some((target, data)) if !data.trim_start().is_empty() => {
+            out.push_str(target);
+            out.push(' ');
+            out.push_str(data.trim_start());
+        }
+        _ => out.push_str(text.trim_end()),
+    }
+    out.push_str("?>");
+}
+
+struct Canonicalizer {
+    method: Method,
+    with_comments: bool,
+}
+
+impl Canonicalizer {
+    /// Write one element. `scope` holds the bindings in scope at its parent,
+    /// `rendered` those the nearest output ancestor rendered. `inherited` are
+    /// `xml:*` attributes a document-subset apex picks up from its ancestors.
+    fn element(
+        &self,
+        elem: &Element,
+        scope: &Scope,
+        rendered: &Scope,
+        inherited: &[(String, String)],
+        out: &mut String,
+    ) {
+        let mut scope = scope.clone();
+        for (name, value) in &elem.attributes {
+            if let Some(prefix) = declared_prefix(name) {
+                scope.insert(prefix.to_string(), value.clone());
+            }
+        }
+
+        // The namespace declarations to render, by prefix.
+        let candidates: Vec<&str> = match self.method {
+            Method::Inclusive => scope.keys().map(String::as_str).collect(),
+            Method::Exclusive => {
+                let mut used = vec![prefix_of(&elem.name)];
+                for (name, _) in &elem.attributes {
+                    if declared_prefix(name).is_none() && name.contains(':') {
+                        used.push(prefix_of(name));
+                    }
+                }
+                used.sort_unstable();
+                used.dedup();
+                used
+            }
+        };
+        let mut rendered = rendered.clone();
+        let mut namespaces = Vec::new();
+        for prefix in candidates {
+            if prefix == "xml" {
+                continue;
+            }
+            let uri = scope.get(prefix).map_or("", String::as_str);
+            let output = rendered.get(prefix).map_or("", String::as_str);
+            // An empty default is only worth an `xmlns=""` when an output
+            // ancestor rendered a non-empty one; an unbound prefix never renders.
+            if uri != output && (!uri.is_empty() || prefix.is_empty()) {
+                namespaces.push((prefix.to_string(), uri.to_string()));
+                rendered.insert(prefix.to_string(), uri.to_string());
+            }
+        }
+
+        let mut attributes: Vec<(&str, &str, &str, &str)> = elem
+            .attributes
+            .iter()
+            .chain(
+                inherited
+                    .iter()
+                    .filter(|(name, _)| !elem.attributes.iter().any(|(own, _)| own == name)),
+            )
+            .filter(|(name, _)| declared_prefix(name).is_none())
+            .map(|(name, value)| {
+                let uri = match prefix_of(name) {
+                    "" => "",
+                    "xml" => XML_NS,
+                    prefix => scope.get(prefix).map_or("", String::as_str),
+                };
+                (uri, local_of(name), name.as_str(), value.as_str())
+            })
+            .collect();
+        attributes.sort_unstable_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+
+        out.push('<');
+        out.push_str(&elem.name);
+        for (prefix, uri) in &namespaces {
+            out.push_str(" xmlns");
+            if !prefix.is_empty() {
+                out.push(':');
+                out.push_str(prefix);
+            }
+            out.push_str("=\"");
+            escape_attribute(uri, out);
+            out.push('"');
+        }
+        for (_, _, name, value) in attributes {
+            out.push(' ');
+            out.push_str(name);
+            out.push_str("=\"");
+            escape_attribute(value, out);
+            out.push('"');
+        }
+        out.push('>');
+        for child in &elem.children {
+            match child {
+                Node::Element(e) => self.element(e, &scope, &rendered, &[], out),
+                Node::Text(text) => escape_text(text, out),
+                Node::Comment(text) if self.with_comments => {
+                    out.push_str("<!--");
+                    out.push_str(text);
+                    out.push_str("-->");
+                }
+                Node::Comment(_) => {}
+                Node::Pi(text) => write_pi(text, out),
+            }
+        }
+        out.push_str("</");
+        out.push_str(&elem.name);
+        out.push('>');
+    }
+
+    /// The whole document: top-level comments and PIs are separated from the
+    /// document element by a newline on the element's side.
+    fn document(&self, nodes: &[Node], out: &mut String) {
+        let mut after_root = false;
+        for node in nodes {
+            match node {
+                Node::Element(e) => {
+                    self.element(e, &Scope::new(), &Scope::new(), &[], out);
+                    after_root = true;
+                    continue;
+                }
+                Node::Comment(_) if !self.with_comments => continue,
+                _ => {}
+            }
+            if after_root {
+                out.push('\n');
+            }
+            match node {
+                Node::Comment(text) => {
+                    out.push_str("<!--");
+                    out.push_str(text);
+                    out.push_str("-->");
+                }
+                Node::Pi(text) => write_pi(text, out),
+                _ => {}
+            }
+            if !after_root {
+                out.push('\n');
+            }
+        }
+    }
+
+    /// Each topmost element matching `pattern`, as a document subset apex.
+    fn select(
+        &self,
+        elem: &Element,
+        pattern: &str,
+        scope: &Scope,
+        xml_attrs: &[(String, String)],
+        out: &mut String,
+    ) {
+        if name_matches_select(&elem.name, pattern) {
+            let inherited = match self.method {
+                Method::Inclusive => xml_attrs,
+                Method::Exclusive => &[],
+            };
+            self.element(elem, scope, &Scope::new(), inherited, out);
+            return;
+        }
+        let mut scope = scope.clone();
+        let mut xml_attrs = xml_attrs.to_vec();
+        for (name, value) in &elem.attributes {
+            if let Some(prefix) = declared_prefix(name) {
+                scope.insert(prefix.to_string(), value.clone());
+            } else if matches!(name.as_str(), "xml:lang" | "xml:space" | "xml:base") {
+                xml_attrs.retain(|(own, _)| own != name);
+                xml_attrs.push((name.clone(), value.clone()));
+            }
+        }
+        for child in &elem.children {
+            if let Node::Element(e) = child {
+                self.select(e, pattern, &scope, &xml_attrs, out);
+            }
+        }
+    }
+}
+
+/// Canonicalise a document, or with `select` the subtrees whose element name
+/// matches it (like `--select`), concatenated in document order.
+pub(crate) fn canonical_xml(
+    content: &str,
+    method: Method,
+    with_comments: bool,
+    select: Option<&str>,
+) -> Result<String> {
+    let nodes = parse(content)?;
+    let c14n = Canonicalizer {
+        method,
+        with_comments,
+    };
+    let mut out = String::new();
+    match select {
+        None => c14n.document(&nodes, &mut out),
+        Some(pattern) => {
+            for node in &nodes {
+                if let Node::Element(e) = node {
+                    c14n.select(e, pattern, &Scope::new(), &[], &mut out);
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Method, canonical_xml};
+
+    #[test]
+    fn canonicalises_whole_documents() {
+        // Adapted from the C14N spec's examples: declarations, DOCTYPE and
+        // superfluous namespace declarations go, attributes sort by namespace
+        // URI then local name, empty elements get end tags.
+        let input = concat!(
+            "<?xml version=\"1.0\"?>\r\n",
+            "<?xml-stylesheet   href=\"doc.xsl\"\n   type=\"text/xsl\"   ?>\n",
+            "<!DOCTYPE doc>\n",
+            "<!-- head -->\n",
+            "<doc xmlns=\"http://example.org\" xmlns:b=\"http://b\" xmlns:a=\"http://a\">\r\n",
+            "  <e1 b:attr=\"sorted\" a:attr=\"out\" attr2=\"all\" attr=\"I'm\"/>\n",
+            "  <e2 xmlns=\"http://example.org\" xmlns:a=\"http://a\"><![CDATA[x < y]]>&#xD;</e2>\n",
+            "  <e3 v=\"  a\tb &quot;c&quot; \"/><!-- inner -->\n",
+            "</doc>\n",
+            "<?trailer?>",
+        );
+        assert_eq!(
+            canonical_xml(input, Method::Inclusive, false, None).unwrap(),
+            concat!(
+                "<?xml-stylesheet href=\"doc.xsl\"\n   type=\"text/xsl\"   ?>\n",
+                "<doc xmlns=\"http://example.org\" xmlns:a=\"http://a\" xmlns:b=\"http://b\">\n",
+                "  <e1 attr=\"I'm\" attr2=\"all\" a:attr=\"out\" b:attr=\"sorted\"></e1>\n",
+                "  <e2>x &lt; y&#xD;</e2>\n",
+                "  <e3 v=\"  a b &quot;c&quot; \"></e3>\n",
+                "</doc>\n",
+                "<?trailer?>",
+            )
+        );
+        assert!(
+            canonical_xml(input, Method::Inclusive, true, None)
+                .unwrap()
+                .contains("<!-- head -->\n<doc")
+        );
+    }
+
+    #[test]
+    fn exclusive_subsets_render_only_visible_namespaces() {
+        let input = concat!(
+            r#"<inv:Invoice xmlns:inv="urn:inv" xmlns:cbc="urn:cbc" xml:lang="en">"#,
+            r#"<cbc:Note xmlns:x="urn:unused">Hi</cbc:Note></inv:Invoice>"#,
+        );
+        assert_eq!(
+            canonical_xml(input, Method::Exclusive, false, Some("Note")).unwrap(),
+            r#"<cbc:Note xmlns:cbc="urn:cbc">Hi</cbc:Note>"#
+        );
+        assert_eq!(
+            canonical_xml(input, Method::Inclusive, false, Some("Note")).unwrap(),
+            concat!(
+                r#"<cbc:Note xmlns:cbc="urn:cbc" xmlns:inv="urn:inv" xmlns:x="urn:unused" "#,
+                r#"xml:lang="en">Hi</cbc:Note>"#
+            )
+        );
+    }
+}
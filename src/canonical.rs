@@ -18,61 +18,88 @@
 //!     so order-only differences disappear. Mixed content (prose interleaved
 //!     with markup) keeps document order, where sequence is meaning.
 //!
+//! The table of recognised vocabularies can be extended with the user's own
+//! (`--ns-prefix`, or the `namespaces` config file; see `KnownPrefixes`).
+//!
 //! Cross-file stability is exact for recognised vocabularies; for unknown URIs
 //! the `ns<n>` numbering is stable only for a given *set* of URIs (it follows
 //! sorted-URI order), so two files with different URI sets may number a shared
-//! URI differently.
+//! URI differently. Two options make it exact across a set of files (see
+//! `NsPrefixes`): `--ns-map FILE` names URIs explicitly, and `--ns-hash`
+//! derives `ns<hex>` from a short hash of the URI instead of its rank.
 
 use std::collections::{BTreeSet, HashMap};
 
+#[cfg(feature = "cli")]
+use anyhow::Context;
+use anyhow::Result;
+
+use crate::document::name_matches_select;
+use crate::ignore::IgnoreRules;
+#[cfg(feature = "cli")]
+use crate::install::config_dir;
+use crate::mask::{MaskRule, Masker};
 use crate::model::{NodeRef, XmlElement};
+use crate::normalize::{Normalizers, SchemaTypes};
+#[cfg(feature = "cli")]
+use crate::parse::read_file_lenient;
 
 /// The implicit `xml:` namespace. Never declared with `xmlns:` and its prefix
 /// is fixed by the spec, so it passes through rewriting untouched.
 const XML_NS: &str = "http://www.w3.org/XML/1998/namespace";
 
+/// Exact W3C / SOAP / WSDL / Schematron namespaces. Order is irrelevant
+/// because matching is by equality, so e.g. XMLSchema-instance never shadows
+/// XMLSchema.
+const EXACT_PREFIXES: &[(&str, &str)] = &[
+    ("http://www.w3.org/1999/XSL/Transform", "xsl"),
+    ("http://www.w3.org/2001/XMLSchema", "xs"),
+    ("http://www.w3.org/2001/XMLSchema-instance", "xsi"),
+    ("http://www.w3.org/1999/xhtml", "xhtml"),
+    ("http://schemas.xmlsoap.org/wsdl/", "wsdl"),
+    ("http://schemas.xmlsoap.org/wsdl/soap/", "soapbind"),
+    ("http://schemas.xmlsoap.org/wsdl/soap12/", "soap12bind"),
+    ("http://schemas.xmlsoap.org/soap/envelope/", "soap"),
+    ("http://www.w3.org/2003/05/soap-envelope", "soap12"),
+    ("http://purl.oclc.org/dsdl/schematron", "sch"),
+];
+
+/// Substring markers for UBL and UN/CEFACT CII (Factur-X / ZUGFeRD).
+const MARKER_PREFIXES: &[(&str, &str)] = &[
+    ("CommonBasicComponents", "cbc"),
+    ("CommonAggregateComponents", "cac"),
+    ("CommonExtensionComponents", "ext"),
+    ("ReusableAggregateBusinessInformationEntity", "ram"),
+    ("CrossIndustryInvoice", "rsm"),
+    ("CrossIndustryDocument", "rsm"),
+    ("UnqualifiedDataType", "udt"),
+    ("QualifiedDataType", "qdt"),
+];
+
 /// Map a namespace URI to a conventional prefix, or `None` if it is not a
 /// recognised vocabulary. W3C / web-service namespaces match exactly; large
 /// business vocabularies (UBL, CII) match by substring marker — the same
 /// technique the document sniffer uses — so they are robust to version variants.
 pub(crate) fn well_known_prefix(uri: &str) -> Option<&'static str> {
-    // Exact W3C / SOAP / WSDL / Schematron namespaces. Order is irrelevant
-    // because matching is by equality, so e.g. XMLSchema-instance never shadows
-    // XMLSchema.
-    const EXACT: &[(&str, &str)] = &[
-        ("http://www.w3.org/1999/XSL/Transform", "xsl"),
-        ("http://www.w3.org/2001/XMLSchema", "xs"),
-        ("http://www.w3.org/2001/XMLSchema-instance", "xsi"),
-        ("http://www.w3.org/1999/xhtml", "xhtml"),
-        ("http://schemas.xmlsoap.org/wsdl/", "wsdl"),
-        ("http://schemas.xmlsoap.org/wsdl/soap/", "soapbind"),
-        ("http://schemas.xmlsoap.org/wsdl/soap12/", "soap12bind"),
-        ("http://schemas.xmlsoap.org/soap/envelope/", "soap"),
-        ("http://www.w3.org/2003/05/soap-envelope", "soap12"),
-        ("http://purl.oclc.org/dsdl/schematron", "sch"),
-    ];
-    for (u, p) in EXACT {
-        if uri == *u {
-            return Some(p);
-        }
+    if let Some((_, p)) = EXACT_PREFIXES.iter().find(|(u, _)| uri == *u) {
+        return Some(p);
     }
-    // Substring markers for UBL and UN/CEFACT CII (Factur-X / ZUGFeRD).
-    const MARKERS: &[(&str, &str)] = &[
-        ("CommonBasicComponents", "cbc"),
-        ("CommonAggregateComponents", "cac"),
-        ("CommonExtensionComponents", "ext"),
-        ("ReusableAggregateBusinessInformationEntity", "ram"),
-        ("CrossIndustryInvoice", "rsm"),
-        ("CrossIndustryDocument", "rsm"),
-        ("UnqualifiedDataType", "udt"),
-        ("QualifiedDataType", "qdt"),
-    ];
-    for (m, p) in MARKERS {
-        if uri.contains(m) {
-            return Some(p);
-        }
+    MARKER_PREFIXES
+        .iter()
+        .find(|(m, _)| uri.contains(m))
+        .map(|(_, p)| *p)
+}
+
+/// What the built-in table gives `prefix` to, if anything.
+#[cfg(feature = "cli")]
+fn built_in_use(prefix: &str) -> Option<String> {
+    if let Some((u, _)) = EXACT_PREFIXES.iter().find(|(_, p)| *p == prefix) {
+        return Some(u.to_string());
     }
-    None
+    MARKER_PREFIXES
+        .iter()
+        .find(|(_, p)| *p == prefix)
+        .map(|(m, _)| format!("namespaces containing '{m}'"))
 }
 
 /// Gather every namespace URI declared anywhere in the tree.
@@ -87,11 +114,184 @@ fn collect_uris(elem: &XmlElement, out: &mut BTreeSet<String>) {
     }
 }
 
+/// Parse one line of a namespace file: `URI=PREFIX`, optionally followed by
+/// `hide`. `None` for blank lines and `#` comments.
+#[cfg(feature = "cli")]
+fn parse_ns_line(line: &str) -> Result<Option<(&str, &str, bool)>> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    // A URI may itself contain `=`; a prefix never does.
+    let Some((uri, rest)) = line.rsplit_once('=') else {
+        return Err(anyhow::anyhow!("expected URI=PREFIX, got '{line}'"));
+    };
+    let mut words = rest.split_whitespace();
+    let (uri, prefix) = (uri.trim(), words.next().unwrap_or_default());
+    let hide = match words.next() {
+        None => false,
+        Some("hide") if words.next().is_none() => true,
+        Some(_) => return Err(anyhow::anyhow!("expected URI=PREFIX [hide], got '{line}'")),
+    };
+    if uri.is_empty() || !is_ncname(prefix) || prefix.starts_with("xml") {
+        return Err(anyhow::anyhow!("invalid mapping '{line}'"));
+    }
+    Ok(Some((uri, prefix, hide)))
+}
+
+/// The well-known prefix table extended with the user's own vocabularies:
+/// the `namespaces` file in the unxml config directory, then `--ns-prefix`
+/// flags, each a `URI=PREFIX [hide]` line. User entries win over the
+/// built-in table and, like it, are consulted by `--canonical`, the `--paths`
+/// legend and namespace sniffing; `hide` adds the prefix to the ones
+/// `--auto`/`--bat` hide.
+#[derive(Debug, Default, Clone)]
+pub struct KnownPrefixes {
+    prefixes: HashMap<String, String>,
+    hidden: BTreeSet<String>,
+}
+
+impl KnownPrefixes {
+    /// Read the config file, if there is one, then apply `--ns-prefix` specs.
+    #[cfg(feature = "cli")]
+    pub fn load(specs: &[String]) -> Result<Self> {
+        let mut known = Self::default();
+        if let Some(path) = config_dir().map(|dir| dir.join("namespaces"))
+            && path.is_file()
+        {
+            let path = path.display().to_string();
+            let content = read_file_lenient(&path)?;
+            for (n, line) in content.lines().enumerate() {
+                known
+                    .add(line)
+                    .with_context(|| format!("In {path}, line {}", n + 1))?;
+            }
+        }
+        for spec in specs {
+            known
+                .add(spec)
+                .with_context(|| format!("Invalid --ns-prefix '{spec}'"))?;
+        }
+        Ok(known)
+    }
+
+    /// Add one entry; a later entry for a URI, or for a prefix, replaces the
+    /// earlier one. A built-in prefix can't be given to another URI: two
+    /// vocabularies would then render under one prefix.
+    #[cfg(feature = "cli")]
+    fn add(&mut self, line: &str) -> Result<()> {
+        if let Some((uri, prefix, hide)) = parse_ns_line(line)? {
+            if let Some(owner) = built_in_use(prefix)
+                && well_known_prefix(uri) != Some(prefix)
+            {
+                return Err(anyhow::anyhow!("prefix '{prefix}' is built in for {owner}"));
+            }
+            self.prefixes.retain(|_, p| p != prefix);
+            self.prefixes.insert(uri.to_string(), prefix.to_string());
+            if hide {
+                self.hidden.insert(uri.to_string());
+            } else {
+                self.hidden.remove(uri);
+            }
+        }
+        Ok(())
+    }
+
+    /// The conventional prefix for `uri`: a user entry, else the built-in one.
+    pub(crate) fn prefix(&self, uri: &str) -> Option<&str> {
+        self.prefixes
+            .get(uri)
+            .map(String::as_str)
+            .or_else(|| well_known_prefix(uri))
+    }
+
+    /// Whether `uri` is one of the user's own vocabularies.
+    pub(crate) fn declares(&self, uri: &str) -> bool {
+        self.prefixes.contains_key(uri)
+    }
+
+    /// Whether `uri` was declared with `hide`.
+    pub(crate) fn hides(&self, uri: &str) -> bool {
+        self.hidden.contains(uri)
+    }
+}
+
+/// How URIs are named beyond the well-known vocabularies: `--ns-map` pairs
+/// first, then `ns<hex>` hashes (`--ns-hash`) or `ns<n>` numbering.
+#[derive(Debug, Default)]
+pub(crate) struct NsPrefixes {
+    /// The conventional prefixes, user vocabularies included.
+    pub(crate) known: KnownPrefixes,
+    map: HashMap<String, String>,
+    hash: bool,
+}
+
+impl NsPrefixes {
+    /// Build from `--ns-map FILE` and `--ns-hash`. The file holds one
+    /// `URI=PREFIX` pair per line; blank lines and `#` comments are skipped.
+    #[cfg(feature = "cli")]
+    pub(crate) fn new(known: KnownPrefixes, map_file: Option<&str>, hash: bool) -> Result<Self> {
+        let mut prefixes = Self {
+            known,
+            map: HashMap::new(),
+            hash,
+        };
+        if let Some(path) = map_file {
+            let content = read_file_lenient(path)?;
+            prefixes
+                .parse_map(&content)
+                .with_context(|| format!("In namespace map {path}"))?;
+        }
+        Ok(prefixes)
+    }
+
+    #[cfg(feature = "cli")]
+    fn parse_map(&mut self, content: &str) -> Result<()> {
+        let mut claimed: HashMap<String, String> = HashMap::new();
+        for (n, line) in content.lines().enumerate() {
+            let Some((uri, prefix, _)) =
+                parse_ns_line(line).with_context(|| format!("line {}", n + 1))?
+            else {
+                continue;
+            };
+            if let Some(other) = claimed.insert(prefix.to_string(), uri.to_string())
+                && other != uri
+            {
+                return Err(anyhow::anyhow!(
+                    "line {}: prefix '{prefix}' already names {other}",
+                    n + 1
+                ));
+            }
+            self.map.insert(uri.to_string(), prefix.to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Whether `s` can serve as a namespace prefix.
+#[cfg(feature = "cli")]
+fn is_ncname(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+/// 32-bit FNV-1a: tiny, and fixed across platforms and releases, unlike
+/// `std`'s hasher.
+fn fnv1a(s: &str) -> u32 {
+    s.bytes().fold(0x811c_9dc5, |h, b| {
+        (h ^ u32::from(b)).wrapping_mul(0x0100_0193)
+    })
+}
+
 /// Build the stable URI -> canonical-prefix map for the whole document.
-/// Recognised vocabularies take their conventional prefix; the remainder are
-/// numbered `ns1`, `ns2`, … in sorted-URI order, skipping any number whose name
-/// a well-known prefix already claimed.
-fn build_prefix_map(roots: &[XmlElement]) -> HashMap<String, String> {
+/// `--ns-map` pairs come first, then recognised vocabularies take their
+/// conventional prefix unless it is already taken (by the map, or by another
+/// URI of the same vocabulary family, e.g. two UBL versions). The remainder
+/// are numbered `ns1`, `ns2`, … in sorted-URI order, skipping any number
+/// whose name is taken, or with `--ns-hash` named `ns` plus four hex digits
+/// of the URI's hash, widened to eight on a clash within the document.
+fn build_prefix_map(roots: &[XmlElement], ns: &NsPrefixes) -> HashMap<String, String> {
     let mut uris = BTreeSet::new();
     for root in roots {
         collect_uris(root, &mut uris);
@@ -100,7 +300,16 @@ fn build_prefix_map(roots: &[XmlElement]) -> HashMap<String, String> {
     let mut map = HashMap::new();
     let mut used = BTreeSet::new();
     for uri in &uris {
-        if let Some(prefix) = well_known_prefix(uri) {
+        if let Some(prefix) = ns.map.get(uri) {
+            map.insert(uri.clone(), prefix.clone());
+            used.insert(prefix.clone());
+        }
+    }
+    for uri in &uris {
+        if let Some(prefix) = ns.known.prefix(uri)
+            && !map.contains_key(uri)
+            && !used.contains(prefix)
+        {
             map.insert(uri.clone(), prefix.to_string());
             used.insert(prefix.to_string());
         }
@@ -110,14 +319,28 @@ fn build_prefix_map(roots: &[XmlElement]) -> HashMap<String, String> {
         if map.contains_key(uri) {
             continue;
         }
-        let mut candidate = format!("ns{n}");
-        while used.contains(&candidate) {
+        let candidate = if ns.hash {
+            let hash = format!("{:08x}", fnv1a(uri));
+            let mut candidate = format!("ns{}", &hash[..4]);
+            if used.contains(&candidate) {
+                candidate = format!("ns{hash}");
+            }
+            while used.contains(&candidate) {
+                candidate = format!("ns{hash}_{n}");
+                n += 1;
+            }
+            candidate
+        } else {
+            let mut candidate = format!("ns{n}");
+            while used.contains(&candidate) {
+                n += 1;
+                candidate = format!("ns{n}");
+            }
             n += 1;
-            candidate = format!("ns{n}");
-        }
+            candidate
+        };
         used.insert(candidate.clone());
         map.insert(uri.clone(), candidate);
-        n += 1;
     }
     map
 }
@@ -208,7 +431,7 @@ fn emit_decls(roots: &mut [XmlElement], uri2pfx: &HashMap<String, String>) {
 /// A stable, order-independent signature of a subtree: name, sorted attributes,
 /// trimmed text, then child signatures. Two structurally identical subtrees
 /// produce equal signatures regardless of their original sibling position.
-fn signature(elem: &XmlElement) -> String {
+pub(crate) fn signature(elem: &XmlElement) -> String {
     let mut attrs: Vec<String> = elem
         .attributes
         .iter()
@@ -238,12 +461,146 @@ fn signature(elem: &XmlElement) -> String {
     )
 }
 
+/// A `--key ELEM=KEY` rule: siblings named `ELEM` are identified by `KEY`, an
+/// attribute (`@id`) or the text of a child element (`cbc:ID`), so sorting and
+/// `unxml diff` keep an edited record in place instead of treating it as a
+/// different one. Both names match like `--select` (bare = local name). For
+/// JSON, `ELEM` names an array and `KEY` a member of its objects.
+#[derive(Debug, Clone)]
+pub(crate) struct KeyRule {
+    pub(crate) element: String,
+    pub(crate) key: String,
+}
+
+impl KeyRule {
+    /// Parse an `ELEM=KEY` spec.
+    pub(crate) fn parse(spec: &str) -> Result<Self> {
+        match spec.split_once('=') {
+            Some((element, key)) if !element.is_empty() && !key.is_empty() && key != "@" => {
+                Ok(Self {
+                    element: element.to_string(),
+                    key: key.to_string(),
+                })
+            }
+            _ => Err(anyhow::anyhow!(
+                "Invalid --key '{spec}': expected ELEM=@attr or ELEM=child"
+            )),
+        }
+    }
+
+    /// Whether this rule applies to an element (or JSON array) named `name`.
+    /// JSON array items are `name[]` elements in the diff tree, so the suffix
+    /// is ignored.
+    pub(crate) fn applies_to(&self, name: &str) -> bool {
+        name_matches_select(name.trim_end_matches("[]"), &self.element)
+    }
+
+    /// The key's value on `elem`, if the rule applies and the key is present.
+    pub(crate) fn value_of(&self, elem: &XmlElement) -> Option<String> {
+        if !self.applies_to(&elem.name) {
+            return None;
+        }
+        match self.key.strip_prefix('@') {
+            Some(attr) => elem
+                .attributes
+                .iter()
+                .find(|(name, _)| name_matches_select(name, attr))
+                .map(|(_, value)| value.clone()),
+            None => elem
+                .children
+                .iter()
+                .find(|child| name_matches_select(&child.name, &self.key))
+                .map(|child| child.text_content.trim().to_string()),
+        }
+    }
+}
+
+/// The first `--key` rule that identifies `elem`, with the key's value.
+pub(crate) fn rule_key<'a>(
+    elem: &XmlElement,
+    keys: &'a [KeyRule],
+) -> Option<(&'a KeyRule, String)> {
+    keys.iter()
+        .find_map(|rule| rule.value_of(elem).map(|value| (rule, value)))
+}
+
+/// The `--canonical` refinements shared by the XML and JSON passes and by
+/// `unxml diff`.
+#[derive(Debug, Default)]
+pub struct CanonicalOptions {
+    /// `--ignore-attr` / `--ignore-elem` noise, stripped before anything else.
+    pub(crate) ignore: IgnoreRules,
+    /// `--key` identity rules for sorting and matching siblings.
+    pub(crate) keys: Vec<KeyRule>,
+    /// `--mask` rules for volatile values, applied next.
+    pub(crate) masks: Vec<MaskRule>,
+    /// `--normalize` value rewriting, applied after masking.
+    pub(crate) normalize: Normalizers,
+    /// `--ns-map` / `--ns-hash` naming of namespaces without a conventional
+    /// prefix.
+    pub(crate) ns: NsPrefixes,
+}
+
+impl CanonicalOptions {
+    /// Build from the raw `--key`, `--mask`, `--normalize` and `--schema`
+    /// values.
+    pub fn parse(
+        keys: &[String],
+        masks: &[String],
+        normalize: &[String],
+        schema: Option<&str>,
+    ) -> Result<Self> {
+        let mut options = Self::default();
+        for spec in keys {
+            options.keys.push(KeyRule::parse(spec)?);
+        }
+        for spec in masks {
+            options.masks.extend(MaskRule::parse(spec)?);
+        }
+        for name in normalize {
+            options.normalize.enable(name)?;
+        }
+        if let Some(path) = schema {
+            #[cfg(feature = "cli")]
+            {
+                options.normalize.schema = Some(SchemaTypes::load(path)?);
+            }
+            #[cfg(not(feature = "cli"))]
+            return Err(anyhow::anyhow!(
+                "--schema {path}: reading files needs the `cli` feature"
+            ));
+        }
+        Ok(options)
+    }
+
+    /// Strip the `--ignore-elem`/`--ignore-attr` matches (and, with `preset`,
+    /// the `--ignore-preset` noise) before comparing.
+    pub fn set_ignore(
+        &mut self,
+        elements: &[String],
+        attributes: &[String],
+        preset: bool,
+    ) -> Result<()> {
+        self.ignore = IgnoreRules::parse(elements, attributes, preset)?;
+        Ok(())
+    }
+
+    /// Normalise by the simple types the XSD text `xsd` declares, as
+    /// `--schema` does with a file.
+    pub fn set_schema(&mut self, xsd: &str) -> Result<()> {
+        self.normalize.schema = Some(SchemaTypes::parse(xsd, "schema")?);
+        Ok(())
+    }
+}
+
 /// Sort sibling elements bottom-up by signature. Mixed-content elements are left
 /// in document order (their `nodes` interleaving carries meaning); for sorted
-/// element-only content `nodes` is rebuilt to match the new child order.
-fn sort_tree(elem: &mut XmlElement) {
+/// element-only content `nodes` is rebuilt to match the new child order. A
+/// sibling a `--key` rule identifies sorts by name and key value first, so
+/// editing its other content doesn't move it.
+fn sort_tree(elem: &mut XmlElement, keys: &[KeyRule]) {
     for child in &mut elem.children {
-        sort_tree(child);
+        sort_tree(child, keys);
     }
     if !elem.is_mixed() && elem.children.len() > 1 {
         // Salvage comment nodes before the drain; sibling order is being
@@ -260,13 +617,10 @@ fn sort_tree(elem: &mut XmlElement) {
             .collect();
         comments.sort();
 
-        let mut keyed: Vec<(String, XmlElement)> = elem
-            .children
-            .drain(..)
-            .map(|c| (signature(&c), c))
-            .collect();
-        keyed.sort_by(|a, b| a.0.cmp(&b.0));
-        elem.children = keyed.into_iter().map(|(_, c)| c).collect();
+        elem.children.sort_by_cached_key(|c| {
+            let key = rule_key(c, keys).map(|(_, value)| value);
+            (c.name.clone(), key, signature(c))
+        });
         // Reattach comments as standalone: the sort has detached them from the
         // sibling they trailed, so an inline flag would now be misleading.
         elem.nodes = (0..elem.children.len())
@@ -282,14 +636,30 @@ fn sort_tree(elem: &mut XmlElement) {
 /// Canonicalise a parsed document in place: rebind namespace prefixes to stable
 /// canonical names so the rendered output of semantically equivalent documents
 /// diffs cleanly. When `sort_siblings` is set, also sort sibling elements.
+/// `--ignore-*` rules in `options` strip noise first; `--mask` and
+/// `--normalize` follow, so sorting sees placeholders and normalised values.
 ///
 /// Sorting is only safe where sibling order is incidental — i.e. plain XML data.
 /// In a dialect/`--special` mode (XSLT, XSD, WSDL, Schematron) element order is
 /// significant (`xsl:*` control flow, `xs:sequence`, rule order), so the caller
 /// passes `sort_siblings = false` there and only the prefix rebinding applies.
 /// See the module docs for the guarantees and their limits.
-pub(crate) fn canonicalize(roots: &mut [XmlElement], sort_siblings: bool) {
-    let uri2pfx = build_prefix_map(roots);
+pub fn canonicalize(roots: &mut [XmlElement], sort_siblings: bool, options: &CanonicalOptions) {
+    if !options.ignore.is_empty() {
+        options.ignore.strip(roots);
+    }
+    if !options.masks.is_empty() {
+        let mut masker = Masker::new(&options.masks);
+        for root in roots.iter_mut() {
+            masker.mask_element(root);
+        }
+    }
+    if !options.normalize.is_empty() {
+        for root in roots.iter_mut() {
+            options.normalize.normalize_element(root);
+        }
+    }
+    let uri2pfx = build_prefix_map(roots, &options.ns);
     let empty = HashMap::new();
     for root in roots.iter_mut() {
         rewrite(root, &empty, &uri2pfx);
@@ -297,7 +667,7 @@ pub(crate) fn canonicalize(roots: &mut [XmlElement], sort_siblings: bool) {
     emit_decls(roots, &uri2pfx);
     if sort_siblings {
         for root in roots.iter_mut() {
-            sort_tree(root);
+            sort_tree(root, &options.keys);
         }
     }
 }
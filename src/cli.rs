@@ -53,12 +53,26 @@ pub(crate) struct Cli {
 
     /// Autodetect the processing mode from each file's extension or content
     ///
-    /// (.xsl/.xslt -> xslt, .sch -> schematron, .xsd -> xsd). For JSON,
+    /// (.xsl/.xslt -> xslt, .sch -> schematron, .xsd -> xsd). Where the
+    /// extension picks none (.xml, --stdin), the document element does:
+    /// xsl:stylesheet, xs:schema, wsdl:definitions, sch:schema or an MSBuild
+    /// <Project>, matched by namespace. For JSON,
     /// simplifies standalone JSON Schema documents and Schema Objects embedded
     /// at known OpenAPI locations. Without this, inputs use generic rendering.
     #[arg(long)]
     pub(crate) auto: bool,
 
+    /// Render files matching PATTERN in MODE (repeatable)
+    ///
+    /// Extends the extension table, e.g. `--map '*.xslt2=xslt'` or
+    /// `--map '*.nuspec=msbuild'`. MODE is xslt, xsd, wsdl, schematron,
+    /// msbuild, special or xml (plain); html or json instead set the input
+    /// format. A PATTERN without `/` matches the file name. Applies with or
+    /// without --auto, and over `.unxml.toml`; mode flags still win. With
+    /// --init-git the patterns are bound to the diff driver too.
+    #[arg(long, value_name = "PATTERN=MODE")]
+    pub(crate) map: Vec<String>,
+
     /// Cancel the --auto that --bat/--html/--cat otherwise imply
     ///
     /// Those three flags default to acting as if --auto were also given
@@ -133,6 +147,17 @@ pub(crate) struct Cli {
     #[arg(long, value_delimiter = ',')]
     pub(crate) hide_ns: Vec<String>,
 
+    /// Treat URI as a known vocabulary with the conventional PREFIX (repeatable)
+    ///
+    /// Extends the built-in table (xsl, xs, cbc, ram, …): --canonical names
+    /// the URI PREFIX instead of `ns1`, and the --paths legend omits it when
+    /// bound to PREFIX. Append ` hide` (`'urn:acme:core=core hide'`) to hide
+    /// the prefix under --auto/--bat. Lines of the same form in
+    /// `~/.config/unxml/namespaces` (or `$XDG_CONFIG_HOME/unxml/namespaces`)
+    /// apply to every run; these flags win over them.
+    #[arg(long, value_name = "URI=PREFIX")]
+    pub(crate) ns_prefix: Vec<String>,
+
     /// Render only the subtrees whose element name matches this tag
     ///
     /// Renders only matching subtrees instead of the whole document. Matching
@@ -156,6 +181,92 @@ pub(crate) struct Cli {
     #[arg(long)]
     pub(crate) canonical: bool,
 
+    /// Identify siblings named ELEM by KEY when sorting (repeatable)
+    ///
+    /// KEY is an attribute (`--key 'row=@id'`) or a child element whose text
+    /// is the key (`--key 'InvoiceLine=cbc:ID'`); names match like --select.
+    /// Keyed siblings sort by their key, so editing a record's other fields
+    /// no longer moves it in the --canonical output. For JSON, ELEM names an
+    /// array and KEY a member of its objects (`--key 'lines=id'`); the array
+    /// is sorted by that member.
+    #[arg(long, value_name = "ELEM=KEY", requires = "canonical")]
+    pub(crate) key: Vec<String>,
+
+    /// Replace volatile values with stable placeholders under --canonical (repeatable)
+    ///
+    /// `NAME` masks the text of elements (or JSON members) named NAME,
+    /// `@NAME` attribute values, `ELEM@NAME` that attribute on ELEM only;
+    /// names match like --select. Detectors mask by content anywhere:
+    /// `:timestamp` (ISO 8601 date-times), `:uuid`, `:digest` (base64
+    /// digests and signatures), or `:auto` for all three. Placeholders are
+    /// `<timestamp>`, `<uuid#1>`, `<digest#1>`, `<masked#1>`; one value gets
+    /// the same number throughout the file.
+    #[arg(long, value_name = "PATTERN", requires = "canonical")]
+    pub(crate) mask: Vec<String>,
+
+    /// Normalise value spellings under --canonical (comma-separated)
+    ///
+    /// `numbers` (`+1.00` → `1`, `-0.0` → `0`), `booleans` (`TRUE` → `true`),
+    /// `whitespace` (trim and collapse runs), `datetimes` (zoned date-times
+    /// to UTC `Z`), or `all`. Applies to leaf text and attribute values.
+    /// Without --schema a number is only rewritten when it has both a sign
+    /// and a fraction, so codes like `007` and versions like `1.10` survive;
+    /// --schema types the rest.
+    #[arg(
+        long,
+        value_name = "RULES",
+        value_delimiter = ',',
+        requires = "canonical"
+    )]
+    pub(crate) normalize: Vec<String>,
+
+    /// Type --normalize by the simple types an XSD declares
+    ///
+    /// Elements and attributes are matched by local name: `xs:int` values
+    /// lose leading zeros, `xs:boolean` maps `1`/`0` to `true`/`false`, and
+    /// values declared as strings are left alone however numeric they look.
+    #[arg(long, value_name = "XSD", requires = "normalize")]
+    pub(crate) schema: Option<String>,
+
+    /// Drop attributes matching PATTERN under --canonical (repeatable)
+    ///
+    /// `NAME` drops attributes named NAME, `ELEM@NAME` only on ELEM, and
+    /// `NAME=VALUE` only where the value matches VALUE, with `*` for any run
+    /// of characters (`--ignore-attr 'id=ctl00_*'`). Names match like
+    /// --select. Stripped before masking, sorting and diffing.
+    #[arg(long, value_name = "PATTERN", requires = "canonical")]
+    pub(crate) ignore_attr: Vec<String>,
+
+    /// Drop elements named NAME, with their subtree, under --canonical (repeatable)
+    ///
+    /// Names match like --select; for JSON, NAME is an object member.
+    #[arg(long, value_name = "NAME", requires = "canonical")]
+    pub(crate) ignore_elem: Vec<String>,
+
+    /// Add the built-in ignore rules for each document's vocabulary
+    ///
+    /// Always `xsi:schemaLocation` and `xsi:noNamespaceSchemaLocation`; plus
+    /// `UBLExtensions` for UBL, `Project@ToolsVersion` for MSBuild, and
+    /// ASP.NET's generated `ctl00_…` ids and names for HTML.
+    #[arg(long, requires = "canonical")]
+    pub(crate) ignore_preset: bool,
+
+    /// Name namespaces by the URI=PREFIX lines of FILE under --canonical
+    ///
+    /// One pair per line (`urn:acme:orders=ord`); blank lines and `#`
+    /// comments are skipped. Mapped URIs get their prefix in every file, so
+    /// canonical output of a set of files agrees on them.
+    #[arg(long, value_name = "FILE", requires = "canonical")]
+    pub(crate) ns_map: Option<String>,
+
+    /// Name unrecognised namespaces by a hash of their URI under --canonical
+    ///
+    /// `ns` plus four hex digits (`ns3fa2`) instead of `ns1`, `ns2`, … in
+    /// sorted-URI order, so one URI gets the same prefix in every file
+    /// whatever other namespaces the file declares.
+    #[arg(long, requires = "canonical")]
+    pub(crate) ns_hash: bool,
+
     /// Dump the distinct element paths as an indented tree instead of the full document
     ///
     /// Each element path is shown once (repeated siblings collapse),
@@ -208,6 +319,91 @@ pub(crate) struct Cli {
     #[arg(long, require_equals = true, value_delimiter = ',', num_args = 0..)]
     pub(crate) collapse: Option<Vec<String>>,
 
+    /// Infer an XML Schema from the inputs instead of rendering them
+    ///
+    /// Every input (all files, or stdin) is read as an instance of one schema:
+    /// element nesting, `minOccurs`/`maxOccurs` from the observed cardinalities,
+    /// attribute optionality, and simple types from the values seen (boolean,
+    /// integer, decimal, date, dateTime, else string). Printed in the compact
+    /// --xsd rendering; add --emit-schema for the `.xsd` document itself.
+    #[arg(long, group = "infer")]
+    pub(crate) infer_xsd: bool,
+
+    /// Infer a JSON Schema from JSON samples instead of rendering them
+    ///
+    /// Every input document (or every line of a JSON Lines file) is a sample
+    /// of one schema: types per location, `required` for keys present in
+    /// every object, string formats (date-time, date, email, uuid), and enum
+    /// candidates for small sets of repeating strings. Printed in the compact
+    /// schema view --auto uses; add --emit-schema for the schema JSON itself.
+    #[arg(long, group = "infer")]
+    pub(crate) infer_json_schema: bool,
+
+    /// With --infer-xsd/--infer-json-schema, print the inferred schema document
+    /// instead of its compact rendering
+    #[arg(long, requires = "infer")]
+    pub(crate) emit_schema: bool,
+
+    /// Print Canonical XML 1.1 instead of rendering the input
+    ///
+    /// The byte form XML-DSig digests are computed over: no declaration or
+    /// DOCTYPE, sorted attributes, expanded empty elements, superfluous
+    /// namespace declarations removed, whitespace and text kept exactly.
+    /// Comments are dropped unless --with-comments. With --select, the
+    /// matching subtrees are canonicalised as a document subset, carrying
+    /// every in-scope namespace and inherited `xml:*` attribute. One input.
+    #[arg(long, group = "canonical_xml")]
+    pub(crate) c14n: bool,
+
+    /// Print Exclusive XML Canonicalization 1.0 instead of rendering the input
+    ///
+    /// Like --c14n, but an element renders only the namespaces it visibly
+    /// uses, so a --select subtree canonicalises the same wherever it is
+    /// embedded (as for signed parts of a UBL invoice).
+    #[arg(long, group = "canonical_xml")]
+    pub(crate) exc_c14n: bool,
+
+    /// With --c14n/--exc-c14n, keep comments (the #WithComments variants)
+    #[arg(long, requires = "canonical_xml")]
+    pub(crate) with_comments: bool,
+
+    /// Write the output to FILE instead of stdout
+    #[arg(short, long, value_name = "FILE", conflicts_with = "bat")]
+    pub(crate) output: Option<String>,
+
+    /// Write one output per input under DIR, mirroring the input tree
+    ///
+    /// Paths are kept relative to the deepest directory holding every input,
+    /// and `--suffix` is appended to each name. With --html each file gets
+    /// its own page, sharing one `unxml.css`, plus an `index.html` listing
+    /// them with their source and rendered sizes.
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["stdin", "bat", "cat", "output", "raw", "infer", "canonical_xml"])]
+    pub(crate) out_dir: Option<String>,
+
+    /// Write one output per input, named input + SUFFIX
+    ///
+    /// Defaults to `.html` with --html, else `.unxml`. Without --out-dir each
+    /// output is written beside its input (`a.xsl` → `a.xsl.unxml`).
+    #[arg(long, value_name = "SUFFIX", conflicts_with_all = ["stdin", "bat", "cat", "output", "raw", "infer", "canonical_xml"])]
+    pub(crate) suffix: Option<String>,
+
+    /// Re-render whenever an input file changes, until interrupted
+    ///
+    /// Clears the terminal before each render, or with --output rewrites
+    /// FILE (e.g. an --html page kept open beside the editor). Under
+    /// --expand the stylesheets the inputs import or include are watched
+    /// too. Files a glob would match only later are not picked up.
+    #[arg(long, conflicts_with_all = ["stdin", "bat", "raw", "infer", "canonical_xml"])]
+    pub(crate) watch: bool,
+
+    /// Ignore `.unxml.toml` and the user config file
+    ///
+    /// By default the nearest `.unxml.toml` from the current directory up,
+    /// over `~/.config/unxml/config.toml`, supplies options for the files its
+    /// patterns match; flags given here override it.
+    #[arg(long)]
+    pub(crate) no_config: bool,
+
     /// Read input from stdin (assumes XML format)
     #[arg(long)]
     pub(crate) stdin: bool,
@@ -239,4 +435,130 @@ pub(crate) struct Cli {
     /// textconv driver via `-c` for that single invocation only.
     #[arg(long)]
     pub(crate) init_git: bool,
+
+    /// With --init-git, also register `unxml merge-driver` as the merge driver
+    ///
+    /// XML files are then merged element by element: independent edits to
+    /// different elements combine cleanly, and conflict markers wrap only
+    /// the elements both branches changed differently.
+    #[arg(long, requires = "init_git")]
+    pub(crate) merge: bool,
+}
+
+/// `unxml diff OLD NEW`, parsed separately from `Cli` (see `main`).
+#[derive(Parser)]
+#[command(name = "unxml diff")]
+#[command(about = "Structurally diff two XML, HTML, or JSON documents")]
+pub(crate) struct DiffCli {
+    /// The old document
+    pub(crate) old: String,
+
+    /// The new document
+    pub(crate) new: String,
+
+    /// Force the input format of both documents (xml, html, or json). If omitted, it is auto-detected
+    #[arg(short, long)]
+    pub(crate) format: Option<String>,
+
+    /// Compare two XSDs by schema component and classify changes
+    ///
+    /// Reports what changed for instances — added, removed and re-typed
+    /// elements and attributes, occurrences, enumeration values, facets — as
+    /// breaking (an instance valid under OLD may be rejected) or compatible.
+    #[arg(long, conflicts_with = "json_schema")]
+    pub(crate) xsd: bool,
+
+    /// Compare two JSON Schema or OpenAPI documents and classify changes
+    ///
+    /// Reports added and removed operations, parameters and properties,
+    /// required-ness, type, enum and bound changes as breaking or compatible,
+    /// in the compact schema view `--auto` renders (`id! : integer int64`).
+    #[arg(long, visible_alias = "openapi")]
+    pub(crate) json_schema: bool,
+
+    /// Match siblings named ELEM by KEY (repeatable; see `unxml --help`)
+    ///
+    /// KEY is an attribute (`row=@id`) or a child element's text
+    /// (`InvoiceLine=cbc:ID`); for JSON, a member of an array's objects
+    /// (`lines=id`). Takes precedence over the built-in id/key/name keys.
+    #[arg(long, value_name = "ELEM=KEY")]
+    pub(crate) key: Vec<String>,
+
+    /// Mask volatile values on both sides before comparing (repeatable; see `unxml --help`)
+    #[arg(long, value_name = "PATTERN")]
+    pub(crate) mask: Vec<String>,
+
+    /// Normalise value spellings on both sides (comma-separated; see `unxml --help`)
+    #[arg(long, value_name = "RULES", value_delimiter = ',')]
+    pub(crate) normalize: Vec<String>,
+
+    /// Type --normalize by the simple types an XSD declares
+    #[arg(long, value_name = "XSD", requires = "normalize")]
+    pub(crate) schema: Option<String>,
+
+    /// Drop attributes matching PATTERN on both sides (repeatable; see `unxml --help`)
+    #[arg(long, value_name = "PATTERN")]
+    pub(crate) ignore_attr: Vec<String>,
+
+    /// Drop elements named NAME on both sides (repeatable)
+    #[arg(long, value_name = "NAME")]
+    pub(crate) ignore_elem: Vec<String>,
+
+    /// Add the built-in ignore rules for each document's vocabulary
+    #[arg(long)]
+    pub(crate) ignore_preset: bool,
+
+    /// Treat URI as a known vocabulary with PREFIX (repeatable; see `unxml --help`)
+    #[arg(long, value_name = "URI=PREFIX")]
+    pub(crate) ns_prefix: Vec<String>,
+
+    /// Name namespaces by the URI=PREFIX lines of FILE (see `unxml --help`)
+    #[arg(long, value_name = "FILE")]
+    pub(crate) ns_map: Option<String>,
+
+    /// Name unrecognised namespaces by a hash of their URI
+    #[arg(long)]
+    pub(crate) ns_hash: bool,
+}
+
+/// `unxml merge-driver BASE OURS THEIRS`, the git merge driver `--init-git
+/// --merge` registers (see `main`).
+#[derive(Parser)]
+#[command(name = "unxml merge-driver")]
+#[command(about = "Three-way merge XML element trees into OURS (a git merge driver)")]
+pub(crate) struct MergeCli {
+    /// The common ancestor (git's %O)
+    pub(crate) base: String,
+
+    /// Our version (git's %A); overwritten with the merge result
+    pub(crate) ours: String,
+
+    /// Their version (git's %B)
+    pub(crate) theirs: String,
+
+    /// Match siblings named ELEM by KEY (repeatable; see `unxml --help`)
+    #[arg(long, value_name = "ELEM=KEY")]
+    pub(crate) key: Vec<String>,
+
+    /// The file's path in the repository (git's %P), which `.unxml.toml`
+    /// `[[files]]` patterns are matched against; OURS by default
+    #[arg(long, value_name = "PATH")]
+    pub(crate) path: Option<String>,
+
+    /// Ignore `.unxml.toml` and the user config file
+    #[arg(long)]
+    pub(crate) no_config: bool,
+}
+
+/// `unxml site SRC_DIR OUT_DIR`, a browsable gallery of rendered documents
+/// (see `main`).
+#[derive(Parser)]
+#[command(name = "unxml site")]
+#[command(about = "Render a directory of documents into a static HTML gallery")]
+pub(crate) struct SiteCli {
+    /// The directory whose XML, HTML and JSON files are rendered, recursively
+    pub(crate) src_dir: String,
+
+    /// Where the pages, `unxml.css` and `index.html` are written
+    pub(crate) out_dir: String,
 }
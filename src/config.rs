@@ -0,0 +1,431 @@
+//! `.unxml.toml`: per-project defaults for the options a repository needs on
+//! every run, so `unxml file.xml` (and the `--init-git` textconv driver)
+//! renders the same way without repeating the flags.
+//!
+//! The nearest `.unxml.toml` walking up from the current directory applies,
+//! on top of the user-wide `config.toml` in the config directory
+//! (`~/.config/unxml/`). Top-level keys apply to every file; `[[files]]`
+//! tables apply to the files their `pattern` matches, later tables winning:
+//!
+//! ```toml
+//! hide-ns = ["cbc", "cac"]
+//! collapse = ["ext:UBLExtensions"]
+//! key = ["InvoiceLine=cbc:ID"]
+//!
+//! [[files]]
+//! pattern = "*.config"
+//! mode = "msbuild"
+//! ```
+//!
+//! `map = ["*.xslt2=xslt", "*.resx=xml"]` is shorthand for `[[files]]`
+//! tables that only set a mode (or an input format), as `--map` gives them on
+//! the command line.
+//!
+//! A pattern with a `/` matches the path from the config file's directory,
+//! one without the file name. Git runs the textconv driver on a temporary
+//! `XXXXXX_<file name>` copy for anything outside the working tree, which
+//! only the `*.ext` kind of pattern still matches; the driver isn't cached
+//! (no `cachetextconv`) so that edits here show in the next diff.
+//!
+//! Keys are the long flag names. Command-line flags override the file:
+//! a mode or `--collapse` flag replaces the configured one, and list options
+//! add to it (a `--key` on the command line is tried before configured ones).
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use glob::{MatchOptions, Pattern};
+use toml::{Table, Value};
+
+use crate::cli::Cli;
+use crate::install::config_dir;
+use crate::model::FormatOpts;
+use crate::parse::read_file_lenient;
+
+/// The project config file name.
+pub(crate) const CONFIG_FILE: &str = ".unxml.toml";
+
+/// The rendering options a config file can set, and the command line's own
+/// values for them (see [`Settings::from_cli`]).
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Settings {
+    /// An explicit mode; `None` leaves it to `--auto`.
+    pub(crate) mode: Option<FormatOpts>,
+    pub(crate) format: Option<String>,
+    pub(crate) hide_ns: Vec<String>,
+    pub(crate) collapse: Option<Vec<String>>,
+    pub(crate) key: Vec<String>,
+    pub(crate) mask: Vec<String>,
+    pub(crate) normalize: Vec<String>,
+    pub(crate) schema: Option<String>,
+    pub(crate) ignore_attr: Vec<String>,
+    pub(crate) ignore_elem: Vec<String>,
+    pub(crate) ignore_preset: bool,
+    pub(crate) ns_prefix: Vec<String>,
+    pub(crate) ns_map: Option<String>,
+    pub(crate) ns_hash: bool,
+}
+
+impl Settings {
+    /// The options given on the command line.
+    pub(crate) fn from_cli(cli: &Cli) -> Self {
+        let mode = FormatOpts {
+            special: cli.special,
+            xslt: cli.xslt,
+            schematron: cli.schematron,
+            xsd: cli.xsd,
+            wsdl: cli.wsdl,
+            msbuild: cli.msbuild,
+            ..FormatOpts::default()
+        };
+        Self {
+            mode: mode.has_mode().then_some(mode),
+            format: cli.format.clone(),
+            hide_ns: cli.hide_ns.clone(),
+            collapse: cli.collapse.clone(),
+            key: cli.key.clone(),
+            mask: cli.mask.clone(),
+            normalize: cli.normalize.clone(),
+            schema: cli.schema.clone(),
+            ignore_attr: cli.ignore_attr.clone(),
+            ignore_elem: cli.ignore_elem.clone(),
+            ignore_preset: cli.ignore_preset,
+            ns_prefix: cli.ns_prefix.clone(),
+            ns_map: cli.ns_map.clone(),
+            ns_hash: cli.ns_hash,
+        }
+    }
+
+    /// `self` with `over` taking precedence: its single values replace these,
+    /// its lists add to them. `--key` rules are tried in order, so `over`'s
+    /// go first; `--ns-prefix` entries replace earlier ones, so they go last.
+    pub(crate) fn overlay(mut self, over: &Settings) -> Self {
+        fn replace<T: Clone>(value: &mut Option<T>, over: &Option<T>) {
+            if over.is_some() {
+                value.clone_from(over);
+            }
+        }
+        replace(&mut self.mode, &over.mode);
+        replace(&mut self.format, &over.format);
+        replace(&mut self.collapse, &over.collapse);
+        replace(&mut self.schema, &over.schema);
+        replace(&mut self.ns_map, &over.ns_map);
+        self.hide_ns.extend(over.hide_ns.iter().cloned());
+        self.key.splice(0..0, over.key.iter().cloned());
+        self.mask.extend(over.mask.iter().cloned());
+        self.normalize.extend(over.normalize.iter().cloned());
+        self.ignore_attr.extend(over.ignore_attr.iter().cloned());
+        self.ignore_elem.extend(over.ignore_elem.iter().cloned());
+        self.ignore_preset |= over.ignore_preset;
+        self.ns_prefix.extend(over.ns_prefix.iter().cloned());
+        self.ns_hash |= over.ns_hash;
+        self
+    }
+
+    /// Read the settings keys of a config table. Paths (`schema`, `ns-map`)
+    /// are relative to `dir`, the config file's directory.
+    fn from_table(table: &Table, dir: &Path, skip: &[&str]) -> Result<Self> {
+        let mut settings = Self::default();
+        for (key, value) in table {
+            if skip.contains(&key.as_str()) {
+                continue;
+            }
+            let path = |value: &Value| -> Result<Option<String>> {
+                Ok(Some(dir.join(string(key, value)?).display().to_string()))
+            };
+            match key.as_str() {
+                "mode" => settings.mode = Some(FormatOpts::from_mode(&string(key, value)?)?),
+                "format" => settings.format = Some(string(key, value)?),
+                "hide-ns" => settings.hide_ns = list(key, value)?,
+                "collapse" => {
+                    settings.collapse = match value {
+                        Value::Boolean(true) => Some(Vec::new()),
+                        Value::Boolean(false) => None,
+                        _ => Some(list(key, value)?),
+                    }
+                }
+                "key" => settings.key = list(key, value)?,
+                "mask" => settings.mask = list(key, value)?,
+                "normalize" => settings.normalize = list(key, value)?,
+                "schema" => settings.schema = path(value)?,
+                "ignore-attr" => settings.ignore_attr = list(key, value)?,
+                "ignore-elem" => settings.ignore_elem = list(key, value)?,
+                "ignore-preset" => settings.ignore_preset = boolean(key, value)?,
+                "ns-prefix" => settings.ns_prefix = list(key, value)?,
+                "ns-map" => settings.ns_map = path(value)?,
+                "ns-hash" => settings.ns_hash = boolean(key, value)?,
+                _ => return Err(anyhow::anyhow!("Unknown key '{key}'")),
+            }
+        }
+        Ok(settings)
+    }
+}
+
+fn string(key: &str, value: &Value) -> Result<String> {
+    value
+        .as_str()
+        .map(str::to_string)
+        .with_context(|| format!("'{key}' must be a string"))
+}
+
+fn boolean(key: &str, value: &Value) -> Result<bool> {
+    value
+        .as_bool()
+        .with_context(|| format!("'{key}' must be true or false"))
+}
+
+/// A list option: an array of strings, or one comma-separated string like
+/// the flag takes (`hide-ns = "cbc,cac"`).
+fn list(key: &str, value: &Value) -> Result<Vec<String>> {
+    match value {
+        Value::String(s) => Ok(s.split(',').map(|s| s.trim().to_string()).collect()),
+        Value::Array(items) => items.iter().map(|item| string(key, item)).collect(),
+        _ => Err(anyhow::anyhow!(
+            "'{key}' must be a string or a list of strings"
+        )),
+    }
+}
+
+/// A `[[files]]` table: settings for the files `patterns` match.
+#[derive(Debug)]
+struct Rule {
+    patterns: Vec<Pattern>,
+    /// The config file's directory, which patterns with a `/` are relative to.
+    dir: PathBuf,
+    settings: Settings,
+}
+
+impl Rule {
+    /// A pattern without `/` matches the file name in any directory (like a
+    /// `.gitignore` line); one with `/` matches the path from the config
+    /// file's directory.
+    fn matches(&self, file: &Path) -> bool {
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..MatchOptions::new()
+        };
+        let absolute = std::path::absolute(file).unwrap_or_else(|_| file.to_path_buf());
+        let relative = absolute.strip_prefix(&self.dir).ok();
+        self.patterns.iter().any(|pattern| {
+            if pattern.as_str().contains('/') {
+                relative.is_some_and(|r| pattern.matches_path_with(r, options))
+            } else {
+                file.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| pattern.matches_with(name, options))
+            }
+        })
+    }
+}
+
+/// The discovered configuration: settings for every file, then per-pattern
+/// rules in file order.
+#[derive(Debug, Default)]
+pub(crate) struct Config {
+    base: Settings,
+    rules: Vec<Rule>,
+}
+
+impl Config {
+    /// Load the user config, then the nearest `.unxml.toml` from the current
+    /// directory up.
+    pub(crate) fn discover() -> Result<Self> {
+        let mut config = Self::default();
+        if let Some(path) = config_dir().map(|dir| dir.join("config.toml"))
+            && path.is_file()
+        {
+            config.load(&path)?;
+        }
+        let cwd = std::env::current_dir().context("Cannot read the current directory")?;
+        if let Some(path) = cwd
+            .ancestors()
+            .map(|dir| dir.join(CONFIG_FILE))
+            .find(|path| path.is_file())
+        {
+            config.load(&path)?;
+        }
+        Ok(config)
+    }
+
+    /// Add one config file's settings and rules over those loaded so far.
+    fn load(&mut self, path: &Path) -> Result<()> {
+        let content = read_file_lenient(&path.display().to_string())?;
+        self.add(&content, path.parent().unwrap_or(Path::new("")))
+            .with_context(|| format!("In {}", path.display()))
+    }
+
+    fn add(&mut self, content: &str, dir: &Path) -> Result<()> {
+        let table: Table = content.parse()?;
+        let dir = std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf());
+        self.base = std::mem::take(&mut self.base).overlay(&Settings::from_table(
+            &table,
+            &dir,
+            &["files", "map"],
+        )?);
+        if let Some(map) = table.get("map") {
+            for spec in list("map", map)? {
+                self.add_map(&spec, &dir)
+                    .with_context(|| format!("Invalid map '{spec}'"))?;
+            }
+        }
+        let Some(files) = table.get("files") else {
+            return Ok(());
+        };
+        let files = files
+            .as_array()
+            .context("'files' must be an array of tables ([[files]])")?;
+        for (n, entry) in files.iter().enumerate() {
+            let context = || format!("In [[files]] #{}", n + 1);
+            let entry = entry
+                .as_table()
+                .context("'files' entries must be tables")
+                .with_context(context)?;
+            let patterns = entry
+                .get("pattern")
+                .context("Missing 'pattern'")
+                .and_then(|value| list("pattern", value))
+                .with_context(context)?;
+            let patterns = patterns
+                .iter()
+                .map(|p| Pattern::new(p).with_context(|| format!("Invalid pattern '{p}'")))
+                .collect::<Result<_>>()
+                .with_context(context)?;
+            self.rules.push(Rule {
+                patterns,
+                dir: dir.clone(),
+                settings: Settings::from_table(entry, &dir, &["pattern"]).with_context(context)?,
+            });
+        }
+        Ok(())
+    }
+
+    /// Add a `PATTERN=MODE` mapping (`--map '*.xslt2=xslt'`): files matching
+    /// PATTERN render in MODE (`xslt`, `xsd`, `msbuild`, … as for `mode`), or
+    /// are read as FORMAT when it is `html` or `json`. `xml` means both plain
+    /// XML input and no mode.
+    pub(crate) fn add_map(&mut self, spec: &str, dir: &Path) -> Result<()> {
+        let (pattern, target) = spec.split_once('=').context("Expected PATTERN=MODE")?;
+        let mut settings = Settings::default();
+        match target.trim().to_lowercase().as_str() {
+            format @ ("html" | "json") => settings.format = Some(format.to_string()),
+            "xml" => {
+                settings.format = Some("xml".to_string());
+                settings.mode = Some(FormatOpts::default());
+            }
+            mode => settings.mode = Some(FormatOpts::from_mode(mode)?),
+        }
+        let pattern = pattern.trim();
+        self.rules.push(Rule {
+            patterns: vec![
+                Pattern::new(pattern).with_context(|| format!("Invalid pattern '{pattern}'"))?,
+            ],
+            dir: std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf()),
+            settings,
+        });
+        Ok(())
+    }
+
+    /// The settings for `file` (`None` for stdin): the top-level ones, then
+    /// every matching rule's in order.
+    pub(crate) fn settings_for(&self, file: Option<&str>) -> Settings {
+        let mut settings = self.base.clone();
+        if let Some(file) = file {
+            for rule in self
+                .rules
+                .iter()
+                .filter(|rule| rule.matches(Path::new(file)))
+            {
+                settings = settings.overlay(&rule.settings);
+            }
+        }
+        settings
+    }
+
+    /// The file-name patterns of the rules, for `--init-git` to route those
+    /// files through the diff driver too.
+    pub(crate) fn patterns(&self) -> impl Iterator<Item = &str> {
+        self.rules
+            .iter()
+            .flat_map(|rule| rule.patterns.iter().map(Pattern::as_str))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::Config;
+
+    const CONFIG: &str = r#"
+hide-ns = ["cbc", "cac"]
+key = "InvoiceLine=cbc:ID"
+map = ["*.xslt2=xslt", "*.page=html"]
+
+[[files]]
+pattern = "*.config"
+mode = "msbuild"
+
+[[files]]
+pattern = ["legacy/*.xml"]
+collapse = true
+key = ["row=@id"]
+"#;
+
+    #[test]
+    fn rules_layer_over_the_top_level_settings() {
+        let mut config = Config::default();
+        config.add(CONFIG, Path::new("/repo")).unwrap();
+
+        let web = config.settings_for(Some("/repo/src/web.config"));
+        assert!(web.mode.is_some_and(|mode| mode.msbuild));
+        assert_eq!(web.hide_ns, ["cbc", "cac"]);
+
+        let legacy = config.settings_for(Some("/repo/legacy/a.xml"));
+        assert!(legacy.mode.is_none());
+        assert_eq!(legacy.collapse, Some(Vec::new()));
+        assert_eq!(legacy.key, ["row=@id", "InvoiceLine=cbc:ID"]);
+
+        // A pattern with a `/` is anchored at the config's directory.
+        let nested = config.settings_for(Some("/repo/src/legacy/a.xml"));
+        assert_eq!(nested.collapse, None);
+        assert_eq!(
+            config.patterns().collect::<Vec<_>>(),
+            ["*.xslt2", "*.page", "*.config", "legacy/*.xml"]
+        );
+    }
+
+    #[test]
+    fn maps_set_a_mode_or_an_input_format() {
+        let mut config = Config::default();
+        config.add(CONFIG, Path::new("/repo")).unwrap();
+        assert!(
+            config
+                .settings_for(Some("a.xslt2"))
+                .mode
+                .is_some_and(|mode| mode.xslt)
+        );
+        assert_eq!(
+            config.settings_for(Some("a.page")).format.as_deref(),
+            Some("html")
+        );
+
+        config.add_map("*.nuspec=xml", Path::new("/repo")).unwrap();
+        let nuspec = config.settings_for(Some("/repo/a.nuspec"));
+        assert!(nuspec.mode.is_some_and(|mode| !mode.has_mode()));
+        assert_eq!(nuspec.format.as_deref(), Some("xml"));
+        assert!(config.add_map("*.x=yaml", Path::new("/")).is_err());
+    }
+
+    #[test]
+    fn unknown_keys_are_errors() {
+        let mut config = Config::default();
+        let err = config.add(
+            "[[files]]\npattern = \"*.xml\"\nhide = \"x\"\n",
+            Path::new("/"),
+        );
+        assert_eq!(
+            format!("{:#}", err.unwrap_err()),
+            "In [[files]] #1: Unknown key 'hide'"
+        );
+    }
+}
@@ -0,0 +1,592 @@
+//! Structural diff (`unxml diff OLD NEW`).
+//!
+//! A line diff of two `--canonical` renders misreports a moved or edited
+//! record as a delete plus an insert, and can only say "this line changed".
+//! Here the two element trees are matched instead, sibling list by sibling
+//! list, and only the differences are printed — as an unxml tree whose lines
+//! carry a marker gutter:
+//!
+//!  - `+` added and `-` removed elements, attributes, text and comments;
+//!  - `~` a matched element whose own attributes, text or comments changed
+//!    (the changes are listed beneath it, `old → new`);
+//!  - blank for an unchanged ancestor, kept so every change has its path.
+//!
+//! Siblings are paired in three passes: by identity key (same name and the
+//! same `--key` rule value, else the same `id`/`xml:id`/`key`/`name`
+//! attribute — or, for JSON array items, the same `id`/`key`/`name`
+//! member), then identical subtrees (`canonical::signature`), then the most
+//! similar remaining element of the same name. Sibling order is not
+//! reported, like `--canonical`'s sort. Both documents go through
+//! `--canonical`'s prefix rebinding together first, so prefix spelling never
+//! shows up as a change. JSON is diffed through the same tree, built by
+//! `json::json_to_elements`. With `--xsd`, two schemas are compared by
+//! component instead (see `xsd_diff`), and with `--json-schema` two JSON
+//! Schema or OpenAPI documents (see `json_schema_diff`).
+
+use std::collections::BTreeSet;
+
+use anyhow::{Context, Result};
+
+use crate::canonical::{
+    CanonicalOptions, KeyRule, KnownPrefixes, NsPrefixes, canonicalize, rule_key, signature,
+};
+use crate::cli::DiffCli;
+use crate::json::json_to_elements;
+use crate::json_schema_diff::diff_json_schemas;
+use crate::model::{FormatOpts, NodeRef, XmlElement};
+use crate::parse::{InputFormat, detect_format, parse_html, parse_xml, read_file_lenient};
+use crate::render::render_text;
+use crate::xsd_diff::diff_schemas;
+
+/// Attributes that identify an element among its same-named siblings.
+const KEY_ATTRS: &[&str] = &["id", "xml:id", "key", "name"];
+
+/// Members that identify a JSON array item (`key[]` element) among its
+/// siblings.
+const KEY_MEMBERS: &[&str] = &["id", "key", "name"];
+
+/// Below this similarity two same-named elements are reported as a removal
+/// plus an addition rather than as one changed element.
+const MIN_SIMILARITY: f64 = 0.5;
+
+/// What identifies an element among its siblings: the key's label (`@id` for
+/// an attribute, the member name for a JSON item) and its value.
+#[derive(PartialEq)]
+pub(crate) struct Identity {
+    label: String,
+    value: String,
+}
+
+/// A `--key` rule's key if one applies, else a built-in key.
+pub(crate) fn identity(elem: &XmlElement, keys: &[KeyRule]) -> Option<Identity> {
+    if let Some((rule, value)) = rule_key(elem, keys) {
+        return Some(Identity {
+            label: rule.key.clone(),
+            value,
+        });
+    }
+    for key in KEY_ATTRS {
+        if let Some(value) = elem.attributes.get(*key) {
+            return Some(Identity {
+                label: format!("@{key}"),
+                value: value.clone(),
+            });
+        }
+    }
+    if elem.name.ends_with("[]") {
+        for key in KEY_MEMBERS {
+            if let Some(member) = elem
+                .children
+                .iter()
+                .find(|c| c.name == *key && c.children.is_empty())
+            {
+                return Some(Identity {
+                    label: key.to_string(),
+                    value: member.text_content.clone(),
+                });
+            }
+        }
+    }
+    None
+}
+
+/// The leaf facts of a subtree (`path@attr=value`, `path=text`), relative to
+/// its root, for similarity scoring.
+fn facts(elem: &XmlElement, path: &str, out: &mut Vec<String>) {
+    for (key, value) in &elem.attributes {
+        out.push(format!("{path}@{key}={value}"));
+    }
+    let text = elem.text_content.trim();
+    if !text.is_empty() {
+        out.push(format!("{path}={text}"));
+    }
+    for child in &elem.children {
+        facts(child, &format!("{path}/{}", child.name), out);
+    }
+}
+
+/// Jaccard similarity of the two subtrees' fact multisets, 0.0 to 1.0.
+fn similarity(a: &XmlElement, b: &XmlElement) -> f64 {
+    let (mut fa, mut fb) = (Vec::new(), Vec::new());
+    facts(a, "", &mut fa);
+    facts(b, "", &mut fb);
+    fa.sort_unstable();
+    fb.sort_unstable();
+    let (mut i, mut j, mut common) = (0, 0, 0);
+    while i < fa.len() && j < fb.len() {
+        match fa[i].cmp(&fb[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                common += 1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    let union = fa.len() + fb.len() - common;
+    if union == 0 {
+        1.0
+    } else {
+        common as f64 / union as f64
+    }
+}
+
+/// One step of a merged sibling list: a matched pair, or an element only on
+/// the old (`Left`) or new (`Right`) side.
+pub(crate) enum Step {
+    Both(usize, usize),
+    Left(usize),
+    Right(usize),
+}
+
+/// Pair up two sibling lists and return them merged in new-side order, each
+/// removed element placed before the first new element matched after it.
+pub(crate) fn match_siblings(
+    left: &[XmlElement],
+    right: &[XmlElement],
+    keys: &[KeyRule],
+) -> Vec<Step> {
+    let left_ids: Vec<Option<Identity>> = left.iter().map(|e| identity(e, keys)).collect();
+    let right_ids: Vec<Option<Identity>> = right.iter().map(|e| identity(e, keys)).collect();
+    let mut partner: Vec<Option<usize>> = vec![None; left.len()];
+    let mut taken = vec![false; right.len()];
+
+    // 1. Same name and identity key.
+    for (i, l) in left.iter().enumerate() {
+        if left_ids[i].is_none() {
+            continue;
+        }
+        if let Some(j) = (0..right.len())
+            .find(|&j| !taken[j] && right[j].name == l.name && right_ids[j] == left_ids[i])
+        {
+            partner[i] = Some(j);
+            taken[j] = true;
+        }
+    }
+
+    // 2. Identical subtrees.
+    let right_sigs: Vec<String> = right.iter().map(signature).collect();
+    for (i, l) in left.iter().enumerate() {
+        if partner[i].is_some() {
+            continue;
+        }
+        let sig = signature(l);
+        if let Some(j) = (0..right.len()).find(|&j| !taken[j] && right_sigs[j] == sig) {
+            partner[i] = Some(j);
+            taken[j] = true;
+        }
+    }
+
+    // 3. The most similar remaining element of the same name, unless their
+    // identity keys disagree. A lone leftover on each side always pairs.
+    let compatible = |i: usize, j: usize| {
+        left[i].name == right[j].name
+            && (left_ids[i].is_none() || right_ids[j].is_none() || left_ids[i] == right_ids[j])
+    };
+    for i in 0..left.len() {
+        if partner[i].is_some() {
+            continue;
+        }
+        let candidates: Vec<usize> = (0..right.len())
+            .filter(|&j| !taken[j] && compatible(i, j))
+            .collect();
+        let rivals = (0..left.len())
+            .filter(|&k| partner[k].is_none() && left[k].name == left[i].name)
+            .count();
+        let best = if candidates.len() == 1 && rivals == 1 {
+            Some(candidates[0])
+        } else {
+            candidates
+                .iter()
+                .map(|&j| (j, similarity(&left[i], &right[j])))
+                .filter(|&(_, score)| score >= MIN_SIMILARITY)
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(j, _)| j)
+        };
+        if let Some(j) = best {
+            partner[i] = Some(j);
+            taken[j] = true;
+        }
+    }
+
+    let mut left_of = vec![None; right.len()];
+    for (i, j) in partner.iter().enumerate() {
+        if let Some(j) = j {
+            left_of[*j] = Some(i);
+        }
+    }
+    let mut steps = Vec::new();
+    let mut next_left = 0;
+    for (j, i) in left_of.iter().enumerate() {
+        match i {
+            Some(i) => {
+                steps.extend(
+                    (next_left..*i)
+                        .filter(|&k| partner[k].is_none())
+                        .map(Step::Left),
+                );
+                next_left = next_left.max(i + 1);
+                steps.push(Step::Both(*i, j));
+            }
+            None => steps.push(Step::Right(j)),
+        }
+    }
+    steps.extend(
+        (next_left..left.len())
+            .filter(|&k| partner[k].is_none())
+            .map(Step::Left),
+    );
+    steps
+}
+
+/// One output line: marker gutter, indentation, content.
+fn push_line(out: &mut String, mark: char, indent: usize, text: &str) {
+    out.push_str(&format!("{mark} {}{text}\n", "  ".repeat(indent)));
+}
+
+/// Prefix every line of an already-indented block with `mark`.
+fn push_marked(out: &mut String, mark: char, block: &str) {
+    for line in block.lines() {
+        out.push_str(&format!("{mark} {line}\n"));
+    }
+}
+
+/// A whole subtree as added or removed.
+fn push_subtree(out: &mut String, mark: char, elem: &XmlElement, indent: usize) {
+    push_marked(
+        out,
+        mark,
+        &elem.format_yaml_like(indent, &FormatOpts::default(), None),
+    );
+}
+
+/// A matched element's header: its name with the attributes both sides share
+/// (`line(sku="A9")`, namespace declarations aside), so the reader can tell
+/// which sibling changed. A JSON item shows its identity member instead
+/// (`owners[][id=2]`).
+fn header(l: &XmlElement, r: &XmlElement, keys: &[KeyRule]) -> String {
+    let mut shared: Vec<String> = r
+        .attributes
+        .iter()
+        .filter(|(k, v)| !k.starts_with("xmlns") && l.attributes.get(*k) == Some(v))
+        .map(|(k, v)| format!("{k}=\"{v}\""))
+        .collect();
+    shared.sort();
+    if !shared.is_empty() {
+        return format!("{}({})", r.name, shared.join(", "));
+    }
+    match identity(r, keys) {
+        Some(id) if !id.label.starts_with('@') => format!("{}[{}={}]", r.name, id.label, id.value),
+        _ => r.name.clone(),
+    }
+}
+
+fn comments(elem: &XmlElement) -> Vec<&str> {
+    let mut comments: Vec<&str> = elem
+        .nodes
+        .iter()
+        .filter_map(|n| match n {
+            NodeRef::Comment { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+    comments.sort_unstable();
+    comments
+}
+
+/// A text value as `= value` (or a `|` block) at `indent`, marked.
+fn push_text(out: &mut String, mark: char, text: &str, indent: usize) {
+    // `render_text` writes ` = …` after an element name; with no name, drop
+    // the separating space.
+    let mut rendered = String::new();
+    render_text(&mut rendered, text, indent);
+    let block = format!("{}{}", "  ".repeat(indent), rendered.trim_start());
+    push_marked(out, mark, &block);
+}
+
+/// Diff a matched pair at `indent`, appending to `out` only when they differ.
+/// Returns whether anything differed.
+fn diff_pair(
+    l: &XmlElement,
+    r: &XmlElement,
+    keys: &[KeyRule],
+    indent: usize,
+    out: &mut String,
+) -> bool {
+    // Mixed content is compared as a whole: its interleaving is its meaning.
+    if l.is_mixed() || r.is_mixed() {
+        if signature(l) == signature(r) {
+            return false;
+        }
+        push_subtree(out, '-', l, indent);
+        push_subtree(out, '+', r, indent);
+        return true;
+    }
+
+    let mut own = String::new();
+    let names: BTreeSet<&String> = l.attributes.keys().chain(r.attributes.keys()).collect();
+    for key in names {
+        match (l.attributes.get(key), r.attributes.get(key)) {
+            (Some(a), Some(b)) if a != b => {
+                push_line(&mut own, '~', indent + 1, &format!("@{key} = {a} → {b}"));
+            }
+            (Some(a), None) => push_line(&mut own, '-', indent + 1, &format!("@{key} = {a}")),
+            (None, Some(b)) => push_line(&mut own, '+', indent + 1, &format!("@{key} = {b}")),
+            _ => {}
+        }
+    }
+
+    let (old_text, new_text) = (l.text_content.trim(), r.text_content.trim());
+    let mut compact_text = None;
+    if old_text != new_text {
+        let single = !old_text.contains('\n') && !new_text.contains('\n');
+        if single && !old_text.is_empty() && !new_text.is_empty() {
+            if own.is_empty() && l.children.is_empty() && r.children.is_empty() {
+                compact_text = Some(format!(" = {old_text} → {new_text}"));
+            } else {
+                push_line(
+                    &mut own,
+                    '~',
+                    indent + 1,
+                    &format!("= {old_text} → {new_text}"),
+                );
+            }
+        } else {
+            if !old_text.is_empty() {
+                push_text(&mut own, '-', old_text, indent + 1);
+            }
+            if !new_text.is_empty() {
+                push_text(&mut own, '+', new_text, indent + 1);
+            }
+        }
+    }
+
+    let (old_comments, new_comments) = (comments(l), comments(r));
+    if old_comments != new_comments {
+        for text in old_comments.iter().filter(|c| !new_comments.contains(c)) {
+            push_line(&mut own, '-', indent + 1, &format!("// {text}"));
+        }
+        for text in new_comments.iter().filter(|c| !old_comments.contains(c)) {
+            push_line(&mut own, '+', indent + 1, &format!("// {text}"));
+        }
+    }
+
+    let mut nested = String::new();
+    diff_siblings(&l.children, &r.children, keys, indent + 1, &mut nested);
+
+    if own.is_empty() && nested.is_empty() && compact_text.is_none() {
+        return false;
+    }
+    let mark = if own.is_empty() && compact_text.is_none() {
+        ' '
+    } else {
+        '~'
+    };
+    let line = format!("{}{}", header(l, r, keys), compact_text.unwrap_or_default());
+    push_line(out, mark, indent, &line);
+    out.push_str(&own);
+    out.push_str(&nested);
+    true
+}
+
+/// Diff two sibling lists at `indent`, appending the differences to `out`.
+fn diff_siblings(
+    left: &[XmlElement],
+    right: &[XmlElement],
+    keys: &[KeyRule],
+    indent: usize,
+    out: &mut String,
+) {
+    for step in match_siblings(left, right, keys) {
+        match step {
+            Step::Both(i, j) => {
+                diff_pair(&left[i], &right[j], keys, indent, out);
+            }
+            Step::Left(i) => push_subtree(out, '-', &left[i], indent),
+            Step::Right(j) => push_subtree(out, '+', &right[j], indent),
+        }
+    }
+}
+
+/// One classified line of a schema diff (`--xsd`, `--json-schema`).
+pub(crate) struct Change {
+    pub(crate) breaking: bool,
+    pub(crate) line: String,
+}
+
+/// The report of a schema diff: a `breaking` and a `compatible` section, each
+/// listing components as `+`/`-` lines, or as a `~` header over the changes
+/// of that class.
+#[derive(Default)]
+pub(crate) struct SchemaReport {
+    breaking: String,
+    compatible: String,
+}
+
+impl SchemaReport {
+    pub(crate) fn removed(&mut self, header: &str) {
+        self.breaking.push_str(&format!("  - {header}\n"));
+    }
+
+    pub(crate) fn added(&mut self, header: &str) {
+        self.compatible.push_str(&format!("  + {header}\n"));
+    }
+
+    pub(crate) fn changed(&mut self, header: &str, changes: &[Change]) {
+        for (section, breaking) in [(&mut self.breaking, true), (&mut self.compatible, false)] {
+            let mut lines = changes.iter().filter(|c| c.breaking == breaking).peekable();
+            if lines.peek().is_none() {
+                continue;
+            }
+            section.push_str(&format!("  ~ {header}\n"));
+            for change in lines {
+                section.push_str(&format!("      {}\n", change.line));
+            }
+        }
+    }
+
+    /// The report text; empty when nothing changed.
+    pub(crate) fn finish(self) -> String {
+        let mut out = String::new();
+        for (title, section) in [("breaking", self.breaking), ("compatible", self.compatible)] {
+            if !section.is_empty() {
+                out.push_str(title);
+                out.push('\n');
+                out.push_str(&section);
+            }
+        }
+        out
+    }
+}
+
+/// Read and parse one side of the diff into its root elements.
+fn load(file_path: &str, format_override: Option<&str>) -> Result<Vec<XmlElement>> {
+    let content = read_file_lenient(file_path)?;
+    let format = match format_override {
+        Some(name) => InputFormat::from_name(name)?,
+        None => detect_format(&content, file_path),
+    };
+    Ok(match format {
+        InputFormat::Xml => {
+            parse_xml(&content)
+                .with_context(|| format!("Failed to parse XML: {file_path}"))?
+                .roots
+        }
+        InputFormat::Html => {
+            parse_html(&content).with_context(|| format!("Failed to parse HTML: {file_path}"))?
+        }
+        InputFormat::Json => {
+            json_to_elements(&content).with_context(|| format!("In {file_path}"))?
+        }
+    })
+}
+
+/// Diff two parsed documents; empty when they are structurally equal.
+pub(crate) fn diff_documents(
+    mut left: Vec<XmlElement>,
+    right: Vec<XmlElement>,
+    options: &CanonicalOptions,
+) -> String {
+    // Rebind prefixes over both documents at once, so one URI gets the same
+    // canonical prefix on each side.
+    let split = left.len();
+    left.extend(right);
+    canonicalize(&mut left, false, options);
+    let right = left.split_off(split);
+
+    let mut out = String::new();
+    diff_siblings(&left, &right, &options.keys, 0, &mut out);
+    out
+}
+
+/// `unxml diff`: print the structural diff of the two inputs. Returns whether
+/// they differ, for the `diff(1)`-style exit status.
+pub(crate) fn run_diff(args: &DiffCli) -> Result<bool> {
+    let out = diff_files(args)?;
+    print!("{out}");
+    Ok(!out.is_empty())
+}
+
+/// The diff `unxml diff` prints for `args`; empty when the inputs match.
+pub(crate) fn diff_files(args: &DiffCli) -> Result<String> {
+    if args.json_schema {
+        return diff_json_schemas(
+            &read_file_lenient(&args.old)?,
+            &read_file_lenient(&args.new)?,
+        );
+    }
+    let left = load(&args.old, args.format.as_deref())?;
+    let right = load(&args.new, args.format.as_deref())?;
+    if args.xsd {
+        return Ok(diff_schemas(&left, &right));
+    }
+    let mut options = CanonicalOptions::parse(
+        &args.key,
+        &args.mask,
+        &args.normalize,
+        args.schema.as_deref(),
+    )?;
+    options.set_ignore(&args.ignore_elem, &args.ignore_attr, args.ignore_preset)?;
+    options.ns = NsPrefixes::new(
+        KnownPrefixes::load(&args.ns_prefix)?,
+        args.ns_map.as_deref(),
+        args.ns_hash,
+    )?;
+    Ok(diff_documents(left, right, &options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::diff_documents;
+    use crate::canonical::CanonicalOptions;
+    use crate::json::json_to_elements;
+    use crate::parse::parse_xml;
+
+    fn diff_xml(a: &str, b: &str) -> String {
+        diff_documents(
+            parse_xml(a).unwrap().roots,
+            parse_xml(b).unwrap().roots,
+            &CanonicalOptions::default(),
+        )
+    }
+
+    #[test]
+    fn reports_changes_on_keyed_records_in_place() {
+        let a = r#"<order><line id="1"><qty>2</qty></line><line id="2" note="x"><qty>1</qty></line><total>3</total></order>"#;
+        let b = r#"<order><line id="2"><qty>5</qty></line><line id="1"><qty>2</qty></line><total>3</total><memo>rush</memo></order>"#;
+        assert_eq!(
+            diff_xml(a, b),
+            concat!(
+                "  order\n",
+                "~   line(id=\"2\")\n",
+                "-     @note = x\n",
+                "~     qty = 1 → 5\n",
+                "+   memo = rush\n",
+            )
+        );
+        assert_eq!(diff_xml(a, a), "");
+    }
+
+    #[test]
+    fn diffs_json_members_and_array_items() {
+        let a = r#"{"name":"svc","owners":[{"id":1,"team":"a"},{"id":2,"team":"b"}]}"#;
+        let b = r#"{"name":"svc","owners":[{"id":2,"team":"c"}],"port":80}"#;
+        let diff = diff_documents(
+            json_to_elements(a).unwrap(),
+            json_to_elements(b).unwrap(),
+            &CanonicalOptions::default(),
+        );
+        assert_eq!(
+            diff,
+            concat!(
+                "- owners[]\n",
+                "-   id = 1\n",
+                "-   team = a\n",
+                "  owners[][id=2]\n",
+                "~   team = b → c\n",
+                "+ port = 80\n",
+            )
+        );
+    }
+}
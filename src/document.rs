@@ -4,6 +4,7 @@
 use std::collections::HashSet;
 use std::path::Path;
 
+use crate::canonical::KnownPrefixes;
 use crate::model::{FormatOpts, XmlElement};
 
 /// Pick a processing mode from a file's extension when the user hasn't forced
@@ -194,6 +195,38 @@ pub(crate) fn is_msbuild_document(root: &XmlElement) -> bool {
                 .any(|c| MSBUILD_CHILDREN.contains(&c.name.as_str())))
 }
 
+/// The dialect mode a document element announces, for `--auto` on content
+/// whose extension didn't pick one (stdin, `.xml`): an `xsl:stylesheet`,
+/// `xsl:transform` or `xsl:package` root is XSLT, `xs:schema` XSD,
+/// `wsdl:definitions` (1.1) or `description` (2.0) WSDL, `sch:schema` (ISO or
+/// 1.5) Schematron, and an MSBuild `<Project>` (see `is_msbuild_document`)
+/// MSBuild. Matched on local name and namespace URI, so any prefix works.
+/// Must run before `--hide-ns` drops the root's prefix and its declaration.
+pub(crate) fn sniff_mode(root: &XmlElement) -> Option<FormatOpts> {
+    let (prefix, local) = match root.name.split_once(':') {
+        Some((prefix, local)) => (Some(prefix), local),
+        None => (None, root.name.as_str()),
+    };
+    let declaration = prefix.map_or_else(|| "xmlns".to_string(), |p| format!("xmlns:{p}"));
+    let uri = root.attributes.get(&declaration).map_or("", String::as_str);
+    let mut mode = FormatOpts::default();
+    match (uri, local) {
+        ("http://www.w3.org/1999/XSL/Transform", "stylesheet" | "transform" | "package") => {
+            mode.xslt = true
+        }
+        ("http://www.w3.org/2001/XMLSchema", "schema") => mode.xsd = true,
+        ("http://schemas.xmlsoap.org/wsdl/", "definitions")
+        | ("http://www.w3.org/ns/wsdl", "description") => mode.wsdl = true,
+        (
+            "http://purl.oclc.org/dsdl/schematron" | "http://www.ascc.net/xml/schematron",
+            "schema",
+        ) => mode.schematron = true,
+        _ if is_msbuild_document(root) => mode.msbuild = true,
+        _ => return None,
+    }
+    Some(mode)
+}
+
 /// Sniff well-known document types from the root elements' namespace bindings
 /// and return the set of prefixes worth hiding. Recognises two families:
 /// - UBL: for a genuine UBL instance document, prefixes bound to the
@@ -206,7 +239,13 @@ pub(crate) fn is_msbuild_document(root: &XmlElement) -> bool {
 /// Prefixes are matched by URI, so it works regardless of the actual prefix the
 /// document chose. Non-matching documents, and stylesheets/schemas that merely
 /// reference these vocabularies, contribute nothing.
-pub(crate) fn sniff_hidden_prefixes(elements: &[XmlElement]) -> HashSet<String> {
+///
+/// User vocabularies declared with `hide` (see `KnownPrefixes`) are hidden the
+/// same way, in documents whose root is in a user-declared namespace.
+pub(crate) fn sniff_hidden_prefixes(
+    elements: &[XmlElement],
+    known: &KnownPrefixes,
+) -> HashSet<String> {
     const UBL_MARKERS: [&str; 2] = ["CommonBasicComponents", "CommonAggregateComponents"];
     const CII_MARKERS: [&str; 5] = [
         "CrossIndustryInvoice",
@@ -217,6 +256,20 @@ pub(crate) fn sniff_hidden_prefixes(elements: &[XmlElement]) -> HashSet<String>
     ];
     let mut hidden = HashSet::new();
     for root in elements {
+        let root_prefix = root.name.split_once(':').map_or("", |(prefix, _)| prefix);
+        let root_ns = match root_prefix {
+            "" => root.attributes.get("xmlns"),
+            prefix => root.attributes.get(&format!("xmlns:{prefix}")),
+        };
+        if root_ns.is_some_and(|uri| known.declares(uri)) {
+            for (key, value) in &root.attributes {
+                if let Some(pfx) = key.strip_prefix("xmlns:")
+                    && known.hides(value)
+                {
+                    hidden.insert(pfx.to_string());
+                }
+            }
+        }
         let markers: &[&str] = if is_ubl_document(root) {
             &UBL_MARKERS
         } else if is_cii_document(root) {
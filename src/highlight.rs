@@ -90,14 +90,38 @@ fn highlight_spans(
     Ok(generator.finalize())
 }
 
+/// Where a page's stylesheet comes from.
+pub(crate) enum Css<'a> {
+    /// Inlined in a `<style>` block (`--html-embed-css`).
+    Embed,
+    /// Linked at this URL: `unxml.css` beside the page, or a relative path to
+    /// the one at the root of an `--out-dir` tree.
+    Link(&'a str),
+}
+
+impl Css<'_> {
+    /// The `--html-embed-css` choice: inline, or link a sibling `unxml.css`.
+    pub(crate) fn new(embed: bool) -> Self {
+        if embed {
+            Css::Embed
+        } else {
+            Css::Link("unxml.css")
+        }
+    }
+
+    /// The `<style>` or `<link>` element for a page's `<head>`.
+    fn tag(&self) -> Result<String> {
+        Ok(match self {
+            Css::Embed => format!("<style>\n{}</style>", html_css()?),
+            Css::Link(href) => format!("<link rel=\"stylesheet\" href=\"{href}\">"),
+        })
+    }
+}
+
 /// Wrap already-highlighted `spans` in the standalone page chrome shared by
 /// `html_page` and `html_page_raw`.
-fn page(spans: &str, embed_css: bool) -> Result<String> {
-    let stylesheet = if embed_css {
-        format!("<style>\n{}</style>", html_css()?)
-    } else {
-        "<link rel=\"stylesheet\" href=\"unxml.css\">".to_string()
-    };
+fn page(spans: &str, css: &Css) -> Result<String> {
+    let stylesheet = css.tag()?;
 
     Ok(format!(
         "<!doctype html>\n\
@@ -116,13 +140,13 @@ fn page(spans: &str, embed_css: bool) -> Result<String> {
 }
 
 /// Render `body` (already-processed `unxml` output) as a standalone HTML
-/// page with classed spans. With `embed_css`, the stylesheet `--html-css`
+/// page with classed spans. With `Css::Embed`, the stylesheet `--html-css`
 /// would otherwise produce is inlined in a `<style>` block instead of
-/// linked as `unxml.css`, so the page has no sibling file to keep with it.
-pub(crate) fn html_page(body: &str, embed_css: bool) -> Result<String> {
+/// linked, so the page has no sibling file to keep with it.
+pub(crate) fn html_page(body: &str, css: &Css) -> Result<String> {
     let syntax_set = syntax_set()?;
     let syntax = find_syntax(&syntax_set, "UnXML")?;
-    page(&highlight_spans(&syntax_set, syntax, body)?, embed_css)
+    page(&highlight_spans(&syntax_set, syntax, body)?, css)
 }
 
 /// Like `html_page`, but for `--raw`: highlights `source` as-is (no unxml
@@ -131,10 +155,10 @@ pub(crate) fn html_page(body: &str, embed_css: bool) -> Result<String> {
 /// stylesheet is keyed by the theme's generic TextMate scope names
 /// (comment, string, keyword, entity.name.tag, ...) rather than anything
 /// specific to the unxml grammar.
-pub(crate) fn html_page_raw(source: &str, syntax_name: &str, embed_css: bool) -> Result<String> {
+pub(crate) fn html_page_raw(source: &str, syntax_name: &str, css: &Css) -> Result<String> {
     let syntax_set = raw_syntax_set();
     let syntax = find_syntax(&syntax_set, syntax_name)?;
-    page(&highlight_spans(&syntax_set, syntax, source)?, embed_css)
+    page(&highlight_spans(&syntax_set, syntax, source)?, css)
 }
 
 /// Highlight `body` through `syntax` as ANSI-escaped text for a terminal.
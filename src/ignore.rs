@@ -0,0 +1,283 @@
+//! Dropping noise before comparison (`--ignore-attr`, `--ignore-elem`).
+//!
+//! Some attributes and elements carry nothing a comparison cares about:
+//! `xsi:schemaLocation` hints, an MSBuild `ToolsVersion`, ASP.NET's generated
+//! `id="ctl00_…"`, a UBL invoice's regenerated signature extensions. Ignore
+//! rules strip them from the tree before anything else in the `--canonical`
+//! pass (and in `unxml diff`), so they neither render nor differ:
+//!
+//!  - `--ignore-elem NAME` drops elements (or JSON members) named `NAME`,
+//!    with their whole subtree.
+//!  - `--ignore-attr NAME` drops attributes named `NAME`, `ELEM@NAME` only on
+//!    `ELEM`, and `NAME=VALUE` only when the value matches `VALUE`, in which
+//!    `*` stands for any run of characters. For JSON both name a member.
+//!
+//! Names match like `--select`. `--ignore-preset` adds the rules for the
+//! document's sniffed vocabulary, per root (see `preset`).
+
+use anyhow::Result;
+use serde_json::Value;
+
+use crate::document::{is_msbuild_document, is_ubl_document, name_matches_select};
+use crate::model::{NodeRef, XmlElement};
+
+/// Rules every document gets under `--ignore-preset`.
+const COMMON_ATTRS: &[&str] = &["xsi:schemaLocation", "xsi:noNamespaceSchemaLocation"];
+
+/// The extra `--ignore-preset` rules for a root's vocabulary, as
+/// `(elements, attributes)` specs.
+fn preset(root: &XmlElement) -> (&'static [&'static str], &'static [&'static str]) {
+    if is_ubl_document(root) {
+        // Signatures and other extensions are regenerated on every export.
+        (&["UBLExtensions"], &[])
+    } else if is_msbuild_document(root) {
+        (&[], &["Project@ToolsVersion"])
+    } else if name_matches_select(&root.name, "html") {
+        // ASP.NET WebForms control IDs and names, renumbered by the page tree.
+        (&[], &["id=ctl00_*", "name=ctl00$*"])
+    } else {
+        (&[], &[])
+    }
+}
+
+/// Whether `text` matches `pattern`, where `*` matches any run of characters.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// One `--ignore-attr` pattern.
+#[derive(Debug, Clone)]
+struct AttrRule {
+    element: Option<String>,
+    name: String,
+    value: Option<String>,
+}
+
+impl AttrRule {
+    /// Parse `[ELEM@]NAME[=VALUE]`; a leading `@` is accepted as in `--mask`.
+    fn parse(spec: &str) -> Result<Self> {
+        let (names, value) = match spec.split_once('=') {
+            Some((names, value)) => (names, Some(value.to_string())),
+            None => (spec, None),
+        };
+        let (element, name) = match names.split_once('@') {
+            Some((element, name)) => ((!element.is_empty()).then(|| element.to_string()), name),
+            None => (None, names),
+        };
+        if name.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Invalid --ignore-attr '{spec}': expected [ELEM@]NAME[=VALUE]"
+            ));
+        }
+        Ok(Self {
+            element,
+            name: name.to_string(),
+            value,
+        })
+    }
+
+    fn matches(&self, element: &str, name: &str, value: &str) -> bool {
+        name_matches_select(name, &self.name)
+            && self
+                .element
+                .as_deref()
+                .is_none_or(|scope| name_matches_select(element, scope))
+            && self
+                .value
+                .as_deref()
+                .is_none_or(|pattern| glob_matches(pattern, value))
+    }
+}
+
+/// The `--ignore-elem` / `--ignore-attr` rules of one run.
+#[derive(Debug, Default)]
+pub(crate) struct IgnoreRules {
+    elements: Vec<String>,
+    attributes: Vec<AttrRule>,
+    preset: bool,
+}
+
+impl IgnoreRules {
+    /// Build from the raw `--ignore-elem` and `--ignore-attr` values and
+    /// `--ignore-preset`.
+    pub(crate) fn parse(elements: &[String], attributes: &[String], preset: bool) -> Result<Self> {
+        let mut rules = Self {
+            preset,
+            ..Self::default()
+        };
+        for spec in elements {
+            if spec.is_empty() {
+                return Err(anyhow::anyhow!("Invalid empty --ignore-elem"));
+            }
+            rules.elements.push(spec.clone());
+        }
+        for spec in attributes {
+            rules.attributes.push(AttrRule::parse(spec)?);
+        }
+        Ok(rules)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.elements.is_empty() && self.attributes.is_empty() && !self.preset
+    }
+
+    /// Strip ignored attributes and elements from every root, adding each
+    /// root's preset rules under `--ignore-preset`.
+    pub(crate) fn strip(&self, roots: &mut [XmlElement]) {
+        for root in roots {
+            if self.preset {
+                let (elements, attributes) = preset(root);
+                let mut rules = Self {
+                    elements: self.elements.clone(),
+                    attributes: self.attributes.clone(),
+                    preset: false,
+                };
+                rules
+                    .elements
+                    .extend(elements.iter().map(|name| name.to_string()));
+                for spec in COMMON_ATTRS.iter().chain(attributes) {
+                    rules
+                        .attributes
+                        .push(AttrRule::parse(spec).expect("valid preset"));
+                }
+                rules.strip_element(root);
+            } else {
+                self.strip_element(root);
+            }
+        }
+    }
+
+    /// Whether `name` is an ignored element. JSON array items are `name[]`
+    /// elements in the diff tree, so the suffix is ignored.
+    fn ignores_element(&self, name: &str) -> bool {
+        let name = name.trim_end_matches("[]");
+        self.elements
+            .iter()
+            .any(|pattern| name_matches_select(name, pattern))
+    }
+
+    fn strip_element(&self, elem: &mut XmlElement) {
+        let element = &elem.name;
+        let rules = &self.attributes;
+        elem.attributes.retain(|name, value| {
+            name == "xmlns"
+                || name.starts_with("xmlns:")
+                || !rules.iter().any(|rule| rule.matches(element, name, value))
+        });
+
+        if elem.children.iter().any(|c| self.ignores_element(&c.name)) {
+            // Renumber the surviving children and drop the removed ones'
+            // `nodes` entries, so document order stays intact.
+            let mut renumbered = Vec::with_capacity(elem.children.len());
+            let mut kept = Vec::new();
+            for child in elem.children.drain(..) {
+                if self.ignores_element(&child.name) {
+                    renumbered.push(None);
+                } else {
+                    renumbered.push(Some(kept.len()));
+                    kept.push(child);
+                }
+            }
+            elem.children = kept;
+            elem.nodes = elem
+                .nodes
+                .drain(..)
+                .filter_map(|node| match node {
+                    NodeRef::Child(i) => renumbered[i].map(NodeRef::Child),
+                    other => Some(other),
+                })
+                .collect();
+            elem.inner_source = None;
+        }
+        for child in &mut elem.children {
+            self.strip_element(child);
+        }
+    }
+
+    /// Strip ignored members from a JSON value: `--ignore-elem` and
+    /// `--ignore-attr` rules both name object members.
+    pub(crate) fn strip_json(&self, value: &mut Value) {
+        match value {
+            Value::Object(object) => {
+                object.retain(|key, member| {
+                    let scalar = match member {
+                        Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    };
+                    !self.ignores_element(key)
+                        && !self
+                            .attributes
+                            .iter()
+                            .any(|rule| rule.matches("", key, &scalar))
+                });
+                for member in object.values_mut() {
+                    self.strip_json(member);
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.strip_json(item);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IgnoreRules, glob_matches};
+    use crate::parse::parse_xml;
+
+    #[test]
+    fn matches_value_globs() {
+        assert!(glob_matches("ctl00_*", "ctl00_Main_Grid"));
+        assert!(glob_matches("*_tmp*", "a_tmp_b"));
+        assert!(glob_matches("exact", "exact"));
+        assert!(!glob_matches("ctl00_*", "main"));
+        assert!(!glob_matches("a*b", "ab_c"));
+    }
+
+    #[test]
+    fn strips_rules_and_presets() {
+        let rules = IgnoreRules::parse(
+            &["Generated".to_string()],
+            &["id=ctl00_*".to_string(), "row@stamp".to_string()],
+            true,
+        )
+        .unwrap();
+        let mut roots = parse_xml(concat!(
+            r#"<doc xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="a b">"#,
+            r#"<row id="ctl00_x" stamp="1"/><Generated>t</Generated>"#,
+            r#"<row id="keep" stamp="2"/><other stamp="3"/>"#,
+            "</doc>",
+        ))
+        .unwrap()
+        .roots;
+        rules.strip(&mut roots);
+        let doc = &roots[0];
+        assert!(!doc.attributes.contains_key("xsi:schemaLocation"));
+        assert!(doc.attributes.contains_key("xmlns:xsi"));
+        let names: Vec<&str> = doc.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["row", "row", "other"]);
+        assert!(doc.children[0].attributes.is_empty());
+        assert_eq!(doc.children[1].attributes.len(), 1);
+        assert_eq!(doc.children[2].attributes["stamp"], "3");
+        assert_eq!(doc.nodes.len(), 3);
+    }
+}
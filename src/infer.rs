@@ -0,0 +1,481 @@
+//! Schema inference (`--infer-xsd`): derive an XML Schema from instances.
+//!
+//! Every input document is treated as an instance of one schema. Like
+//! `--paths`, elements are accumulated per distinct path, so each node of the
+//! resulting schema summarises every occurrence of that path:
+//!
+//!  - **Nesting** — one nested anonymous type per path (Russian-doll style), so
+//!    a `Line/Qty` and a `Total/Qty` get independent declarations.
+//!  - **Cardinality** — `minOccurs="0"` when some parent occurrence lacked the
+//!    child, `maxOccurs="unbounded"` when any had more than one. Children seen
+//!    in a consistent order form an `xs:sequence`; if the order varies between
+//!    occurrences (or a child reappears after a sibling) they fall back to an
+//!    unbounded `xs:choice`.
+//!  - **Attributes** — `use="required"` when present on every occurrence.
+//!  - **Simple types** — the narrowest built-in type every observed value
+//!    satisfies: `boolean`, `integer`, `decimal`, `date`, `dateTime`, else
+//!    `string`.
+//!
+//! Names are declared by local name in the root element's namespace (the
+//! `targetNamespace`). Prefixed attributes (`xsi:*`, `xml:lang`, foreign
+//! vocabularies) and namespace declarations are left out: declaring them
+//! would need schema imports the instances alone cannot provide.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{Context, Result};
+
+use crate::model::{FormatOpts, NodeRef, XmlElement};
+use crate::parse::parse_xml;
+use crate::types::{is_xsd_date, is_xsd_datetime};
+use crate::write::write_xml;
+
+const XS_NS: &str = "http://www.w3.org/2001/XMLSchema";
+
+/// Bit flags for the built-in types a value satisfies. Merging observations
+/// intersects the flags; the inferred type is the narrowest flag that survives.
+const BOOLEAN: u8 = 1;
+const INTEGER: u8 = 2;
+const DECIMAL: u8 = 4;
+const DATE: u8 = 8;
+const DATE_TIME: u8 = 16;
+
+/// Narrowest first: the first surviving flag names the inferred type.
+const TYPE_ORDER: [(u8, &str); 5] = [
+    (BOOLEAN, "boolean"),
+    (INTEGER, "integer"),
+    (DECIMAL, "decimal"),
+    (DATE, "date"),
+    (DATE_TIME, "dateTime"),
+];
+
+/// The built-in types one lexical value satisfies.
+fn value_flags(value: &str) -> u8 {
+    let mut flags = 0;
+    if matches!(value, "true" | "false") {
+        flags |= BOOLEAN;
+    }
+    let unsigned = value.strip_prefix(['+', '-']).unwrap_or(value);
+    let (int_part, frac_part) = match unsigned.split_once('.') {
+        Some((i, f)) => (i, Some(f)),
+        None => (unsigned, None),
+    };
+    let all_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if !int_part.is_empty() && all_digits(int_part) {
+        match frac_part {
+            None => flags |= INTEGER | DECIMAL,
+            Some(f) if !f.is_empty() && all_digits(f) => flags |= DECIMAL,
+            _ => {}
+        }
+    }
+    if is_xsd_date(value) {
+        flags |= DATE;
+    }
+    if is_xsd_datetime(value) {
+        flags |= DATE_TIME;
+    }
+    flags
+}
+
+/// The running type inference for a set of values. `flags` is `None` until
+/// the first non-empty value is seen, so an all-empty path can be told apart
+/// from a string one; `empty` records an empty value wherever it occurs, so
+/// the result does not depend on the order of the values.
+#[derive(Default, Clone, Copy)]
+struct TypeGuess {
+    flags: Option<u8>,
+    empty: bool,
+}
+
+impl TypeGuess {
+    fn observe(&mut self, value: &str) {
+        if value.is_empty() {
+            self.empty = true;
+            return;
+        }
+        let flags = value_flags(value);
+        self.flags = Some(self.flags.map_or(flags, |seen| seen & flags));
+    }
+
+    fn seen(&self) -> bool {
+        self.flags.is_some()
+    }
+
+    /// The inferred built-in type's local name (`string` when nothing
+    /// narrower fits every value, or when no value was seen). An empty value
+    /// satisfies none of the narrower types.
+    fn type_name(&self) -> &'static str {
+        let flags = if self.empty {
+            0
+        } else {
+            self.flags.unwrap_or(0)
+        };
+        TYPE_ORDER
+            .iter()
+            .find(|(flag, _)| flags & flag != 0)
+            .map_or("string", |(_, name)| name)
+    }
+}
+
+/// What is seen on one attribute name across a path's occurrences.
+#[derive(Default)]
+struct AttrInfo {
+    count: usize,
+    guess: TypeGuess,
+}
+
+/// Per-child cardinality across a path's occurrences: in how many parent
+/// occurrences the child appeared, and its fewest/most repetitions there.
+#[derive(Default)]
+struct Occurs {
+    present: usize,
+    min: usize,
+    max: usize,
+}
+
+/// Everything accumulated for one distinct element path.
+#[derive(Default)]
+struct PathInfo {
+    name: String,
+    count: usize,
+    text: TypeGuess,
+    mixed: bool,
+    attrs: BTreeMap<String, AttrInfo>,
+    /// Child local names in merged first-seen order.
+    children: Vec<String>,
+    occurs: HashMap<String, Occurs>,
+    /// False once two occurrences disagree on child order (or a child name
+    /// reappears after a different sibling).
+    ordered: bool,
+}
+
+/// The local part of a qualified name.
+fn local(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// Fold one element occurrence (and, recursively, its subtree) into `acc`.
+fn collect(elem: &XmlElement, parent: &str, acc: &mut BTreeMap<String, PathInfo>) {
+    let name = local(&elem.name);
+    let path = if parent.is_empty() {
+        name.to_string()
+    } else {
+        format!("{parent}/{name}")
+    };
+
+    let info = acc.entry(path.clone()).or_insert_with(|| PathInfo {
+        name: name.to_string(),
+        ordered: true,
+        ..PathInfo::default()
+    });
+    info.count += 1;
+
+    for (key, value) in &elem.attributes {
+        if key.contains(':') || key == "xmlns" {
+            continue;
+        }
+        let attr = info.attrs.entry(key.clone()).or_default();
+        attr.count += 1;
+        attr.guess.observe(value);
+    }
+
+    if elem.children.is_empty() {
+        info.text.observe(elem.text_content.trim());
+    } else if elem.is_mixed() {
+        info.mixed = true;
+    }
+
+    // Runs of same-named children in document order, e.g. [A, B, B, C] →
+    // [(A, 1), (B, 2), (C, 1)]. A name occurring in two separate runs means
+    // this occurrence interleaves its children, so no sequence can describe it.
+    let mut runs: Vec<(&str, usize)> = Vec::new();
+    for child in &elem.children {
+        let child_name = local(&child.name);
+        match runs.last_mut() {
+            Some((last, n)) if *last == child_name => *n += 1,
+            _ => {
+                if runs.iter().any(|(seen, _)| *seen == child_name) {
+                    info.ordered = false;
+                }
+                runs.push((child_name, 1));
+            }
+        }
+    }
+
+    // Merge this occurrence's child order into the path's: known names must
+    // appear in increasing merged position.
+    let mut cursor = 0;
+    for (i, (child_name, _)) in runs.iter().enumerate() {
+        match info.children.iter().position(|c| c == child_name) {
+            Some(pos) if pos >= cursor => cursor = pos + 1,
+            Some(_) => info.ordered = false,
+            None => {
+                // Slot a new name in before the next already-known sibling of
+                // this occurrence, or at the end when none follows.
+                let next_known = runs[i + 1..]
+                    .iter()
+                    .find_map(|(next, _)| info.children.iter().position(|c| c == next));
+                let at = next_known.map_or(info.children.len(), |pos| pos.max(cursor));
+                info.children.insert(at, child_name.to_string());
+                cursor = at + 1;
+            }
+        }
+    }
+
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for (child_name, n) in &runs {
+        *counts.entry(child_name).or_default() += n;
+    }
+    for (child_name, n) in counts {
+        let occurs = info.occurs.entry(child_name.to_string()).or_default();
+        occurs.min = if occurs.present == 0 {
+            n
+        } else {
+            occurs.min.min(n)
+        };
+        occurs.max = occurs.max.max(n);
+        occurs.present += 1;
+    }
+
+    for child in &elem.children {
+        collect(child, &path, acc);
+    }
+}
+
+/// Build an `xs:` element with the given attributes, keeping `nodes` in step
+/// with `children` (the XSD renderer walks `nodes` for folded sequences).
+fn xs(name: &str, attrs: &[(&str, String)]) -> XmlElement {
+    let mut elem = XmlElement::new(format!("xs:{name}"));
+    for (key, value) in attrs {
+        elem.attributes.insert((*key).to_string(), value.clone());
+    }
+    elem
+}
+
+fn push(parent: &mut XmlElement, child: XmlElement) {
+    parent.nodes.push(NodeRef::Child(parent.children.len()));
+    parent.children.push(child);
+}
+
+/// The `xs:attribute` declarations for a path, sorted by name.
+fn attribute_decls(info: &PathInfo) -> Vec<XmlElement> {
+    info.attrs
+        .iter()
+        .map(|(name, attr)| {
+            let mut attrs = vec![
+                ("name", name.clone()),
+                ("type", format!("xs:{}", attr.guess.type_name())),
+            ];
+            if attr.count == info.count {
+                attrs.push(("use", "required".to_string()));
+            }
+            xs("attribute", &attrs)
+        })
+        .collect()
+}
+
+/// The `xs:element` declaration for `path`, with `occurs` already resolved by
+/// the parent (empty for a global element).
+fn element_decl(
+    path: &str,
+    acc: &BTreeMap<String, PathInfo>,
+    occurs: Vec<(&str, String)>,
+) -> XmlElement {
+    let info = &acc[path];
+    let mut attrs = vec![("name", info.name.clone())];
+    attrs.extend(occurs);
+
+    // A leaf with no attributes is a plain simple-typed element.
+    if info.children.is_empty() && info.attrs.is_empty() {
+        if info.text.seen() {
+            attrs.push(("type", format!("xs:{}", info.text.type_name())));
+            return xs("element", &attrs);
+        }
+        let mut elem = xs("element", &attrs);
+        push(&mut elem, xs("complexType", &[]));
+        return elem;
+    }
+
+    let mut elem = xs("element", &attrs);
+    let mut complex = if info.mixed {
+        xs("complexType", &[("mixed", "true".to_string())])
+    } else {
+        xs("complexType", &[])
+    };
+
+    if info.children.is_empty() {
+        // Attributes only, optionally over simple text content.
+        if info.text.seen() {
+            let mut content = xs("simpleContent", &[]);
+            let mut ext = xs(
+                "extension",
+                &[("base", format!("xs:{}", info.text.type_name()))],
+            );
+            for attr in attribute_decls(info) {
+                push(&mut ext, attr);
+            }
+            push(&mut content, ext);
+            push(&mut complex, content);
+        } else {
+            for attr in attribute_decls(info) {
+                push(&mut complex, attr);
+            }
+        }
+        push(&mut elem, complex);
+        return elem;
+    }
+
+    let mut group = if info.ordered {
+        xs("sequence", &[])
+    } else {
+        xs(
+            "choice",
+            &[
+                ("minOccurs", "0".to_string()),
+                ("maxOccurs", "unbounded".to_string()),
+            ],
+        )
+    };
+    for child in &info.children {
+        let child_path = format!("{path}/{child}");
+        let mut child_occurs = Vec::new();
+        if info.ordered {
+            let o = &info.occurs[child];
+            if o.present < info.count {
+                child_occurs.push(("minOccurs", "0".to_string()));
+            }
+            if o.max > 1 {
+                child_occurs.push(("maxOccurs", "unbounded".to_string()));
+            }
+        }
+        push(&mut group, element_decl(&child_path, acc, child_occurs));
+    }
+    push(&mut complex, group);
+    for attr in attribute_decls(info) {
+        push(&mut complex, attr);
+    }
+    push(&mut elem, complex);
+    elem
+}
+
+/// The namespace URI of a root element: its default namespace when unprefixed,
+/// otherwise the URI bound to its own prefix.
+fn root_namespace(root: &XmlElement) -> Option<&String> {
+    match root.name.split_once(':') {
+        Some((prefix, _)) => root.attributes.get(&format!("xmlns:{prefix}")),
+        None => root.attributes.get("xmlns"),
+    }
+}
+
+/// Infer one `xs:schema` describing every root in `documents`.
+pub(crate) fn infer_xsd(documents: &[Vec<XmlElement>]) -> XmlElement {
+    let mut acc = BTreeMap::new();
+    let mut roots: Vec<String> = Vec::new();
+    let mut target_ns = None;
+    for roots_of_doc in documents {
+        for root in roots_of_doc {
+            let name = local(&root.name).to_string();
+            if !roots.contains(&name) {
+                roots.push(name);
+            }
+            if target_ns.is_none() {
+                target_ns = root_namespace(root).cloned();
+            }
+            collect(root, "", &mut acc);
+        }
+    }
+
+    let mut attrs = vec![("xmlns:xs", XS_NS.to_string())];
+    if let Some(ns) = target_ns {
+        attrs.push(("xmlns", ns.clone()));
+        attrs.push(("targetNamespace", ns));
+        attrs.push(("elementFormDefault", "qualified".to_string()));
+    }
+    let mut schema = xs("schema", &attrs);
+    for root in &roots {
+        push(&mut schema, element_decl(root, &acc, Vec::new()));
+    }
+    schema
+}
+
+/// `--infer-xsd`: parse every `(name, content)` source as an instance and
+/// return the inferred schema — as `.xsd` text with `emit_schema`, otherwise
+/// in the compact `--xsd` rendering.
+pub(crate) fn run_infer_xsd(sources: &[(String, String)], emit_schema: bool) -> Result<String> {
+    let mut documents = Vec::new();
+    for (name, content) in sources {
+        let parsed = parse_xml(content).with_context(|| format!("Failed to parse XML: {name}"))?;
+        documents.push(parsed.roots);
+    }
+    let schema = infer_xsd(&documents);
+    Ok(if emit_schema {
+        write_xml(&[schema])
+    } else {
+        let opts = FormatOpts {
+            xsd: true,
+            ..FormatOpts::default()
+        };
+        schema.format_yaml_like(0, &opts, None)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TypeGuess, run_infer_xsd, value_flags};
+
+    #[test]
+    fn infers_narrowest_common_type() {
+        let mut guess = TypeGuess::default();
+        for v in ["1", "42", "-3"] {
+            guess.observe(v);
+        }
+        assert_eq!(guess.type_name(), "integer");
+        guess.observe("2.50");
+        assert_eq!(guess.type_name(), "decimal");
+        guess.observe("n/a");
+        assert_eq!(guess.type_name(), "string");
+        assert_eq!(
+            value_flags("2026-10-18T08:30:00Z") & super::DATE_TIME,
+            super::DATE_TIME
+        );
+        // Multibyte text, where a zone would start mid-character.
+        guess = TypeGuess::default();
+        for v in ["€abcde", "2026-10-18T08:30:00€0:00", "x+01:00"] {
+            guess.observe(v);
+        }
+        assert_eq!(guess.type_name(), "string");
+    }
+
+    #[test]
+    fn infers_occurrences_and_attribute_use() {
+        let a = r#"<order id="1"><line sku="A"><qty>2</qty></line><line sku="B" note="x"><qty>1</qty></line></order>"#;
+        let b = r#"<order id="2"><note>rush</note></order>"#;
+        let sources = vec![
+            ("a.xml".to_string(), a.to_string()),
+            ("b.xml".to_string(), b.to_string()),
+        ];
+        assert_eq!(
+            run_infer_xsd(&sources, false).unwrap(),
+            concat!(
+                "schema\n",
+                "  element order\n",
+                "    line *\n",
+                "      type\n",
+                "        qty : xs:integer\n",
+                "        @note : xs:string\n",
+                "        @sku : xs:string (required)\n",
+                "    note : xs:string ?\n",
+                "    @id : xs:integer (required)\n",
+            )
+        );
+    }
+
+    #[test]
+    fn empty_leaves_type_the_same_in_any_order() {
+        for doc in ["<r><a></a><a>1</a></r>", "<r><a>1</a><a></a></r>"] {
+            let sources = vec![("a.xml".to_string(), doc.to_string())];
+            let schema = run_infer_xsd(&sources, false).unwrap();
+            assert!(schema.contains("a : xs:string +"), "{doc}: {schema}");
+        }
+    }
+}
@@ -11,6 +11,8 @@ use std::process::Command;
 use anyhow::{Context, Result};
 use include_dir::{Dir, include_dir};
 
+use crate::config::Config;
+
 /// The skills tree, embedded at build time from `skills/`.
 static SKILLS_DIR: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/skills");
 
@@ -30,6 +32,16 @@ fn home_dir() -> Result<PathBuf> {
         .context("Could not determine home directory (neither HOME nor USERPROFILE is set)")
 }
 
+/// The unxml config directory: `$XDG_CONFIG_HOME/unxml`, else
+/// `~/.config/unxml`. `None` when neither can be resolved.
+pub(crate) fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| home_dir().ok().map(|home| home.join(".config")))
+        .map(|dir| dir.join("unxml"))
+}
+
 /// Recursively write an embedded directory's contents under `dest`, creating
 /// directories as needed and overwriting existing files.
 fn extract_dir(dir: &Dir<'_>, dest: &Path) -> Result<usize> {
@@ -135,6 +147,29 @@ const GIT_PATTERNS: &[&str] = &[
 /// normal `cargo install --path .` outcome).
 const GIT_TEXTCONV: &str = "unxml --canonical --auto";
 
+/// The patterns `--init-git --merge` binds to the merge driver: XML only, since
+/// the merged result is written back as XML.
+const GIT_MERGE_PATTERNS: &[&str] = &["*.xml", "*.xsl", "*.xslt", "*.xsd", "*.wsdl", "*.sch"];
+
+/// The merge-driver command stored in git config (`%P` the path, for
+/// `.unxml.toml`'s `[[files]]` rules; `%O` base, `%A` ours and result, `%B`
+/// theirs).
+const GIT_MERGE_DRIVER: &str = "unxml merge-driver --path %P %O %A %B";
+
+/// The patterns bound to the diff driver: the usual XML/HTML globs, plus the
+/// configured ones — `.unxml.toml`'s `[[files]]` and `map` patterns and
+/// `--map` (e.g. `*.config` rendered as MSBuild) — which the textconv command
+/// then applies.
+fn diff_patterns(config: &Config) -> Vec<&str> {
+    let mut patterns = GIT_PATTERNS.to_vec();
+    for pattern in config.patterns() {
+        if !patterns.contains(&pattern) {
+            patterns.push(pattern);
+        }
+    }
+    patterns
+}
+
 /// Run `git` with `args`, capturing output and turning a missing binary into a
 /// clear error.
 fn run_git(args: &[&str]) -> Result<std::process::Output> {
@@ -144,16 +179,17 @@ fn run_git(args: &[&str]) -> Result<std::process::Output> {
         .context("Failed to run `git` (is it installed and on PATH?)")
 }
 
-/// Whether `info/attributes` already binds `pattern` to the unxml driver,
-/// ignoring commented and blank lines so re-runs stay idempotent.
-fn attr_present(existing: &str, pattern: &str) -> bool {
+/// Whether `info/attributes` already binds `pattern` with `attr` (e.g.
+/// `diff=unxml`), ignoring commented and blank lines so re-runs stay
+/// idempotent.
+fn attr_present(existing: &str, pattern: &str, attr: &str) -> bool {
     existing.lines().any(|line| {
         let line = line.trim();
         if line.starts_with('#') {
             return false;
         }
         let mut fields = line.split_whitespace();
-        fields.next() == Some(pattern) && fields.any(|f| f == "diff=unxml")
+        fields.next() == Some(pattern) && fields.any(|f| f == attr)
     })
 }
 
@@ -171,11 +207,11 @@ fn attr_present(existing: &str, pattern: &str) -> bool {
 /// the diff driver is passed via `-c`, and the file-pattern bindings live in
 /// a temporary attributes file pointed to by a transient
 /// `core.attributesFile` override.
-pub(crate) fn git_passthrough(args: &[String]) -> Result<()> {
+pub(crate) fn git_passthrough(args: &[String], config: &Config) -> Result<()> {
     let attrs_path =
         std::env::temp_dir().join(format!("unxml-git-attributes-{}", std::process::id()));
     let mut attrs = String::new();
-    for pattern in GIT_PATTERNS {
+    for pattern in diff_patterns(config) {
         attrs.push_str(&format!("{pattern} diff=unxml\n"));
     }
     std::fs::write(&attrs_path, attrs)
@@ -185,8 +221,6 @@ pub(crate) fn git_passthrough(args: &[String]) -> Result<()> {
         .arg("-c")
         .arg(format!("diff.unxml.textconv={GIT_TEXTCONV}"))
         .arg("-c")
-        .arg("diff.unxml.cachetextconv=true")
-        .arg("-c")
         .arg(format!("core.attributesFile={}", attrs_path.display()))
         .args(args)
         .status()
@@ -198,9 +232,11 @@ pub(crate) fn git_passthrough(args: &[String]) -> Result<()> {
     std::process::exit(status.code().unwrap_or(1));
 }
 
-/// Configure the current git repo to diff XML/HTML through `unxml --canonical`.
-/// Idempotent: re-running only adds patterns not already present.
-pub(crate) fn init_git() -> Result<()> {
+/// Configure the current git repo to diff XML/HTML through `unxml --canonical`,
+/// and with `merge` to merge XML through `unxml merge-driver`. `--map` specs
+/// are stored in the textconv command. Idempotent: re-running only adds
+/// patterns not already present.
+pub(crate) fn init_git(merge: bool, project: &Config, maps: &[String]) -> Result<()> {
     // Locate the git dir; a failure here also doubles as the "not in a repo"
     // check. The path may be relative (`.git`) — that's fine, we run in cwd.
     let out = run_git(&["rev-parse", "--git-dir"])?;
@@ -213,11 +249,22 @@ pub(crate) fn init_git() -> Result<()> {
     let git_dir = String::from_utf8_lossy(&out.stdout).trim().to_string();
     let git_dir = Path::new(&git_dir);
 
-    // 1. Define the diff driver in repo-local config (.git/config).
-    for (key, val) in [
-        ("diff.unxml.textconv", GIT_TEXTCONV),
-        ("diff.unxml.cachetextconv", "true"),
-    ] {
+    // 1. Define the drivers in repo-local config (.git/config). Git runs the
+    //    textconv command through the shell, hence the quoting.
+    let mut textconv = GIT_TEXTCONV.to_string();
+    for spec in maps {
+        textconv.push_str(&format!(" --map '{}'", spec.replace('\'', "'\\''")));
+    }
+    // No `cachetextconv`: git keys its cache by blob, so it would keep serving
+    // renderings made under an older `.unxml.toml`. Earlier versions set it;
+    // drop it from clones configured by them.
+    let _ = run_git(&["config", "--unset", "diff.unxml.cachetextconv"]);
+    let mut config = vec![("diff.unxml.textconv", textconv.as_str())];
+    if merge {
+        config.push(("merge.unxml.name", "unxml structural XML merge"));
+        config.push(("merge.unxml.driver", GIT_MERGE_DRIVER));
+    }
+    for (key, val) in config {
         let out = run_git(&["config", key, val])?;
         if !out.status.success() {
             return Err(anyhow::anyhow!(
@@ -235,11 +282,14 @@ pub(crate) fn init_git() -> Result<()> {
     let attrs_path = info.join("attributes");
     let existing = std::fs::read_to_string(&attrs_path).unwrap_or_default();
 
-    let missing: Vec<&str> = GIT_PATTERNS
-        .iter()
-        .copied()
-        .filter(|p| !attr_present(&existing, p))
+    let mut missing: Vec<(&str, &str)> = diff_patterns(project)
+        .into_iter()
+        .map(|p| (p, "diff=unxml"))
         .collect();
+    if merge {
+        missing.extend(GIT_MERGE_PATTERNS.iter().map(|p| (*p, "merge=unxml")));
+    }
+    missing.retain(|(p, attr)| !attr_present(&existing, p, attr));
 
     if !missing.is_empty() {
         let mut text = existing;
@@ -247,15 +297,18 @@ pub(crate) fn init_git() -> Result<()> {
             text.push('\n');
         }
         text.push_str("# added by `unxml --init-git`\n");
-        for p in &missing {
-            text.push_str(&format!("{p} diff=unxml\n"));
+        for (p, attr) in &missing {
+            text.push_str(&format!("{p} {attr}\n"));
         }
         std::fs::write(&attrs_path, text)
             .with_context(|| format!("Failed to write {}", attrs_path.display()))?;
     }
 
     println!("Configured unxml diff driver in {}", git_dir.display());
-    println!("  diff.unxml.textconv = {GIT_TEXTCONV}");
+    println!("  diff.unxml.textconv = {textconv}");
+    if merge {
+        println!("  merge.unxml.driver = {GIT_MERGE_DRIVER}");
+    }
     if missing.is_empty() {
         println!("  patterns already present in {}", attrs_path.display());
     } else {
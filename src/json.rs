@@ -7,8 +7,35 @@
 use anyhow::{Context, Result};
 use serde_json::{Map, Value};
 
-pub(crate) fn render_json(content: &str, canonical: bool, auto: bool) -> Result<String> {
-    let value: Value = serde_json::from_str(content).context("Failed to parse JSON")?;
+use crate::canonical::{CanonicalOptions, KeyRule};
+use crate::mask::Masker;
+#[cfg(feature = "cli")]
+use crate::model::{NodeRef, XmlElement};
+
+/// Render a JSON document. `canonical` (under `--canonical`) sorts object keys,
+/// applies its `--ignore-*`, `--mask` and `--normalize` rules, and its `--key`
+/// rules to arrays of objects.
+pub fn render_json(
+    content: &str,
+    canonical: Option<&CanonicalOptions>,
+    auto: bool,
+) -> Result<String> {
+    let mut value: Value = serde_json::from_str(content).context("Failed to parse JSON")?;
+    if let Some(options) = canonical {
+        if !options.ignore.is_empty() {
+            options.ignore.strip_json(&mut value);
+        }
+        if !options.masks.is_empty() {
+            Masker::new(&options.masks).mask_json("", &mut value);
+        }
+        if !options.normalize.is_empty() {
+            options.normalize.normalize_json(&mut value);
+        }
+        if !options.keys.is_empty() {
+            sort_keyed_arrays(&mut value, &options.keys);
+        }
+    }
+    let canonical = canonical.is_some();
     let mut out = String::new();
     if auto && is_json_schema_document(&value) {
         render_schema_document(&value, canonical, &mut out);
@@ -254,6 +281,91 @@ fn ordered_keys(object: &Map<String, Value>, canonical: bool) -> Vec<&str> {
     keys
 }
 
+/// Sort the items of every array a `--key` rule names by the rule's member,
+/// so an edited record keeps its place. Arrays are otherwise ordered data and
+/// stay as they are. Items lacking the member keep their relative order, first.
+fn sort_keyed_arrays(value: &mut Value, keys: &[KeyRule]) {
+    match value {
+        Value::Object(object) => {
+            for (name, member) in object.iter_mut() {
+                if let Value::Array(items) = member
+                    && let Some(rule) = keys.iter().find(|rule| rule.applies_to(name))
+                {
+                    let member_name = rule.key.trim_start_matches('@');
+                    items.sort_by_cached_key(|item| {
+                        item.get(member_name)
+                            .filter(|v| is_scalar(v))
+                            .map(|v| render_scalar(v, ScalarContext::Plain))
+                    });
+                }
+                sort_keyed_arrays(member, keys);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                sort_keyed_arrays(item, keys);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Build an element tree mirroring a JSON document, for the features that
+/// work on `XmlElement` trees (`unxml diff`). Object members become child
+/// elements named by their rendered key, array items repeated `key[]` siblings
+/// (the same spelling the renderer uses), and scalars leaf text in their
+/// rendered form. A root object contributes its members as the roots; a root
+/// array its items as `[]` roots.
+#[cfg(feature = "cli")]
+pub(crate) fn json_to_elements(content: &str) -> Result<Vec<XmlElement>> {
+    let value: Value = serde_json::from_str(content).context("Failed to parse JSON")?;
+    let mut root = XmlElement::new(String::new());
+    match &value {
+        Value::Object(object) => {
+            for (key, child) in object {
+                push_json_member(&mut root, render_key(key), child);
+            }
+        }
+        Value::Array(array) => {
+            for item in array {
+                push_json_member(&mut root, "[]".to_string(), item);
+            }
+        }
+        scalar => push_json_member(&mut root, "value".to_string(), scalar),
+    }
+    Ok(root.children)
+}
+
+/// Append `value` to `parent` as a child element `name` (arrays expand to
+/// one `name[]` child per item).
+#[cfg(feature = "cli")]
+fn push_json_member(parent: &mut XmlElement, name: String, value: &Value) {
+    if let Value::Array(array) = value
+        && !array.is_empty()
+    {
+        for item in array {
+            push_json_member(parent, format!("{name}[]"), item);
+        }
+        return;
+    }
+    let mut elem = XmlElement::new(name);
+    match value {
+        Value::Object(object) if !object.is_empty() => {
+            for (key, child) in object {
+                push_json_member(&mut elem, render_key(key), child);
+            }
+        }
+        Value::Object(_) => elem.text_content = "{}".to_string(),
+        Value::Array(_) => elem.text_content = "[]".to_string(),
+        scalar => elem.text_content = render_scalar(scalar, ScalarContext::Plain),
+    }
+    if !elem.text_content.is_empty() {
+        elem.nodes.push(NodeRef::Text(elem.text_content.clone()));
+    }
+    parent.nodes.push(NodeRef::Child(parent.children.len()));
+    parent.children.push(elem);
+}
+
 fn is_json_schema_document(value: &Value) -> bool {
     value
         .as_object()
@@ -273,7 +385,7 @@ fn is_openapi_document(value: &Value) -> bool {
         && (object.contains_key("paths") || object.contains_key("components"))
 }
 
-fn render_schema_document(value: &Value, canonical: bool, out: &mut String) {
+pub(crate) fn render_schema_document(value: &Value, canonical: bool, out: &mut String) {
     let object = value.as_object().expect("JSON Schema root is an object");
     for key in ["$schema", "$id"] {
         if let Some(value) = object.get(key) {
@@ -436,7 +548,7 @@ fn render_schema_label(name: &str) -> String {
     }
 }
 
-fn schema_type_inline(schema: &Map<String, Value>) -> Option<String> {
+pub(crate) fn schema_type_inline(schema: &Map<String, Value>) -> Option<String> {
     if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
         return Some(format!("ref {}", short_reference(reference)));
     }
@@ -484,7 +596,7 @@ fn schema_type_inline(schema: &Map<String, Value>) -> Option<String> {
     schema_type
 }
 
-fn short_reference(reference: &str) -> &str {
+pub(crate) fn short_reference(reference: &str) -> &str {
     reference
         .strip_prefix("#/components/schemas/")
         .or_else(|| reference.strip_prefix("#/$defs/"))
@@ -605,12 +717,13 @@ fn render_key(key: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::render_json;
+    use crate::canonical::{CanonicalOptions, KeyRule};
 
     #[test]
     fn renders_uniform_objects_as_a_table() {
         let input = r#"{"users":[{"id":1,"name":"Ada"},{"name":"Lin","id":2}]}"#;
         assert_eq!(
-            render_json(input, false, false).unwrap(),
+            render_json(input, None, false).unwrap(),
             "users[]{id,name}\n  1, Ada\n  2, Lin\n"
         );
     }
@@ -619,7 +732,7 @@ mod tests {
     fn falls_back_for_nested_rows() {
         let input = r#"{"users":[{"id":1,"meta":{"active":true}},{"id":2,"meta":null}]}"#;
         assert_eq!(
-            render_json(input, false, false).unwrap(),
+            render_json(input, None, false).unwrap(),
             concat!(
                 "users[]\n",
                 "  id = 1\n",
@@ -636,7 +749,7 @@ mod tests {
     fn canonical_sorts_object_keys_and_table_columns() {
         let input = r#"{"z":0,"rows":[{"b":2,"a":1},{"a":3,"b":4}],"a":9}"#;
         assert_eq!(
-            render_json(input, true, false).unwrap(),
+            render_json(input, Some(&CanonicalOptions::default()), false).unwrap(),
             concat!(
                 "a = 9\n",
                 "rows[]{a,b}\n",
@@ -647,14 +760,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn canonical_key_rules_sort_keyed_arrays_only() {
+        let canonical = CanonicalOptions {
+            keys: vec![KeyRule::parse("rows=id").unwrap()],
+            ..CanonicalOptions::default()
+        };
+        let input = r#"{"rows":[{"id":"b","n":1},{"id":"a","n":2}],"tags":["y","x"]}"#;
+        assert_eq!(
+            render_json(input, Some(&canonical), false).unwrap(),
+            "rows[]{id,n}\n  a, 2\n  b, 1\ntags = [y, x]\n"
+        );
+    }
+
     #[test]
     fn renders_root_arrays_and_quoted_keys() {
         assert_eq!(
-            render_json(r#"[{"first name":"Ada","active":true}]"#, false, false).unwrap(),
+            render_json(r#"[{"first name":"Ada","active":true}]"#, None, false).unwrap(),
             concat!("[]\n", "  \"first name\" = Ada\n", "  active = true\n")
         );
         assert_eq!(
-            render_json(r#"["null",null,-1.5e2,true]"#, false, false).unwrap(),
+            render_json(r#"["null",null,-1.5e2,true]"#, None, false).unwrap(),
             "[\"null\", null, -150.0, true]\n"
         );
     }
@@ -664,7 +790,7 @@ mod tests {
         assert_eq!(
             render_json(
                 r#"{"rows":[{"text":"comma, quote: \""},{"text":"line\nbreak"}]}"#,
-                false,
+                None,
                 false,
             )
             .unwrap(),
@@ -675,7 +801,7 @@ mod tests {
             )
         );
         assert_eq!(
-            render_json(r#"{"rows":[{"id":1}]}"#, false, false).unwrap(),
+            render_json(r#"{"rows":[{"id":1}]}"#, None, false).unwrap(),
             "rows[]\n  id = 1\n"
         );
     }
@@ -684,7 +810,7 @@ mod tests {
     fn distinguishes_empty_and_mixed_containers() {
         let input = r#"{"emptyObject":{},"emptyArray":[],"mixed":[1,{"x":2},[3,4]]}"#;
         assert_eq!(
-            render_json(input, false, false).unwrap(),
+            render_json(input, None, false).unwrap(),
             concat!(
                 "emptyObject = {}\n",
                 "emptyArray = []\n",
@@ -710,7 +836,7 @@ mod tests {
             "structural":"[value]"
         }"##;
         assert_eq!(
-            render_json(input, false, false).unwrap(),
+            render_json(input, None, false).unwrap(),
             concat!(
                 "name = Ada Lovelace\n",
                 "url = https://example.test/a\n",
@@ -728,7 +854,7 @@ mod tests {
     fn renders_named_multiline_strings_as_blocks() {
         let input = r#"{"description":"first\n\nthird","mixed":["one\nline",{"x":1}]}"#;
         assert_eq!(
-            render_json(input, false, false).unwrap(),
+            render_json(input, None, false).unwrap(),
             concat!(
                 "description =\n",
                 "  | first\n",
@@ -746,15 +872,15 @@ mod tests {
     #[test]
     fn keeps_multiline_strings_escaped_in_delimited_values() {
         assert_eq!(
-            render_json(r#"["one\nline","two"]"#, false, false).unwrap(),
+            render_json(r#"["one\nline","two"]"#, None, false).unwrap(),
             "[\"one\\nline\", two]\n"
         );
         assert_eq!(
-            render_json(r#""one\n\nthree""#, false, false).unwrap(),
+            render_json(r#""one\n\nthree""#, None, false).unwrap(),
             "=\n  | one\n  |\n  | three\n"
         );
         assert_eq!(
-            render_json(r#"{"text":"one\n\ttwo"}"#, false, false).unwrap(),
+            render_json(r#"{"text":"one\n\ttwo"}"#, None, false).unwrap(),
             "text = \"one\\n\\ttwo\"\n"
         );
     }
@@ -782,7 +908,7 @@ mod tests {
           "required":["basicString","uuid","stringArray","objectArray"]
         }"#;
         assert_eq!(
-            render_json(input, false, true).unwrap(),
+            render_json(input, None, true).unwrap(),
             concat!(
                 "\"$schema\" = http://json-schema.org/draft-07/schema#\n",
                 "schema : object\n",
@@ -825,7 +951,7 @@ mod tests {
           }},
           "unrelated":{"type":"object","properties":{"leave":"generic"}}
         }"##;
-        let output = render_json(input, false, true).unwrap();
+        let output = render_json(input, None, true).unwrap();
         assert!(output.contains("schema : ref User[]\n"));
         assert!(output.contains("schemas\n    User : object\n"));
         assert!(output.contains("      id! : integer int64\n"));
@@ -838,13 +964,13 @@ mod tests {
         let schema =
             r#"{"$schema":"https://json-schema.org/draft/2020-12/schema","type":"string"}"#;
         assert_eq!(
-            render_json(schema, false, false).unwrap(),
+            render_json(schema, None, false).unwrap(),
             "\"$schema\" = https://json-schema.org/draft/2020-12/schema\ntype = string\n"
         );
 
         let ordinary = r#"{"type":"object","properties":{"name":"Ada"}}"#;
         assert_eq!(
-            render_json(ordinary, false, true).unwrap(),
+            render_json(ordinary, None, true).unwrap(),
             "type = object\nproperties\n  name = Ada\n"
         );
     }
@@ -0,0 +1,322 @@
+//! JSON Schema inference (`--infer-json-schema`): derive a schema from samples.
+//!
+//! The JSON counterpart of `--infer-xsd`. Every input document (or every line
+//! of a JSON Lines file) is a sample of one schema; values are merged per
+//! location, so each node of the result summarises everything seen there:
+//!
+//!  - **Types** — every JSON type observed, as a single `type` or a list
+//!    (`["string", "null"]`). `integer` widens to `number` when both occur.
+//!  - **Objects** — properties in first-seen order; a property is `required`
+//!    when it is present in every object observed at that location.
+//!  - **Arrays** — one `items` schema merged from every element of every array.
+//!  - **Formats** — `date-time`, `date`, `email` or `uuid` when every string
+//!    value at the location matches.
+//!  - **Enum candidates** — a small set of string values that keep repeating
+//!    (at most `ENUM_MAX_VALUES` distinct, each seen twice on average).
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use serde_json::{Map, Value, json};
+
+use crate::json::render_schema_document;
+use crate::types::{has_timezone, is_uuid, is_xsd_date, is_xsd_datetime};
+
+const SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Most distinct string values a location may have to become an `enum`.
+const ENUM_MAX_VALUES: usize = 5;
+
+/// Bit flags for the string formats a value satisfies, merged by intersection
+/// like `--infer-xsd`'s simple types.
+const DATE_TIME: u8 = 1;
+const DATE: u8 = 2;
+const EMAIL: u8 = 4;
+const UUID: u8 = 8;
+
+const FORMAT_ORDER: [(u8, &str); 4] = [
+    (DATE_TIME, "date-time"),
+    (DATE, "date"),
+    (EMAIL, "email"),
+    (UUID, "uuid"),
+];
+
+/// `local@domain.tld`, without whitespace.
+fn is_email(s: &str) -> bool {
+    let Some((local, domain)) = s.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && !domain.contains('@')
+        && !s.chars().any(char::is_whitespace)
+        && domain
+            .split_once('.')
+            .is_some_and(|(host, tld)| !host.is_empty() && !tld.is_empty())
+        && !domain.ends_with('.')
+}
+
+/// The string formats one value satisfies. JSON Schema's `date-time` is
+/// RFC 3339, so unlike `xs:dateTime` it needs an explicit zone.
+fn format_flags(value: &str) -> u8 {
+    let mut flags = 0;
+    if is_xsd_datetime(value) && has_timezone(value) {
+        flags |= DATE_TIME;
+    }
+    if is_xsd_date(value) && !has_timezone(value) {
+        flags |= DATE;
+    }
+    if is_email(value) {
+        flags |= EMAIL;
+    }
+    if is_uuid(value) {
+        flags |= UUID;
+    }
+    flags
+}
+
+/// Everything observed at one location of the samples.
+#[derive(Default)]
+struct Shape {
+    nulls: usize,
+    booleans: usize,
+    integers: usize,
+    numbers: usize,
+    strings: usize,
+    arrays: usize,
+    objects: usize,
+    /// Intersected `format_flags` of every string; `None` until one is seen.
+    formats: Option<u8>,
+    /// Distinct string values in first-seen order, until there are too many.
+    values: Vec<String>,
+    too_many_values: bool,
+    /// Property names in first-seen order, and each property's shape (whose
+    /// `count()` is how many objects carried it).
+    keys: Vec<String>,
+    properties: HashMap<String, Shape>,
+    items: Option<Box<Shape>>,
+}
+
+impl Shape {
+    fn observe(&mut self, value: &Value) {
+        match value {
+            Value::Null => self.nulls += 1,
+            Value::Bool(_) => self.booleans += 1,
+            Value::Number(n) if n.is_i64() || n.is_u64() => self.integers += 1,
+            Value::Number(_) => self.numbers += 1,
+            Value::String(s) => {
+                self.strings += 1;
+                let flags = format_flags(s);
+                self.formats = Some(self.formats.map_or(flags, |seen| seen & flags));
+                if !self.too_many_values && !self.values.contains(s) {
+                    if self.values.len() == ENUM_MAX_VALUES {
+                        self.too_many_values = true;
+                        self.values.clear();
+                    } else {
+                        self.values.push(s.clone());
+                    }
+                }
+            }
+            Value::Array(array) => {
+                self.arrays += 1;
+                let items = self.items.get_or_insert_with(Box::default);
+                for item in array {
+                    items.observe(item);
+                }
+            }
+            Value::Object(object) => {
+                self.objects += 1;
+                for (key, child) in object {
+                    if !self.properties.contains_key(key) {
+                        self.keys.push(key.clone());
+                    }
+                    self.properties
+                        .entry(key.clone())
+                        .or_default()
+                        .observe(child);
+                }
+            }
+        }
+    }
+
+    /// How many values were observed here.
+    fn count(&self) -> usize {
+        self.nulls
+            + self.booleans
+            + self.integers
+            + self.numbers
+            + self.strings
+            + self.arrays
+            + self.objects
+    }
+
+    /// The observed JSON types, most structured first.
+    fn type_names(&self) -> Vec<&'static str> {
+        let mut names = Vec::new();
+        for (count, name) in [
+            (self.objects, "object"),
+            (self.arrays, "array"),
+            (self.strings, "string"),
+        ] {
+            if count > 0 {
+                names.push(name);
+            }
+        }
+        if self.numbers > 0 {
+            names.push("number");
+        } else if self.integers > 0 {
+            names.push("integer");
+        }
+        if self.booleans > 0 {
+            names.push("boolean");
+        }
+        if self.nulls > 0 {
+            names.push("null");
+        }
+        names
+    }
+
+    /// The inferred schema for this location.
+    fn schema(&self) -> Map<String, Value> {
+        let mut schema = Map::new();
+        let names = self.type_names();
+        match names.as_slice() {
+            [] => {}
+            [name] => {
+                schema.insert("type".to_string(), json!(name));
+            }
+            _ => {
+                schema.insert("type".to_string(), json!(names));
+            }
+        }
+
+        if self.strings > 0 {
+            let flags = self.formats.unwrap_or(0);
+            let format = FORMAT_ORDER.iter().find(|(flag, _)| flags & flag != 0);
+            if let Some((_, format)) = format {
+                schema.insert("format".to_string(), json!(format));
+            } else if names == ["string"]
+                && !self.too_many_values
+                && self.strings >= 2 * self.values.len()
+            {
+                schema.insert("enum".to_string(), json!(self.values));
+            }
+        }
+
+        if self.objects > 0 {
+            let mut properties = Map::new();
+            let mut required = Vec::new();
+            for key in &self.keys {
+                let property = &self.properties[key];
+                properties.insert(key.clone(), Value::Object(property.schema()));
+                if property.count() == self.objects {
+                    required.push(key.clone());
+                }
+            }
+            schema.insert("properties".to_string(), Value::Object(properties));
+            if !required.is_empty() {
+                schema.insert("required".to_string(), json!(required));
+            }
+        }
+
+        if let Some(items) = &self.items
+            && items.count() > 0
+        {
+            schema.insert("items".to_string(), Value::Object(items.schema()));
+        }
+        schema
+    }
+}
+
+/// One source's samples: the whole document, or — when it isn't a single
+/// JSON value — one sample per non-blank line (JSON Lines).
+fn samples(name: &str, content: &str) -> Result<Vec<Value>> {
+    match serde_json::from_str(content) {
+        Ok(value) => Ok(vec![value]),
+        Err(e) => {
+            let lines: Result<Vec<Value>, _> = content
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(serde_json::from_str)
+                .collect();
+            match lines {
+                Ok(values) if values.len() > 1 => Ok(values),
+                _ => Err(e).with_context(|| format!("Failed to parse JSON: {name}")),
+            }
+        }
+    }
+}
+
+/// Infer one JSON Schema (with a `$schema` dialect) covering every sample.
+pub(crate) fn infer_json_schema(samples: &[Value]) -> Value {
+    let mut shape = Shape::default();
+    for sample in samples {
+        shape.observe(sample);
+    }
+    let mut schema = Map::new();
+    schema.insert("$schema".to_string(), json!(SCHEMA_DIALECT));
+    schema.extend(shape.schema());
+    Value::Object(schema)
+}
+
+/// `--infer-json-schema`: read every `(name, content)` source as samples and
+/// return the inferred schema — as JSON with `emit_schema`, otherwise in the
+/// compact schema view `--auto` uses for JSON Schema documents.
+pub(crate) fn run_infer_json_schema(
+    sources: &[(String, String)],
+    emit_schema: bool,
+) -> Result<String> {
+    let mut all = Vec::new();
+    for (name, content) in sources {
+        all.extend(samples(name, content)?);
+    }
+    let schema = infer_json_schema(&all);
+    Ok(if emit_schema {
+        format!("{}\n", serde_json::to_string_pretty(&schema)?)
+    } else {
+        let mut out = String::new();
+        render_schema_document(&schema, false, &mut out);
+        out
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DATE, DATE_TIME, EMAIL, UUID, format_flags, run_infer_json_schema};
+
+    #[test]
+    fn detects_string_formats() {
+        assert_eq!(format_flags("2026-10-18T08:30:00Z"), DATE_TIME);
+        assert_eq!(format_flags("2026-10-18T08:30:00"), 0);
+        assert_eq!(format_flags("2026-10-18"), DATE);
+        assert_eq!(format_flags("ada@example.org"), EMAIL);
+        assert_eq!(format_flags("6f1c2a4e-8b1d-4c7a-9e3f-0a1b2c3d4e5f"), UUID);
+        assert_eq!(format_flags("not @ mail"), 0);
+    }
+
+    #[test]
+    fn merges_samples_from_json_lines() {
+        let lines = concat!(
+            r#"{"id":1,"status":"open","email":"a@x.org","tags":["a"]}"#,
+            "\n",
+            r#"{"id":2,"status":"closed","email":null,"tags":[]}"#,
+            "\n",
+            r#"{"id":3,"status":"open","score":1.5}"#,
+            "\n",
+            r#"{"id":4,"status":"open"}"#,
+            "\n",
+        );
+        let sources = vec![("events.jsonl".to_string(), lines.to_string())];
+        assert_eq!(
+            run_infer_json_schema(&sources, false).unwrap(),
+            concat!(
+                "\"$schema\" = https://json-schema.org/draft/2020-12/schema\n",
+                "schema : object\n",
+                "  id! : integer\n",
+                "  status! : enum [open, closed]\n",
+                "  email : string | null email\n",
+                "  tags : string[]\n",
+                "  score : number\n",
+            )
+        );
+    }
+}
@@ -0,0 +1,580 @@
+//! Semantic diff of two JSON Schemas or OpenAPI documents
+//! (`unxml diff --json-schema OLD NEW`).
+//!
+//! The JSON counterpart of `xsd_diff`. Both documents are read into their
+//! components, each with its schema flattened into members by path
+//! (`address/city`, `lines[]/qty`), and the components are compared:
+//!
+//!  - a JSON Schema contributes its root (`schema`) and each `$defs` /
+//!    `definitions` entry (`schema NAME`);
+//!  - an OpenAPI (or Swagger 2) document contributes each named schema
+//!    (`schema NAME`) and each operation (`GET /users/{id}`), whose members
+//!    are its parameters (`query limit`, `path id`), its request `body` and
+//!    its responses by status code, with inline schemas flattened beneath.
+//!
+//! Changes are classified like `--xsd`: **breaking** when a document valid
+//! under OLD may be rejected by NEW (a removed property or operation, a new
+//! required property or parameter, a narrowed type, a removed enum value, a
+//! tightened bound), **compatible** otherwise. That is the direction of what
+//! clients send: schemas, parameters and the request `body`. Responses go the
+//! other way, since clients read them: there a new enum value, a widened type,
+//! a dropped bound or a new optional property is breaking, and a new required
+//! property is not. Members are rendered in the compact schema view of
+//! `--auto` (`id! : integer int64`); `$ref`s are compared by name, and the
+//! referenced schema is compared as a component of its own.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+
+use crate::diff::{Change, SchemaReport};
+use crate::json::{schema_type_inline, short_reference};
+
+/// Bounds whose value is a maximum: lowering it is breaking.
+const UPPER_BOUNDS: &[&str] = &[
+    "maxLength",
+    "maximum",
+    "exclusiveMaximum",
+    "maxItems",
+    "maxProperties",
+];
+
+/// Bounds whose value is a minimum: raising it is breaking.
+const LOWER_BOUNDS: &[&str] = &[
+    "minLength",
+    "minimum",
+    "exclusiveMinimum",
+    "minItems",
+    "minProperties",
+];
+
+/// Other constraints: adding or changing one is breaking.
+const CONSTRAINTS: &[&str] = &["pattern", "multipleOf", "uniqueItems", "const"];
+
+const METHODS: &[&str] = &[
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// One schema, parameter, body or response, reduced to what is compared.
+#[derive(Debug, Default, PartialEq)]
+struct Decl {
+    /// `type` values (`null` for OpenAPI's `nullable`), or `ref NAME`; empty
+    /// for a schema that accepts anything.
+    types: BTreeSet<String>,
+    format: Option<String>,
+    required: bool,
+    enums: BTreeSet<String>,
+    bounds: BTreeMap<String, String>,
+    /// The compact-view type, for rendering.
+    label: Option<String>,
+    /// Under a response status code: written by the server and read by
+    /// clients, so a change breaks when NEW allows more rather than less.
+    response: bool,
+}
+
+/// A named schema or an operation, with its members by path.
+#[derive(Debug, Default)]
+struct Component {
+    decl: Decl,
+    members: BTreeMap<String, Decl>,
+}
+
+fn scalar_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn decl(schema: &Map<String, Value>, required: bool) -> Decl {
+    let mut decl = Decl {
+        required,
+        label: schema_type_inline(schema),
+        format: schema
+            .get("format")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        ..Decl::default()
+    };
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        decl.types
+            .insert(format!("ref {}", short_reference(reference)));
+    }
+    match schema.get("type") {
+        Some(Value::String(ty)) => {
+            decl.types.insert(ty.clone());
+        }
+        Some(Value::Array(types)) => {
+            decl.types
+                .extend(types.iter().filter_map(Value::as_str).map(str::to_string));
+        }
+        _ => {}
+    }
+    if schema.get("nullable").and_then(Value::as_bool) == Some(true) {
+        decl.types.insert("null".to_string());
+    }
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        decl.enums = values.iter().map(scalar_text).collect();
+    }
+    for key in UPPER_BOUNDS.iter().chain(LOWER_BOUNDS).chain(CONSTRAINTS) {
+        if let Some(value) = schema.get(*key) {
+            decl.bounds.insert(key.to_string(), scalar_text(value));
+        }
+    }
+    if schema.get("additionalProperties") == Some(&Value::Bool(false)) {
+        decl.bounds
+            .insert("additionalProperties".to_string(), "false".to_string());
+    }
+    decl
+}
+
+/// `path/name`, or `name` at the top.
+fn join(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{path}/{name}")
+    }
+}
+
+/// Flatten the properties, items and variants of the schema at `path` into
+/// `members`. `$ref`s are not followed.
+fn flatten(schema: &Map<String, Value>, path: &str, members: &mut BTreeMap<String, Decl>) {
+    let required: BTreeSet<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .collect();
+    if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+        for (name, property) in properties {
+            if let Some(property) = property.as_object() {
+                let child = join(path, name);
+                members.insert(
+                    child.clone(),
+                    decl(property, required.contains(name.as_str())),
+                );
+                flatten(property, &child, members);
+            }
+        }
+    }
+    if let Some(items) = schema.get("items").and_then(Value::as_object) {
+        let child = format!("{path}[]");
+        members.insert(child.clone(), decl(items, false));
+        flatten(items, &child, members);
+    }
+    // `allOf` parts describe the same value; variants get a path of their own.
+    for part in schema
+        .get("allOf")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object)
+    {
+        flatten(part, path, members);
+    }
+    for keyword in ["oneOf", "anyOf"] {
+        let variants = schema.get(keyword).and_then(Value::as_array);
+        for (i, variant) in variants.into_iter().flatten().enumerate() {
+            if let Some(variant) = variant.as_object() {
+                let child = join(path, &format!("{keyword}[{i}]"));
+                members.insert(child.clone(), decl(variant, false));
+                flatten(variant, &child, members);
+            }
+        }
+    }
+}
+
+fn schema_component(schema: &Map<String, Value>) -> Component {
+    let mut component = Component {
+        decl: decl(schema, false),
+        ..Component::default()
+    };
+    flatten(schema, "", &mut component.members);
+    component
+}
+
+/// Read a JSON Schema or OpenAPI document into its components.
+fn read(root: &Value) -> BTreeMap<String, Component> {
+    let mut components = BTreeMap::new();
+    let Some(root) = root.as_object() else {
+        return components;
+    };
+    let api = root.contains_key("openapi") || root.contains_key("swagger");
+    let named = [
+        root.get("$defs"),
+        root.get("definitions"),
+        root.get("components").and_then(|c| c.get("schemas")),
+    ];
+    for schemas in named.into_iter().flatten().filter_map(Value::as_object) {
+        for (name, schema) in schemas {
+            if let Some(schema) = schema.as_object() {
+                components.insert(format!("schema {name}"), schema_component(schema));
+            }
+        }
+    }
+    if !api {
+        components.insert("schema".to_string(), schema_component(root));
+        return components;
+    }
+    let paths = root.get("paths").and_then(Value::as_object);
+    for (route, item) in paths.into_iter().flatten() {
+        let Some(item) = item.as_object() else {
+            continue;
+        };
+        for method in METHODS {
+            if let Some(operation) = item.get(*method).and_then(Value::as_object) {
+                let key = format!("{} {route}", method.to_uppercase());
+                components.insert(key, operation_component(root, item, operation));
+            }
+        }
+    }
+    components
+}
+
+/// Resolve a `$ref` to another part of the document (`#/components/…`).
+fn resolve<'a>(root: &'a Map<String, Value>, value: &'a Value) -> Option<&'a Map<String, Value>> {
+    let object = value.as_object()?;
+    let Some(reference) = object.get("$ref").and_then(Value::as_str) else {
+        return Some(object);
+    };
+    let mut segments = reference
+        .strip_prefix("#/")?
+        .split('/')
+        .map(|segment| segment.replace("~1", "/").replace("~0", "~"));
+    let mut target = root.get(&segments.next()?)?;
+    for segment in segments {
+        target = target.get(&segment)?;
+    }
+    target.as_object()
+}
+
+/// The JSON schema of a request body or response: its `application/json`
+/// content (else the first one), or Swagger 2's `schema`.
+fn content_schema(object: &Map<String, Value>) -> Option<&Map<String, Value>> {
+    if let Some(content) = object.get("content").and_then(Value::as_object) {
+        let media = content
+            .get("application/json")
+            .or_else(|| content.values().next())?;
+        return media.get("schema").and_then(Value::as_object);
+    }
+    object.get("schema").and_then(Value::as_object)
+}
+
+fn operation_component(
+    root: &Map<String, Value>,
+    item: &Map<String, Value>,
+    operation: &Map<String, Value>,
+) -> Component {
+    let mut component = Component::default();
+    let members = &mut component.members;
+
+    // Path-level parameters apply to every operation; the operation's own
+    // override them by location and name.
+    let parameters = [item.get("parameters"), operation.get("parameters")];
+    let mut by_key = BTreeMap::new();
+    for parameter in parameters
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_array)
+        .flatten()
+        .filter_map(|p| resolve(root, p))
+    {
+        let location = parameter
+            .get("in")
+            .and_then(Value::as_str)
+            .unwrap_or("query");
+        let name = parameter.get("name").and_then(Value::as_str).unwrap_or("");
+        by_key.insert(format!("{location} {name}"), parameter);
+    }
+    for (key, parameter) in by_key {
+        let required = parameter.get("required").and_then(Value::as_bool) == Some(true);
+        let schema = parameter
+            .get("schema")
+            .and_then(Value::as_object)
+            .unwrap_or(parameter);
+        if key.starts_with("body ") {
+            members.insert("body".to_string(), decl(schema, required));
+            flatten(schema, "body", members);
+        } else {
+            members.insert(key, decl(schema, required));
+        }
+    }
+
+    if let Some(body) = operation.get("requestBody").and_then(|b| resolve(root, b)) {
+        let required = body.get("required").and_then(Value::as_bool) == Some(true);
+        let mut body_decl = Decl {
+            required,
+            ..Decl::default()
+        };
+        if let Some(schema) = content_schema(body) {
+            body_decl = decl(schema, required);
+            flatten(schema, "body", members);
+        }
+        members.insert("body".to_string(), body_decl);
+    }
+
+    let responses = operation.get("responses").and_then(Value::as_object);
+    for (status, response) in responses.into_iter().flatten() {
+        let Some(response) = resolve(root, response) else {
+            continue;
+        };
+        let schema = content_schema(response);
+        let mut returned = BTreeMap::new();
+        returned.insert(
+            status.clone(),
+            schema.map(|s| decl(s, false)).unwrap_or_default(),
+        );
+        if let Some(schema) = schema {
+            flatten(schema, status, &mut returned);
+        }
+        for (path, mut decl) in returned {
+            decl.response = true;
+            members.insert(path, decl);
+        }
+    }
+    component
+}
+
+/// A member in the compact schema view: `id! : integer int64`.
+fn render(path: &str, decl: &Decl) -> String {
+    let required = if decl.required { "!" } else { "" };
+    match &decl.label {
+        Some(label) => format!("{path}{required} : {label}"),
+        None => format!("{path}{required}"),
+    }
+}
+
+fn render_types(decl: &Decl) -> String {
+    let mut text = if decl.types.is_empty() {
+        "any".to_string()
+    } else {
+        decl.types.iter().cloned().collect::<Vec<_>>().join(" | ")
+    };
+    if let Some(format) = &decl.format {
+        text.push(' ');
+        text.push_str(format);
+    }
+    text
+}
+
+/// Whether a value of one of `types` always satisfies one of `wider`
+/// (`integer` is a `number`; no types accepts anything).
+fn covers(wider: &BTreeSet<String>, types: &BTreeSet<String>) -> bool {
+    wider.is_empty()
+        || !types.is_empty()
+            && types
+                .iter()
+                .all(|ty| wider.contains(ty) || ty == "integer" && wider.contains("number"))
+}
+
+/// Compare two declarations of one member (`label` is its path and a space,
+/// empty for a component's own schema). Each change carries whether it breaks
+/// what clients send and whether it breaks what they read; `response` picks.
+fn compare(label: &str, old: &Decl, new: &Decl, changes: &mut Vec<Change>) {
+    let response = new.response;
+    let mut push = |sent: bool, read: bool, line: String| {
+        changes.push(Change {
+            breaking: if response { read } else { sent },
+            line,
+        })
+    };
+
+    if old.types != new.types || old.format != new.format {
+        // Widened when every old value is still accepted and no format was added.
+        let format_kept = new.format.is_none() || new.format == old.format;
+        let widened = covers(&new.types, &old.types) && format_kept;
+        let narrowed =
+            covers(&old.types, &new.types) && (old.format.is_none() || old.format == new.format);
+        let note = if widened {
+            " (widened)"
+        } else if narrowed {
+            " (narrowed)"
+        } else {
+            ""
+        };
+        push(
+            !widened,
+            !narrowed,
+            format!(
+                "~ {label}: {} → {}{note}",
+                render_types(old),
+                render_types(new)
+            ),
+        );
+    }
+
+    if old.required != new.required {
+        let word = |required| if required { "required" } else { "optional" };
+        push(
+            new.required,
+            old.required,
+            format!("~ {label}{} → {}", word(old.required), word(new.required)),
+        );
+    }
+
+    // A new enum restricts a value set that had none.
+    let restricts = old.enums.is_empty();
+    for value in old.enums.difference(&new.enums) {
+        let restricted = !new.enums.is_empty();
+        push(restricted, !restricted, format!("- {label}| {value}"));
+    }
+    for value in new.enums.difference(&old.enums) {
+        push(restricts, !restricts, format!("+ {label}| {value}"));
+    }
+
+    let bounds: BTreeSet<&String> = old.bounds.keys().chain(new.bounds.keys()).collect();
+    for bound in bounds {
+        match (old.bounds.get(bound), new.bounds.get(bound)) {
+            (Some(o), None) => push(false, true, format!("- {label}{bound} {o}")),
+            (None, Some(n)) => push(true, false, format!("+ {label}{bound} {n}")),
+            (Some(o), Some(n)) if o != n => {
+                let (tighter, looser) = match (o.parse::<f64>(), n.parse::<f64>()) {
+                    (Ok(o), Ok(n)) if UPPER_BOUNDS.contains(&bound.as_str()) => (n < o, n > o),
+                    (Ok(o), Ok(n)) if LOWER_BOUNDS.contains(&bound.as_str()) => (n > o, n < o),
+                    _ => (true, true),
+                };
+                push(tighter, looser, format!("~ {label}{bound} {o} → {n}"));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn compare_component(old: &Component, new: &Component) -> Vec<Change> {
+    let mut changes = Vec::new();
+    compare("", &old.decl, &new.decl, &mut changes);
+    let paths: BTreeSet<&String> = old.members.keys().chain(new.members.keys()).collect();
+    for path in paths {
+        match (old.members.get(path), new.members.get(path)) {
+            (Some(o), None) => changes.push(Change {
+                breaking: true,
+                line: format!("- {}", render(path, o)),
+            }),
+            // A new response member only breaks clients when it may be
+            // absent or unknown to them; a new required one is just more data.
+            (None, Some(n)) => changes.push(Change {
+                breaking: n.required != n.response,
+                line: format!("+ {}", render(path, n)),
+            }),
+            (Some(o), Some(n)) if o != n => compare(&format!("{path} "), o, n, &mut changes),
+            _ => {}
+        }
+    }
+    changes
+}
+
+/// A component's header line: `schema User : object`, `GET /users`.
+fn render_header(key: &str, component: &Component) -> String {
+    match &component.decl.label {
+        Some(label) => format!("{key} : {label}"),
+        None => key.to_string(),
+    }
+}
+
+/// Compare two JSON Schema or OpenAPI documents; empty when no component
+/// changed.
+pub(crate) fn diff_json_schemas(old: &str, new: &str) -> Result<String> {
+    let old: Value = serde_json::from_str(old).context("Failed to parse OLD as JSON")?;
+    let new: Value = serde_json::from_str(new).context("Failed to parse NEW as JSON")?;
+    let (old, new) = (read(&old), read(&new));
+    let mut report = SchemaReport::default();
+    let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    for key in keys {
+        match (old.get(key), new.get(key)) {
+            (Some(o), None) => report.removed(&render_header(key, o)),
+            (None, Some(n)) => report.added(&render_header(key, n)),
+            (Some(o), Some(n)) => {
+                report.changed(&render_header(key, n), &compare_component(o, n));
+            }
+            (None, None) => {}
+        }
+    }
+    Ok(report.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::diff_json_schemas;
+
+    #[test]
+    fn classifies_openapi_changes() {
+        let old = r##"{"openapi":"3.0.3","paths":{
+          "/users":{"get":{"parameters":[{"name":"limit","in":"query","schema":{"type":"integer"}}],
+            "responses":{"200":{"content":{"application/json":{"schema":{"$ref":"#/components/schemas/User"}}}}}}},
+          "/legacy":{"get":{"responses":{"204":{"description":"gone"}}}}},
+          "components":{"schemas":{"User":{"type":"object","required":["id"],"properties":{
+            "id":{"type":"integer","format":"int64"},
+            "role":{"type":"string","enum":["admin","user"]},
+            "email":{"type":"string"}}}}}}"##;
+        let new = r##"{"openapi":"3.0.3","paths":{
+          "/users":{"get":{"parameters":[{"name":"limit","in":"query","schema":{"type":"number"}},
+              {"name":"team","in":"query","required":true,"schema":{"type":"string"}}],
+            "responses":{"200":{"content":{"application/json":{"schema":{"$ref":"#/components/schemas/User"}}}}}}}},
+          "components":{"schemas":{"User":{"type":"object","required":["id","email"],"properties":{
+            "id":{"type":"integer","format":"int64"},
+            "role":{"type":"string","enum":["admin","guest"]},
+            "email":{"type":"string"}}}}}}"##;
+        assert_eq!(
+            diff_json_schemas(old, new).unwrap(),
+            concat!(
+                "breaking\n",
+                "  - GET /legacy\n",
+                "  ~ GET /users\n",
+                "      + query team! : string\n",
+                "  ~ schema User : object\n",
+                "      ~ email optional → required\n",
+                "      - role | user\n",
+                "compatible\n",
+                "  ~ GET /users\n",
+                "      ~ query limit : integer → number (widened)\n",
+                "  ~ schema User : object\n",
+                "      + role | guest\n",
+            )
+        );
+        assert!(diff_json_schemas(old, old).unwrap().is_empty());
+    }
+
+    #[test]
+    fn classifies_responses_in_the_reading_direction() {
+        // The same schema as the request body and as the response.
+        let api = |schema: &str| {
+            format!(
+                r#"{{"openapi":"3.0.3","paths":{{"/orders":{{"post":{{
+                  "requestBody":{{"content":{{"application/json":{{"schema":{schema}}}}}}},
+                  "responses":{{"200":{{"content":{{"application/json":{{"schema":{schema}}}}}}}}}}}}}}}}}"#
+            )
+        };
+        let old = api(r#"{"type":"object","required":["id"],"properties":{
+              "id":{"type":"integer"},
+              "state":{"type":"string","enum":["open","closed"]},
+              "note":{"type":"string","maxLength":10}}}"#);
+        let new = api(r#"{"type":"object","required":["id","total"],"properties":{
+              "id":{"type":"number"},
+              "state":{"type":"string","enum":["open","closed","held"]},
+              "note":{"type":"string"},
+              "total":{"type":"number"},
+              "tag":{"type":"string"}}}"#);
+        assert_eq!(
+            diff_json_schemas(&old, &new).unwrap(),
+            concat!(
+                "breaking\n",
+                "  ~ POST /orders\n",
+                "      ~ 200/id : integer → number (widened)\n",
+                "      - 200/note maxLength 10\n",
+                "      + 200/state | held\n",
+                "      + 200/tag : string\n",
+                "      + body/total! : number\n",
+                "compatible\n",
+                "  ~ POST /orders\n",
+                "      + 200/total! : number\n",
+                "      ~ body/id : integer → number (widened)\n",
+                "      - body/note maxLength 10\n",
+                "      + body/state | held\n",
+                "      + body/tag : string\n",
+            )
+        );
+    }
+}
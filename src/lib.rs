@@ -0,0 +1,94 @@
+//! unxml — simplify and "flatten" XML and HTML into a light, Pug/YAML-like
+//! readable form.
+//!
+//! The library is the renderer behind the CLI:
+//!
+//! - [`parse_xml`] and [`parse_html`] build the [`XmlElement`] tree;
+//! - [`Renderer`] (or [`process_content`] with a [`ProcessOptions`]) turns a
+//!   whole XML, HTML or JSON document into unxml text, and
+//!   [`XmlElement::format_yaml_like`] renders one subtree;
+//! - [`canonicalize`], [`dump_paths`] and [`render_json`] are the
+//!   `--canonical`, `--paths` and JSON passes on their own;
+//! - the [`snapshot`] module builds snapshot-test assertions
+//!   ([`assert_unxml_eq!`], [`assert_unxml_snapshot!`]) on the canonical
+//!   rendering.
+//!
+//! ```
+//! let parsed = unxml::parse_xml("<a x='1'><b>hi</b></a>").unwrap();
+//! let text = parsed.roots[0].format_yaml_like(0, &unxml::FormatOpts::default(), None);
+//! assert_eq!(text, "a(x=\"1\")\n  b = hi\n");
+//! ```
+
+#[cfg(feature = "cli")]
+mod app;
+#[cfg(feature = "cli")]
+mod batch;
+#[cfg(feature = "cli")]
+mod c14n;
+mod canonical;
+#[cfg(feature = "cli")]
+mod cli;
+#[cfg(feature = "cli")]
+mod config;
+#[cfg(feature = "cli")]
+mod diff;
+mod document;
+#[cfg(feature = "cli")]
+mod highlight;
+mod ignore;
+#[cfg(feature = "cli")]
+mod infer;
+#[cfg(feature = "cli")]
+mod install;
+mod json;
+#[cfg(feature = "cli")]
+mod json_infer;
+#[cfg(feature = "cli")]
+mod json_schema_diff;
+#[cfg(feature = "cli")]
+mod lsp;
+mod mask;
+#[cfg(feature = "cli")]
+mod mcp;
+#[cfg(feature = "cli")]
+mod merge;
+mod model;
+mod msbuild;
+mod normalize;
+mod parse;
+mod paths;
+mod process;
+#[cfg(feature = "python")]
+mod python;
+mod render;
+mod schematron;
+#[cfg(feature = "cli")]
+mod site;
+#[cfg(feature = "cli")]
+pub mod snapshot;
+mod types;
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(feature = "cli")]
+mod watch;
+#[cfg(feature = "cli")]
+mod write;
+mod wsdl;
+mod xsd;
+#[cfg(feature = "cli")]
+mod xsd_diff;
+mod xslt;
+
+// The binary's entry points; they exit the process on some paths.
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub use app::{run, run_from};
+pub use canonical::{CanonicalOptions, KnownPrefixes, canonicalize};
+pub use json::render_json;
+pub use model::{Collapse, FormatOpts, NodeRef, XmlElement};
+pub use parse::{InputFormat, ParsedXml, detect_format, parse_html, parse_xml};
+pub use paths::dump_paths;
+#[cfg(feature = "cli")]
+pub use process::process_file;
+pub use process::{ProcessOptions, Renderer, process_content};
+pub use xslt::TemplateRegistry;
@@ -0,0 +1,648 @@
+//! `unxml lsp`: a language server for XML files (XSLT, XSD, WSDL,
+//! Schematron, MSBuild and plain XML), over stdio.
+//!
+//! For an open XML document it provides:
+//!
+//!  - document symbols: an outline from the parsed tree, per dialect —
+//!    templates and functions in XSLT, global and nested elements and types
+//!    in XSD, targets, property and item groups in MSBuild, patterns and
+//!    rules in Schematron, messages and operations in WSDL, and the element
+//!    structure (leaves left out) in plain XML;
+//!  - folding ranges for every element that spans several lines;
+//!  - the "unxml view": a custom `unxml/view` request returning the
+//!    rendering (as `unxml --auto` prints it) under the virtual URI
+//!    `unxml:<document uri>`. Go-to-definition on a line of that view jumps
+//!    to the element in the source it was rendered from.
+//!
+//! Documents are synced in full (`TextDocumentSyncKind.Full`); the view
+//! renders the latest synced text, so a client that re-requests it on
+//! change gets a live preview.
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+
+use crate::document::{detect_mode_from_ext, sniff_mode};
+use crate::model::{FormatOpts, XmlElement};
+use crate::parse::{parse_xml, read_file_lenient};
+use crate::process::Renderer;
+use crate::render::render_with_source_map;
+
+/// The scheme of view documents: `unxml:` followed by the source URI.
+const VIEW_SCHEME: &str = "unxml:";
+
+// LSP `SymbolKind` values.
+const MODULE: u32 = 2;
+const NAMESPACE: u32 = 3;
+const CLASS: u32 = 5;
+const METHOD: u32 = 6;
+const PROPERTY: u32 = 7;
+const FIELD: u32 = 8;
+const ENUM: u32 = 10;
+const INTERFACE: u32 = 11;
+const FUNCTION: u32 = 12;
+const VARIABLE: u32 = 13;
+const KEY: u32 = 20;
+const OBJECT: u32 = 19;
+const STRUCT: u32 = 23;
+const EVENT: u32 = 24;
+
+/// Serve until the client sends `exit` or closes stdin.
+pub(crate) fn run_lsp() -> Result<()> {
+    let stdin = std::io::stdin();
+    let mut input = stdin.lock();
+    let mut stdout = std::io::stdout();
+    let mut server = Server::default();
+    while let Some(message) = read_message(&mut input)? {
+        if message.get("method").and_then(Value::as_str) == Some("exit") {
+            break;
+        }
+        if let Some(response) = server.handle(&message) {
+            let body = response.to_string();
+            write!(stdout, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+            stdout.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Read one `Content-Length`-framed message; `None` at end of input.
+fn read_message(input: &mut impl BufRead) -> Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = Some(
+                value
+                    .trim()
+                    .parse::<usize>()
+                    .context("Bad Content-Length")?,
+            );
+        }
+    }
+    let length = length.context("Message without Content-Length")?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(
+        serde_json::from_slice(&body).context("Malformed message")?,
+    ))
+}
+
+#[derive(Default)]
+struct Server {
+    /// Text of the open documents by URI.
+    documents: HashMap<String, String>,
+}
+
+impl Server {
+    /// Answer one message; `None` for notifications.
+    fn handle(&mut self, message: &Value) -> Option<Value> {
+        let method = message.get("method").and_then(Value::as_str).unwrap_or("");
+        let params = message.get("params").unwrap_or(&Value::Null);
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or("");
+        let Some(id) = message.get("id").cloned() else {
+            match method {
+                "textDocument/didOpen" => {
+                    if let Some(text) = params["textDocument"]["text"].as_str() {
+                        self.documents.insert(uri.to_string(), text.to_string());
+                    }
+                }
+                "textDocument/didChange" => {
+                    let changes = params["contentChanges"].as_array();
+                    if let Some(text) = changes
+                        .and_then(|c| c.last())
+                        .and_then(|c| c["text"].as_str())
+                    {
+                        self.documents.insert(uri.to_string(), text.to_string());
+                    }
+                }
+                "textDocument/didClose" => {
+                    self.documents.remove(uri);
+                }
+                _ => {}
+            }
+            return None;
+        };
+        let result = match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "documentSymbolProvider": true,
+                    "foldingRangeProvider": true,
+                    "definitionProvider": true,
+                },
+                "serverInfo": { "name": "unxml", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "shutdown" => Ok(Value::Null),
+            "textDocument/documentSymbol" => self.document_symbols(uri),
+            "textDocument/foldingRange" => self.folding_ranges(uri),
+            "textDocument/definition" => {
+                self.definition(uri, params["position"]["line"].as_u64().unwrap_or(0))
+            }
+            "unxml/view" => self.view(uri),
+            _ => {
+                return Some(json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": { "code": -32601, "message": format!("Method not found: {method}") },
+                }));
+            }
+        };
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32603, "message": format!("{e:#}") },
+            }),
+        })
+    }
+
+    /// The synced text of `uri`, or the file's contents when it isn't open.
+    fn text(&self, uri: &str) -> Result<String> {
+        match self.documents.get(uri) {
+            Some(text) => Ok(text.clone()),
+            None => read_file_lenient(&uri_to_path(uri)),
+        }
+    }
+
+    fn document_symbols(&self, uri: &str) -> Result<Value> {
+        let text = self.text(uri)?;
+        let roots = parse_xml(&text)?.roots;
+        let mode = dialect(uri, &roots);
+        let index = LineIndex::new(&text);
+        Ok(Value::Array(symbols(&roots, None, &mode, &index)))
+    }
+
+    fn folding_ranges(&self, uri: &str) -> Result<Value> {
+        let text = self.text(uri)?;
+        let index = LineIndex::new(&text);
+        let mut ranges = Vec::new();
+        for root in &parse_xml(&text)?.roots {
+            folds(root, &index, &mut ranges);
+        }
+        Ok(Value::Array(ranges))
+    }
+
+    /// The rendering of `uri` and, per rendered line, the source offset of
+    /// the element it came from.
+    fn render(&self, uri: &str) -> Result<(String, Vec<Option<usize>>)> {
+        let text = self.text(uri)?;
+        let path = uri_to_path(uri);
+        render_with_source_map(|| Renderer::new().auto(true).render(&text, &path))
+    }
+
+    fn view(&self, uri: &str) -> Result<Value> {
+        let (text, _) = self.render(uri)?;
+        Ok(json!({ "uri": format!("{VIEW_SCHEME}{uri}"), "text": text }))
+    }
+
+    /// From a view line to the element it was rendered from. Definition in a
+    /// source document itself has no answer.
+    fn definition(&self, uri: &str, line: u64) -> Result<Value> {
+        let Some(source) = uri.strip_prefix(VIEW_SCHEME) else {
+            return Ok(Value::Null);
+        };
+        let (_, offsets) = self.render(source)?;
+        let Some(offset) = offsets.get(line as usize).copied().flatten() else {
+            return Ok(Value::Null);
+        };
+        let text = self.text(source)?;
+        let position = LineIndex::new(&text).position(&text, offset);
+        Ok(json!({ "uri": source, "range": { "start": position, "end": position } }))
+    }
+}
+
+/// The dialect whose outline a document gets: from the extension, else
+/// sniffed from the document element, as under `--auto`.
+fn dialect(uri: &str, roots: &[XmlElement]) -> FormatOpts {
+    let mode = detect_mode_from_ext(&uri_to_path(uri));
+    if mode.has_mode() {
+        return mode;
+    }
+    roots.iter().find_map(sniff_mode).unwrap_or(mode)
+}
+
+/// A file path from a `file:` URI (percent-decoded); other URIs are used as
+/// they are.
+fn uri_to_path(uri: &str) -> String {
+    let Some(path) = uri.strip_prefix("file://") else {
+        return uri.to_string();
+    };
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = path
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    let path = String::from_utf8_lossy(&decoded).into_owned();
+    // `file:///C:/x` names `C:/x` on Windows.
+    match path.as_bytes() {
+        [b'/', drive, b':', ..] if drive.is_ascii_alphabetic() => path[1..].to_string(),
+        _ => path,
+    }
+}
+
+/// Byte offsets to LSP positions (zero-based line, UTF-16 column).
+struct LineIndex {
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    fn new(text: &str) -> Self {
+        let mut starts = vec![0];
+        starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+        Self { starts }
+    }
+
+    fn line(&self, offset: usize) -> usize {
+        self.starts.partition_point(|&start| start <= offset) - 1
+    }
+
+    fn position(&self, text: &str, offset: usize) -> Value {
+        let line = self.line(offset);
+        let column = text
+            .get(self.starts[line]..offset)
+            .map_or(0, |s| s.encode_utf16().count());
+        json!({ "line": line, "character": column })
+    }
+}
+
+/// Fold every element whose start and end tags are on different lines.
+fn folds(elem: &XmlElement, index: &LineIndex, out: &mut Vec<Value>) {
+    if let Some(span) = &elem.span {
+        let (start, end) = (
+            index.line(span.start),
+            index.line(span.end.saturating_sub(1)),
+        );
+        if end > start {
+            out.push(json!({ "startLine": start, "endLine": end }));
+        }
+    }
+    for child in &elem.children {
+        folds(child, index, out);
+    }
+}
+
+/// How an element figures in the outline.
+enum Entry {
+    Symbol {
+        name: String,
+        detail: Option<String>,
+        kind: u32,
+    },
+    /// Not listed itself; its children are listed in its place.
+    Transparent,
+    Skip,
+}
+
+fn symbol(name: impl Into<String>, detail: Option<&String>, kind: u32) -> Entry {
+    Entry::Symbol {
+        name: name.into(),
+        detail: detail.cloned(),
+        kind,
+    }
+}
+
+fn local(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+fn symbols(
+    elems: &[XmlElement],
+    parent: Option<&XmlElement>,
+    mode: &FormatOpts,
+    index: &LineIndex,
+) -> Vec<Value> {
+    let mut out = Vec::new();
+    for elem in elems {
+        let Some(span) = &elem.span else { continue };
+        match classify(elem, parent, mode) {
+            Entry::Symbol { name, detail, kind } => {
+                let line = index.line(span.start);
+                let start = json!({ "line": line, "character": 0 });
+                let range = json!({
+                    "start": start,
+                    "end": { "line": index.line(span.end.saturating_sub(1)) + 1, "character": 0 },
+                });
+                let mut symbol = json!({
+                    "name": if name.is_empty() { elem.name.clone() } else { name },
+                    "kind": kind,
+                    "range": range,
+                    "selectionRange": { "start": start, "end": start },
+                    "children": symbols(&elem.children, Some(elem), mode, index),
+                });
+                if let Some(detail) = detail {
+                    symbol["detail"] = Value::String(detail);
+                }
+                out.push(symbol);
+            }
+            Entry::Transparent => out.extend(symbols(&elem.children, Some(elem), mode, index)),
+            Entry::Skip => {}
+        }
+    }
+    out
+}
+
+fn classify(elem: &XmlElement, parent: Option<&XmlElement>, mode: &FormatOpts) -> Entry {
+    let attr = |name: &str| elem.attributes.get(name);
+    let parent_local = parent.map_or("", |p| local(&p.name));
+    let name = local(&elem.name);
+
+    if mode.xslt {
+        let top_level = matches!(parent_local, "stylesheet" | "transform" | "package");
+        return match name {
+            "stylesheet" | "transform" | "package" => Entry::Transparent,
+            "template" => match (attr("name"), attr("match")) {
+                (Some(n), _) => symbol(n.clone(), attr("mode"), FUNCTION),
+                (None, Some(m)) => symbol(format!("match {m}"), attr("mode"), FUNCTION),
+                _ => symbol("template", None, FUNCTION),
+            },
+            "function" => symbol(
+                attr("name").cloned().unwrap_or_default(),
+                attr("as"),
+                FUNCTION,
+            ),
+            "variable" | "param" if top_level => symbol(
+                attr("name").cloned().unwrap_or_default(),
+                attr("select"),
+                VARIABLE,
+            ),
+            "key" if top_level => symbol(
+                attr("name").cloned().unwrap_or_default(),
+                attr("match"),
+                KEY,
+            ),
+            "attribute-set" => symbol(attr("name").cloned().unwrap_or_default(), None, STRUCT),
+            "import" | "include" => symbol(name, attr("href"), MODULE),
+            _ => Entry::Skip,
+        };
+    }
+
+    if mode.schematron {
+        return match name {
+            "schema" => Entry::Transparent,
+            "pattern" => symbol(
+                attr("id").cloned().unwrap_or_else(|| "pattern".into()),
+                None,
+                NAMESPACE,
+            ),
+            "rule" => symbol(
+                attr("context").cloned().unwrap_or_else(|| "rule".into()),
+                attr("id"),
+                FUNCTION,
+            ),
+            "phase" => symbol(attr("id").cloned().unwrap_or_default(), None, EVENT),
+            "let" if parent_local == "schema" => symbol(
+                attr("name").cloned().unwrap_or_default(),
+                attr("value"),
+                VARIABLE,
+            ),
+            _ => Entry::Skip,
+        };
+    }
+
+    if mode.wsdl {
+        match name {
+            "definitions" | "types" => return Entry::Transparent,
+            "message" => return symbol(attr("name").cloned().unwrap_or_default(), None, STRUCT),
+            "part" if parent_local == "message" => {
+                return symbol(
+                    attr("name").cloned().unwrap_or_default(),
+                    attr("element").or(attr("type")),
+                    FIELD,
+                );
+            }
+            "portType" => {
+                return symbol(attr("name").cloned().unwrap_or_default(), None, INTERFACE);
+            }
+            "binding" => {
+                return symbol(
+                    attr("name").cloned().unwrap_or_default(),
+                    attr("type"),
+                    CLASS,
+                );
+            }
+            "service" => return symbol(attr("name").cloned().unwrap_or_default(), None, MODULE),
+            "operation" => return symbol(attr("name").cloned().unwrap_or_default(), None, METHOD),
+            "port" => {
+                return symbol(
+                    attr("name").cloned().unwrap_or_default(),
+                    attr("binding"),
+                    FIELD,
+                );
+            }
+            "input" | "output" | "fault" | "documentation" => return Entry::Skip,
+            // The embedded schema falls through to the XSD outline.
+            _ => {}
+        }
+    }
+
+    if mode.xsd || mode.wsdl {
+        let named = |kind| match (attr("name"), attr("ref")) {
+            (Some(n), _) => symbol(n.clone(), attr("type"), kind),
+            (None, Some(r)) => symbol(r.clone(), Some(&"ref".to_string()), kind),
+            _ => Entry::Transparent,
+        };
+        return match name {
+            "schema" | "sequence" | "choice" | "all" | "complexContent" | "simpleContent"
+            | "extension" | "restriction" => Entry::Transparent,
+            "element" => named(FIELD),
+            "attribute" => named(PROPERTY),
+            "complexType" => named(CLASS),
+            "simpleType" => named(ENUM),
+            "group" | "attributeGroup" => named(NAMESPACE),
+            "import" | "include" => {
+                symbol(name, attr("schemaLocation").or(attr("namespace")), MODULE)
+            }
+            _ => Entry::Skip,
+        };
+    }
+
+    if mode.msbuild {
+        return match name {
+            "Project" | "Choose" => Entry::Transparent,
+            "Target" => symbol(
+                attr("Name").cloned().unwrap_or_default(),
+                attr("DependsOnTargets"),
+                FUNCTION,
+            ),
+            "Import" => symbol(
+                attr("Project").cloned().unwrap_or_default(),
+                attr("Condition"),
+                MODULE,
+            ),
+            "UsingTask" => symbol(attr("TaskName").cloned().unwrap_or_default(), None, CLASS),
+            "PropertyGroup" | "ItemGroup" | "ItemDefinitionGroup" | "When" | "Otherwise" => {
+                symbol(name, attr("Condition"), NAMESPACE)
+            }
+            _ if parent_local == "PropertyGroup" => {
+                symbol(name, Some(&elem.text_content), PROPERTY)
+            }
+            _ if parent_local == "ItemGroup" => symbol(
+                attr("Include")
+                    .or(attr("Update"))
+                    .or(attr("Remove"))
+                    .cloned()
+                    .unwrap_or_default(),
+                Some(&name.to_string()),
+                FIELD,
+            ),
+            _ => Entry::Skip,
+        };
+    }
+
+    // Plain XML: the element structure, leaves left out.
+    if elem.children.is_empty() && parent.is_some() {
+        return Entry::Skip;
+    }
+    let key = ["id", "name", "key"]
+        .iter()
+        .find_map(|k| attr(k).map(|v| format!("{k}={v}")));
+    symbol(elem.name.clone(), key.as_ref(), OBJECT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LineIndex, Renderer, Server, uri_to_path};
+    use serde_json::{Value, json};
+
+    fn request(server: &mut Server, method: &str, params: Value) -> Value {
+        server
+            .handle(&json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }))
+            .unwrap()["result"]
+            .clone()
+    }
+
+    #[test]
+    fn outlines_an_xslt_stylesheet_and_maps_view_lines_back() {
+        let mut server = Server::default();
+        let uri = "file:///tmp/a.xsl";
+        let text = concat!(
+            "<xsl:stylesheet xmlns:xsl=\"http://www.w3.org/1999/XSL/Transform\">\n",
+            "  <xsl:param name=\"p\"/>\n",
+            "  <xsl:template match=\"/\">\n",
+            "    <out/>\n",
+            "  </xsl:template>\n",
+            "  <xsl:template name=\"row\"/>\n",
+            "</xsl:stylesheet>\n",
+        );
+        server.handle(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": uri, "text": text } },
+        }));
+
+        let symbols = request(
+            &mut server,
+            "textDocument/documentSymbol",
+            json!({ "textDocument": { "uri": uri } }),
+        );
+        let names: Vec<&str> = symbols
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["p", "match /", "row"]);
+        assert_eq!(symbols[1]["range"]["start"]["line"], 2);
+
+        let folds = request(
+            &mut server,
+            "textDocument/foldingRange",
+            json!({ "textDocument": { "uri": uri } }),
+        );
+        assert_eq!(
+            folds,
+            json!([{ "startLine": 0, "endLine": 6 }, { "startLine": 2, "endLine": 4 }])
+        );
+
+        let view = request(
+            &mut server,
+            "unxml/view",
+            json!({ "textDocument": { "uri": uri } }),
+        );
+        let lines: Vec<&str> = view["text"].as_str().unwrap().lines().collect();
+        let out_line = lines.iter().position(|l| l.trim() == "out").unwrap();
+        let target = request(
+            &mut server,
+            "textDocument/definition",
+            json!({ "textDocument": { "uri": format!("unxml:{uri}") }, "position": { "line": out_line, "character": 0 } }),
+        );
+        assert_eq!(
+            target["range"]["start"],
+            json!({ "line": 3, "character": 4 })
+        );
+    }
+
+    #[test]
+    fn the_view_is_the_plain_rendering() {
+        let mut server = Server::default();
+        let uri = "file:///tmp/b.xml";
+        let text = "<list>\n  <item>a\u{E000}12\u{E001}b</item>\n  <item>c</item>\n</list>\n";
+        server.handle(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": uri, "text": text } },
+        }));
+
+        let view = request(
+            &mut server,
+            "unxml/view",
+            json!({ "textDocument": { "uri": uri } }),
+        );
+        let view = view["text"].as_str().unwrap();
+        let rendered = Renderer::new()
+            .auto(true)
+            .render(text, "/tmp/b.xml")
+            .unwrap();
+        assert_eq!(view, rendered);
+        assert!(view.contains("a\u{E000}12\u{E001}b"));
+
+        let line = view.lines().position(|l| l.contains("= c")).unwrap();
+        let target = request(
+            &mut server,
+            "textDocument/definition",
+            json!({ "textDocument": { "uri": format!("unxml:{uri}") }, "position": { "line": line, "character": 0 } }),
+        );
+        assert_eq!(
+            target["range"]["start"],
+            json!({ "line": 2, "character": 2 })
+        );
+    }
+
+    #[test]
+    fn positions_count_utf16_columns() {
+        let text = "é\n𝄞x";
+        let index = LineIndex::new(text);
+        assert_eq!(
+            index.position(text, text.find('x').unwrap()),
+            json!({ "line": 1, "character": 2 })
+        );
+        assert_eq!(
+            uri_to_path("file:///C:/My%20Files/a.xsl"),
+            "C:/My Files/a.xsl"
+        );
+    }
+}
@@ -0,0 +1,321 @@
+//! Masking volatile values under `--canonical` (`--mask`).
+//!
+//! Regression snapshots of generated documents differ on every run in values
+//! that carry no meaning for the comparison: timestamps, GUIDs, generated IDs,
+//! signature digests. Masking replaces them with stable placeholders before
+//! the canonical sort, so two runs render identically:
+//!
+//!  - `--mask NAME` masks the text of elements (or JSON members) named `NAME`,
+//!    `--mask @NAME` attribute values, `--mask ELEM@NAME` only on `ELEM`. Names
+//!    match like `--select`. The placeholder is `<masked#n>`.
+//!  - `--mask :timestamp`, `:uuid` and `:digest` detect values by content
+//!    anywhere (ISO 8601 date-times, UUIDs, base64 digests); `:auto` enables
+//!    all three. Placeholders are `<timestamp>`, `<uuid#n>` and `<digest#n>`.
+//!
+//! Numbered placeholders are assigned per file in document order, and one
+//! value always gets the same number, so references between masked IDs (a
+//! `uuid` and the element that points at it) survive masking.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use serde_json::Value;
+
+use crate::document::name_matches_select;
+use crate::model::XmlElement;
+use crate::types::{is_uuid, is_xsd_datetime};
+
+/// One `--mask` rule.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum MaskRule {
+    /// Leaf text of elements with this name.
+    Element(String),
+    /// Values of attributes with this name, on any element or only on the
+    /// named one.
+    Attribute {
+        element: Option<String>,
+        name: String,
+    },
+    /// ISO 8601 date-times, wherever they occur.
+    Timestamps,
+    /// UUIDs (bare or `urn:uuid:`), wherever they occur.
+    Uuids,
+    /// Base64 digests and signature values, wherever they occur.
+    Digests,
+}
+
+impl MaskRule {
+    /// Parse a `--mask` value. `:auto` expands to every detector.
+    pub(crate) fn parse(spec: &str) -> Result<Vec<Self>> {
+        Ok(match spec {
+            ":timestamp" => vec![Self::Timestamps],
+            ":uuid" => vec![Self::Uuids],
+            ":digest" => vec![Self::Digests],
+            ":auto" => vec![Self::Timestamps, Self::Uuids, Self::Digests],
+            _ if spec.starts_with(':') => {
+                return Err(anyhow::anyhow!(
+                    "Unknown --mask detector '{spec}': use :timestamp, :uuid, :digest or :auto"
+                ));
+            }
+            _ => match spec.split_once('@') {
+                Some((element, name)) if !name.is_empty() => vec![Self::Attribute {
+                    element: (!element.is_empty()).then(|| element.to_string()),
+                    name: name.to_string(),
+                }],
+                None if !spec.is_empty() => vec![Self::Element(spec.to_string())],
+                _ => return Err(anyhow::anyhow!("Invalid --mask '{spec}'")),
+            },
+        })
+    }
+}
+
+/// True for a base64 value long enough to be a digest (SHA-1 and up) that
+/// mixes cases and digits the way encoded hashes do — not a long word.
+/// Line-wrapped values (XML-DSig `SignatureValue`) are accepted.
+fn is_base64_digest(s: &str) -> bool {
+    let compact: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+    let body = compact.trim_end_matches('=');
+    compact.len() >= 24
+        && compact.len().is_multiple_of(4)
+        && compact.len() - body.len() <= 2
+        && body
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'+' || b == b'/')
+        && body.bytes().any(|b| b.is_ascii_uppercase())
+        && body.bytes().any(|b| b.is_ascii_lowercase())
+        && body
+            .bytes()
+            .any(|b| b.is_ascii_digit() || b == b'+' || b == b'/')
+}
+
+/// Applies one file's mask rules, numbering placeholders as it goes.
+pub(crate) struct Masker<'a> {
+    rules: &'a [MaskRule],
+    numbers: HashMap<(&'static str, String), usize>,
+    counts: HashMap<&'static str, usize>,
+}
+
+impl<'a> Masker<'a> {
+    pub(crate) fn new(rules: &'a [MaskRule]) -> Self {
+        Self {
+            rules,
+            numbers: HashMap::new(),
+            counts: HashMap::new(),
+        }
+    }
+
+    /// `<kind#n>`, the same `n` for the same value.
+    fn numbered(&mut self, kind: &'static str, value: &str) -> String {
+        let next = self.counts.get(kind).copied().unwrap_or(0) + 1;
+        let n = *self
+            .numbers
+            .entry((kind, value.to_string()))
+            .or_insert_with(|| {
+                self.counts.insert(kind, next);
+                next
+            });
+        format!("<{kind}#{n}>")
+    }
+
+    /// The placeholder for a value a detector recognises, if any.
+    fn detect(&mut self, value: &str) -> Option<String> {
+        let value = value.trim();
+        for rule in self.rules {
+            match rule {
+                MaskRule::Timestamps if is_xsd_datetime(value) => {
+                    return Some("<timestamp>".to_string());
+                }
+                MaskRule::Uuids if is_uuid(value.strip_prefix("urn:uuid:").unwrap_or(value)) => {
+                    let uuid = value.strip_prefix("urn:uuid:").unwrap_or(value);
+                    return Some(self.numbered("uuid", &uuid.to_lowercase()));
+                }
+                MaskRule::Digests if is_base64_digest(value) => {
+                    return Some(self.numbered("digest", value));
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Whether a rule names the text of elements (or JSON members) `name`.
+    fn names_text(&self, name: &str) -> bool {
+        let name = name.trim_end_matches("[]");
+        self.rules.iter().any(
+            |rule| matches!(rule, MaskRule::Element(pattern) if name_matches_select(name, pattern)),
+        )
+    }
+
+    /// Whether a rule names attribute `name` on element `element`.
+    fn names_attr(&self, element: &str, name: &str) -> bool {
+        self.rules.iter().any(|rule| match rule {
+            MaskRule::Attribute {
+                element: scope,
+                name: pattern,
+            } => {
+                name_matches_select(name, pattern)
+                    && scope
+                        .as_deref()
+                        .is_none_or(|scope| name_matches_select(element, scope))
+            }
+            _ => false,
+        })
+    }
+
+    /// Mask an element subtree in document order. Leaf text and attribute
+    /// values are replaced; namespace declarations and mixed-content prose
+    /// are left alone.
+    pub(crate) fn mask_element(&mut self, elem: &mut XmlElement) {
+        let mut names: Vec<String> = elem.attributes.keys().cloned().collect();
+        names.sort();
+        for name in names {
+            if name == "xmlns" || name.starts_with("xmlns:") {
+                continue;
+            }
+            let value = &elem.attributes[&name];
+            let masked = if self.names_attr(&elem.name, &name) {
+                Some(self.numbered("masked", value))
+            } else {
+                self.detect(value)
+            };
+            if let Some(masked) = masked {
+                elem.attributes.insert(name, masked);
+            }
+        }
+
+        if elem.children.is_empty() && !elem.text_content.trim().is_empty() {
+            let masked = if self.names_text(&elem.name) {
+                Some(self.numbered("masked", elem.text_content.trim()))
+            } else {
+                self.detect(&elem.text_content)
+            };
+            if let Some(masked) = masked {
+                elem.set_leaf_text(masked);
+            }
+            return;
+        }
+
+        for child in &mut elem.children {
+            self.mask_element(child);
+        }
+    }
+
+    /// Mask a JSON value in document order: scalar members a rule names
+    /// (`NAME` and `@NAME` both name a member) and detected values.
+    pub(crate) fn mask_json(&mut self, name: &str, value: &mut Value) {
+        match value {
+            Value::Object(object) => {
+                for (key, member) in object.iter_mut() {
+                    self.mask_json(key, member);
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.mask_json(name, item);
+                }
+            }
+            Value::Null => {}
+            scalar => {
+                let text = match &*scalar {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                let masked = if self.names_text(name) || self.names_attr("", name) {
+                    Some(self.numbered("masked", &text))
+                } else {
+                    self.detect(&text)
+                };
+                if let Some(masked) = masked {
+                    *scalar = Value::String(masked);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MaskRule, Masker, is_base64_digest};
+    use crate::parse::parse_xml;
+
+    #[test]
+    fn detects_digests_but_not_words() {
+        assert!(is_base64_digest("q0V8Wv2k7hJxN1c9R6mZ5tYbL3aE4fGd"));
+        assert!(is_base64_digest(
+            "MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8A\n    MIIBCgKCAQEAx0=="
+        ));
+        assert!(!is_base64_digest("SupercalifragilisticExpialidocious"));
+        assert!(!is_base64_digest("short1Ab"));
+    }
+
+    #[test]
+    fn numbers_placeholders_per_distinct_value() {
+        let rules: Vec<MaskRule> = [":auto", "@created", "InvoiceID"]
+            .iter()
+            .flat_map(|spec| MaskRule::parse(spec).unwrap())
+            .collect();
+        let mut roots = parse_xml(concat!(
+            r#"<doc created="today">"#,
+            "<at>2026-10-18T08:30:00Z</at>",
+            "<ref>6F1C2A4E-8B1D-4C7A-9E3F-0A1B2C3D4E5F</ref>",
+            "<ref>urn:uuid:6f1c2a4e-8b1d-4c7a-9e3f-0a1b2c3d4e5f</ref>",
+            "<ref>00000000-0000-0000-0000-000000000000</ref>",
+            "<InvoiceID>A-17</InvoiceID>",
+            "<day>2026-10-18</day>",
+            "</doc>",
+        ))
+        .unwrap()
+        .roots;
+        let mut masker = Masker::new(&rules);
+        masker.mask_element(&mut roots[0]);
+        let doc = &roots[0];
+        assert_eq!(doc.attributes["created"], "<masked#1>");
+        let texts: Vec<&str> = doc
+            .children
+            .iter()
+            .map(|c| c.text_content.as_str())
+            .collect();
+        assert_eq!(
+            texts,
+            [
+                "<timestamp>",
+                "<uuid#1>",
+                "<uuid#1>",
+                "<uuid#2>",
+                "<masked#2>",
+                "2026-10-18"
+            ]
+        );
+    }
+
+    #[test]
+    fn leaves_multibyte_text_alone() {
+        let rules = MaskRule::parse(":timestamp").unwrap();
+        let mut roots = parse_xml("<a><b>€abcde</b><c>2026-10-18T08:30:00€0:00</c></a>")
+            .unwrap()
+            .roots;
+        Masker::new(&rules).mask_element(&mut roots[0]);
+        assert_eq!(roots[0].children[0].text_content, "€abcde");
+        assert_eq!(
+            roots[0].children[1].text_content,
+            "2026-10-18T08:30:00€0:00"
+        );
+    }
+
+    // `unxml diff` is part of the command line.
+    #[cfg(feature = "cli")]
+    #[test]
+    fn masks_multibyte_values_in_a_diff() {
+        use crate::canonical::CanonicalOptions;
+        use crate::diff::diff_documents;
+
+        let options = CanonicalOptions::parse(&[], &[":timestamp".to_string()], &[], None).unwrap();
+        let roots = |xml: &str| parse_xml(xml).unwrap().roots;
+        let diff = diff_documents(
+            roots("<a><b>€abcde</b></a>"),
+            roots("<a><b>€abcdf</b></a>"),
+            &options,
+        );
+        assert!(diff.contains("€abcdf"), "{diff}");
+    }
+}
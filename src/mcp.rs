@@ -0,0 +1,523 @@
+//! `unxml mcp`: a Model Context Protocol server on stdio.
+//!
+//! Agents call tools with a file path and options instead of composing CLI
+//! strings, and get the rendered text back:
+//!
+//!  - `render` — the whole document, as `unxml [--auto]` prints it;
+//!  - `paths` — the distinct element paths (`--paths`), for orientation;
+//!  - `select` — only the subtrees rooted at one element name (`--select`);
+//!  - `diff` — the structural diff of two documents (`unxml diff`);
+//!  - `stats` — element counts, depth and namespaces.
+//!
+//! `diff` applies the `.unxml.toml` and user config settings for `new`
+//! (`key`, `mask`, `normalize`, `schema`, `ignore-*`, `ns-*`), with the call's
+//! own `key`, `mask` and `ignore_*` added first.
+//!
+//! Long renderings are cut at `max_lines` (500 by default) with a note on
+//! how many lines were left out, so a huge document is explored with `paths`
+//! and `select` rather than read in one go.
+//!
+//! The transport is MCP's stdio one: newline-delimited JSON-RPC 2.0 messages
+//! on stdin and stdout. Only `initialize`, `ping`, `tools/list` and
+//! `tools/call` are served; notifications are accepted and ignored.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, Write};
+
+use anyhow::{Context, Result};
+use serde_json::{Map, Value, json};
+
+use crate::cli::DiffCli;
+use crate::config::{Config, Settings};
+use crate::diff::diff_files;
+use crate::model::{FormatOpts, XmlElement};
+use crate::parse::{InputFormat, detect_format, parse_html, parse_xml, read_file_lenient};
+use crate::process::Renderer;
+
+/// The protocol revision answered when the client asks for one we don't know.
+const PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// Revisions whose tool surface this server implements.
+const SUPPORTED_VERSIONS: &[&str] = &["2024-11-05", "2025-03-26", "2025-06-18"];
+
+const DEFAULT_MAX_LINES: usize = 500;
+
+/// Serve requests from stdin until it closes.
+pub(crate) fn run_mcp() -> Result<()> {
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout();
+    for line in stdin.lock().lines() {
+        let line = line.context("Failed to read from stdin")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = handle_message(&line) {
+            writeln!(stdout, "{response}")?;
+            stdout.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Answer one JSON-RPC message; `None` for notifications.
+fn handle_message(line: &str) -> Option<Value> {
+    let message: Value = match serde_json::from_str(line) {
+        Ok(message) => message,
+        Err(e) => {
+            return Some(error_response(
+                Value::Null,
+                -32700,
+                &format!("Parse error: {e}"),
+            ));
+        }
+    };
+    let id = message.get("id").cloned()?;
+    let Some(method) = message.get("method").and_then(Value::as_str) else {
+        return Some(error_response(id, -32600, "Invalid request: no method"));
+    };
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+    let result = match method {
+        "initialize" => initialize(&params),
+        "ping" => json!({}),
+        "tools/list" => json!({ "tools": tool_list() }),
+        "tools/call" => match call_tool(&params) {
+            Ok(text) => json!({ "content": [{ "type": "text", "text": text }] }),
+            // Tool failures (a missing file, a bad option) go back to the
+            // model as a result it can read, not as a protocol error.
+            Err(e) => json!({
+                "content": [{ "type": "text", "text": format!("Error: {e:#}") }],
+                "isError": true,
+            }),
+        },
+        _ => {
+            return Some(error_response(
+                id,
+                -32601,
+                &format!("Method not found: {method}"),
+            ));
+        }
+    };
+    Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn initialize(params: &Value) -> Value {
+    let requested = params.get("protocolVersion").and_then(Value::as_str);
+    let version = requested
+        .filter(|v| SUPPORTED_VERSIONS.contains(v))
+        .unwrap_or(PROTOCOL_VERSION);
+    json!({
+        "protocolVersion": version,
+        "capabilities": { "tools": {} },
+        "serverInfo": { "name": "unxml", "version": env!("CARGO_PKG_VERSION") },
+        "instructions": "Tools render XML, HTML and JSON files as terse unxml text. \
+            For a large document, start with `stats` or `paths`, then `select` \
+            the parts you need.",
+    })
+}
+
+/// The JSON Schema `properties` the rendering tools share.
+fn render_properties() -> Map<String, Value> {
+    let mut properties = Map::new();
+    properties.insert(
+        "path".into(),
+        json!({ "type": "string", "description": "The file to read" }),
+    );
+    properties.insert(
+        "mode".into(),
+        json!({
+            "type": "string",
+            "enum": ["xml", "special", "xslt", "schematron", "xsd", "wsdl", "msbuild"],
+            "description": "Processing mode; by default picked from the extension",
+        }),
+    );
+    properties.insert(
+        "format".into(),
+        json!({
+            "type": "string",
+            "enum": ["xml", "html", "json"],
+            "description": "Input format; detected when omitted",
+        }),
+    );
+    properties.insert(
+        "hide_ns".into(),
+        json!({
+            "type": "array",
+            "items": { "type": "string" },
+            "description": "Namespace prefixes to drop from names (\"ALL\" drops every prefix)",
+        }),
+    );
+    properties.insert(
+        "canonical".into(),
+        json!({ "type": "boolean", "description": "Sort attributes and siblings, rebind prefixes" }),
+    );
+    properties.insert(
+        "max_lines".into(),
+        json!({
+            "type": "integer",
+            "minimum": 0,
+            "description": format!("Cut the output after this many lines (default {DEFAULT_MAX_LINES}; 0 for all)"),
+        }),
+    );
+    properties
+}
+
+fn tool_list() -> Value {
+    let render = render_properties();
+
+    let mut select = render_properties();
+    select.insert(
+        "element".into(),
+        json!({ "type": "string", "description": "Element name (local or prefixed) whose subtrees to render" }),
+    );
+
+    let mut paths = Map::new();
+    paths.insert("path".into(), render["path"].clone());
+    paths.insert("format".into(), render["format"].clone());
+    paths.insert(
+        "depth".into(),
+        json!({ "type": "integer", "minimum": 0, "description": "Levels to list (0 for all)" }),
+    );
+    paths.insert(
+        "no_attrs".into(),
+        json!({ "type": "boolean", "description": "Leave attribute names out" }),
+    );
+    paths.insert(
+        "fold".into(),
+        json!({ "type": "boolean", "description": "Hoist repeated subtree shapes into a legend" }),
+    );
+
+    let list = json!({ "type": "array", "items": { "type": "string" } });
+    let diff = json!({
+        "old": { "type": "string", "description": "The old document" },
+        "new": { "type": "string", "description": "The new document" },
+        "format": render["format"],
+        "key": { "type": "array", "items": { "type": "string" }, "description": "ELEM=KEY rules matching siblings by an attribute (row=@id) or child text" },
+        "mask": list,
+        "ignore_attr": list,
+        "ignore_elem": list,
+        "xsd": { "type": "boolean", "description": "Compare two XSDs and classify changes as breaking or compatible" },
+        "json_schema": { "type": "boolean", "description": "Compare two JSON Schema or OpenAPI documents" },
+        "max_lines": render["max_lines"],
+    });
+
+    let stats = json!({ "path": render["path"], "format": render["format"] });
+
+    json!([
+        tool(
+            "render",
+            "Render a whole XML, HTML or JSON file as unxml text.",
+            Value::Object(render),
+            &["path"]
+        ),
+        tool(
+            "paths",
+            "List the distinct element paths of a document as an indented tree.",
+            Value::Object(paths),
+            &["path"]
+        ),
+        tool(
+            "select",
+            "Render only the subtrees rooted at elements with the given name.",
+            Value::Object(select),
+            &["path", "element"]
+        ),
+        tool(
+            "diff",
+            "Structurally diff two documents: + added, - removed, ~ changed.",
+            diff,
+            &["old", "new"]
+        ),
+        tool(
+            "stats",
+            "Count elements, attributes and depth, and list namespaces and the commonest element names.",
+            stats,
+            &["path"]
+        ),
+    ])
+}
+
+fn tool(name: &str, description: &str, properties: Value, required: &[&str]) -> Value {
+    json!({
+        "name": name,
+        "description": description,
+        "inputSchema": { "type": "object", "properties": properties, "required": required },
+    })
+}
+
+fn call_tool(params: &Value) -> Result<String> {
+    let name = params
+        .get("name")
+        .and_then(Value::as_str)
+        .context("tools/call without a tool name")?;
+    let empty = Map::new();
+    let args = params
+        .get("arguments")
+        .and_then(Value::as_object)
+        .unwrap_or(&empty);
+    let max_lines = opt_usize(args, "max_lines")?.unwrap_or(DEFAULT_MAX_LINES);
+    match name {
+        "render" => Ok(truncate(
+            &renderer(args)?.render_file(str_arg(args, "path")?)?,
+            max_lines,
+        )),
+        "select" => {
+            let element = str_arg(args, "element")?;
+            let out = renderer(args)?
+                .select(element)
+                .render_file(str_arg(args, "path")?)?;
+            if out.is_empty() {
+                return Ok(format!("No <{element}> elements."));
+            }
+            Ok(truncate(&out, max_lines))
+        }
+        "paths" => {
+            let mut renderer = Renderer::new()
+                .auto(true)
+                .paths(opt_usize(args, "depth")?.unwrap_or(0))
+                .no_attrs(bool_arg(args, "no_attrs")?)
+                .fold(bool_arg(args, "fold")?);
+            if let Some(format) = opt_str(args, "format")? {
+                renderer = renderer.format(format);
+            }
+            Ok(truncate(
+                &renderer.render_file(str_arg(args, "path")?)?,
+                max_lines,
+            ))
+        }
+        "diff" => {
+            let new = str_arg(args, "new")?;
+            let call = Settings {
+                format: opt_str(args, "format")?.map(str::to_string),
+                key: list_arg(args, "key")?,
+                mask: list_arg(args, "mask")?,
+                ignore_attr: list_arg(args, "ignore_attr")?,
+                ignore_elem: list_arg(args, "ignore_elem")?,
+                ..Settings::default()
+            };
+            let settings = Config::discover()?.settings_for(Some(new)).overlay(&call);
+            let out = diff_files(&DiffCli {
+                old: str_arg(args, "old")?.to_string(),
+                new: new.to_string(),
+                format: settings.format,
+                xsd: bool_arg(args, "xsd")?,
+                json_schema: bool_arg(args, "json_schema")?,
+                key: settings.key,
+                mask: settings.mask,
+                normalize: settings.normalize,
+                schema: settings.schema,
+                ignore_attr: settings.ignore_attr,
+                ignore_elem: settings.ignore_elem,
+                ignore_preset: settings.ignore_preset,
+                ns_prefix: settings.ns_prefix,
+                ns_map: settings.ns_map,
+                ns_hash: settings.ns_hash,
+            })?;
+            if out.is_empty() {
+                return Ok("No differences.".to_string());
+            }
+            Ok(truncate(&out, max_lines))
+        }
+        "stats" => stats(str_arg(args, "path")?, opt_str(args, "format")?),
+        _ => Err(anyhow::anyhow!("Unknown tool: {name}")),
+    }
+}
+
+/// The renderer for `render` and `select`: `--auto` unless a mode is given.
+fn renderer(args: &Map<String, Value>) -> Result<Renderer> {
+    let mut renderer = Renderer::new()
+        .auto(true)
+        .hide_ns(list_arg(args, "hide_ns")?)
+        .canonical(bool_arg(args, "canonical")?);
+    if let Some(mode) = opt_str(args, "mode")? {
+        renderer = renderer.mode(FormatOpts::from_mode(mode)?);
+    }
+    if let Some(format) = opt_str(args, "format")? {
+        renderer = renderer.format(format);
+    }
+    Ok(renderer)
+}
+
+/// Keep the first `max_lines` lines (all when 0), noting how many were cut.
+fn truncate(text: &str, max_lines: usize) -> String {
+    let total = text.lines().count();
+    if max_lines == 0 || total <= max_lines {
+        return text.to_string();
+    }
+    let mut out: String = text
+        .lines()
+        .take(max_lines)
+        .flat_map(|line| [line, "\n"])
+        .collect();
+    out.push_str(&format!(
+        "… {} of {total} lines not shown (raise max_lines, or narrow with paths/select)\n",
+        total - max_lines
+    ));
+    out
+}
+
+/// Element, attribute and depth counts, declared namespaces, and the most
+/// frequent element names.
+fn stats(path: &str, format: Option<&str>) -> Result<String> {
+    let content = read_file_lenient(path)?;
+    let format = match format {
+        Some(name) => InputFormat::from_name(name)?,
+        None => detect_format(&content, path),
+    };
+    let roots = match format {
+        InputFormat::Xml => parse_xml(&content)?.roots,
+        InputFormat::Html => parse_html(&content)?,
+        InputFormat::Json => return Err(anyhow::anyhow!("stats supports XML and HTML only")),
+    };
+
+    let mut counts = Counts::default();
+    for root in &roots {
+        counts.visit(root, 1);
+    }
+
+    let mut out = format!(
+        "bytes: {}\nelements: {}\ndistinct names: {}\nattributes: {}\ntext leaves: {}\nmax depth: {}\n",
+        content.len(),
+        counts.elements,
+        counts.names.len(),
+        counts.attributes,
+        counts.leaves,
+        counts.depth
+    );
+    if !counts.namespaces.is_empty() {
+        out.push_str("namespaces:\n");
+        for (prefix, uri) in &counts.namespaces {
+            let prefix = if prefix.is_empty() {
+                "(default)"
+            } else {
+                prefix
+            };
+            out.push_str(&format!("  {prefix} = {uri}\n"));
+        }
+    }
+    let mut names: Vec<(&String, &usize)> = counts.names.iter().collect();
+    names.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    out.push_str("top elements:\n");
+    for (name, count) in names.into_iter().take(15) {
+        out.push_str(&format!("  {name} {count}\n"));
+    }
+    Ok(out)
+}
+
+#[derive(Default)]
+struct Counts {
+    elements: usize,
+    attributes: usize,
+    leaves: usize,
+    depth: usize,
+    names: HashMap<String, usize>,
+    namespaces: BTreeMap<String, String>,
+}
+
+impl Counts {
+    fn visit(&mut self, elem: &XmlElement, depth: usize) {
+        self.elements += 1;
+        self.depth = self.depth.max(depth);
+        *self.names.entry(elem.name.clone()).or_default() += 1;
+        for (name, value) in &elem.attributes {
+            if let Some(prefix) = name.strip_prefix("xmlns") {
+                let prefix = prefix.strip_prefix(':').unwrap_or(prefix);
+                self.namespaces
+                    .entry(prefix.to_string())
+                    .or_insert_with(|| value.clone());
+            } else {
+                self.attributes += 1;
+            }
+        }
+        if elem.children.is_empty() && !elem.text_content.trim().is_empty() {
+            self.leaves += 1;
+        }
+        for child in &elem.children {
+            self.visit(child, depth + 1);
+        }
+    }
+}
+
+fn str_arg<'a>(args: &'a Map<String, Value>, name: &str) -> Result<&'a str> {
+    opt_str(args, name)?.with_context(|| format!("Missing argument: {name}"))
+}
+
+fn opt_str<'a>(args: &'a Map<String, Value>, name: &str) -> Result<Option<&'a str>> {
+    match args.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s)),
+        Some(_) => Err(anyhow::anyhow!("Argument {name} must be a string")),
+    }
+}
+
+fn bool_arg(args: &Map<String, Value>, name: &str) -> Result<bool> {
+    match args.get(name) {
+        None | Some(Value::Null) => Ok(false),
+        Some(Value::Bool(b)) => Ok(*b),
+        Some(_) => Err(anyhow::anyhow!("Argument {name} must be a boolean")),
+    }
+}
+
+fn opt_usize(args: &Map<String, Value>, name: &str) -> Result<Option<usize>> {
+    match args.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => value
+            .as_u64()
+            .map(|n| Some(n as usize))
+            .with_context(|| format!("Argument {name} must be a non-negative integer")),
+    }
+}
+
+/// A list argument, given as an array of strings or one comma-separated
+/// string.
+fn list_arg(args: &Map<String, Value>, name: &str) -> Result<Vec<String>> {
+    match args.get(name) {
+        None | Some(Value::Null) => Ok(Vec::new()),
+        Some(Value::String(s)) => Ok(s.split(',').map(|p| p.trim().to_string()).collect()),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| {
+                item.as_str()
+                    .map(str::to_string)
+                    .with_context(|| format!("Argument {name} must be a list of strings"))
+            })
+            .collect(),
+        Some(_) => Err(anyhow::anyhow!("Argument {name} must be a list of strings")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{handle_message, truncate};
+
+    #[test]
+    fn answers_requests_and_ignores_notifications() {
+        let init = handle_message(
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2024-11-05"}}"#,
+        )
+        .unwrap();
+        assert_eq!(init["result"]["protocolVersion"], "2024-11-05");
+        assert!(
+            handle_message(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#).is_none()
+        );
+        let unknown = handle_message(r#"{"jsonrpc":"2.0","id":2,"method":"nope"}"#).unwrap();
+        assert_eq!(unknown["error"]["code"], -32601);
+        let failed = handle_message(
+            r#"{"jsonrpc":"2.0","id":3,"method":"tools/call","params":{"name":"render","arguments":{}}}"#,
+        )
+        .unwrap();
+        assert_eq!(failed["result"]["isError"], true);
+    }
+
+    #[test]
+    fn truncation_notes_the_cut_lines() {
+        assert_eq!(
+            truncate("a\nb\nc\n", 2),
+            "a\nb\n… 1 of 3 lines not shown (raise max_lines, or narrow with paths/select)\n"
+        );
+        assert_eq!(truncate("a\nb\n", 0), "a\nb\n");
+    }
+}
@@ -0,0 +1,612 @@
+//! Structural three-way merge (`unxml merge-driver BASE OURS THEIRS`).
+//!
+//! Git's line merge reports a conflict whenever both branches touched nearby
+//! lines of a large XML file, even when they edited different elements. As a
+//! merge driver (`--init-git --merge`), unxml merges the element trees
+//! instead and writes the result over OURS, the way git expects:
+//!
+//!  - Siblings are matched like `unxml diff` matches them (`--key` rules,
+//!    built-in `id`/`key`/`name` keys, identical subtrees, then similarity)
+//!    against the common ancestor, so an edit on one side and a reorder or an
+//!    unrelated edit on the other combine cleanly.
+//!  - Attributes and leaf text merge value by value: a side that left a value
+//!    as it was in BASE takes the other side's.
+//!  - Additions from both sides are kept, in each side's position; the same
+//!    element added on both sides is kept once.
+//!  - Only genuinely conflicting nodes get git-style markers: two different
+//!    edits of one value, or an edit on one side of an element the other
+//!    deleted. The markers wrap just that element (or just its start tag,
+//!    for an attribute conflict), and the exit status is non-zero.
+//!
+//! When only one side changed anything, that side's file is kept byte for
+//! byte; otherwise the result is re-serialised (indented, attributes sorted).
+//! Mixed content is merged as a whole element. Inputs that don't parse as XML
+//! (say, a file already holding conflict markers) fall back to
+//! `git merge-file`, and so do inputs the element tree can't carry through a
+//! re-serialisation — a DOCTYPE, processing instructions, CDATA sections,
+//! `xml:space="preserve"` text, or text whose leading or trailing whitespace
+//! the tree trims — rather than lose them from a clean merge.
+//!
+//! The `key` rules of `.unxml.toml` (and the user config) apply as they do
+//! for the textconv driver, matched against `--path` (git's `%P`); `--key`
+//! rules on the command line are tried first.
+
+use std::collections::BTreeSet;
+use std::process::Command;
+
+use anyhow::{Context, Result};
+use quick_xml::Reader;
+use quick_xml::events::Event;
+
+use crate::canonical::{KeyRule, signature};
+use crate::cli::MergeCli;
+use crate::config::Config;
+use crate::diff::{Step, identity, match_siblings};
+use crate::model::{NodeRef, XmlElement};
+use crate::parse::{ParsedXml, parse_xml, read_file_lenient};
+use crate::write::{open_tag, write_element};
+
+/// One piece of merged content.
+enum Merged {
+    /// A subtree taken whole from one side.
+    Node(XmlElement),
+    /// An element whose children were merged one by one. `tag` carries the
+    /// merged name and attributes; `tag_conflict` both sides' start tags when
+    /// their attributes conflict.
+    Element {
+        tag: XmlElement,
+        tag_conflict: Option<Box<(XmlElement, XmlElement)>>,
+        content: Vec<Merged>,
+    },
+    Comment(String),
+    /// Both sides' versions of a node; `None` where a side deleted it.
+    Conflict {
+        ours: Option<XmlElement>,
+        theirs: Option<XmlElement>,
+    },
+}
+
+/// The three-way value merge: a side that kept the base value yields to the
+/// other side. `None` when both sides changed it differently.
+fn merge3<T: PartialEq + Clone>(
+    base: Option<&T>,
+    ours: Option<&T>,
+    theirs: Option<&T>,
+) -> Option<Option<T>> {
+    if ours == theirs || base == theirs {
+        Some(ours.cloned())
+    } else if base == ours {
+        Some(theirs.cloned())
+    } else {
+        None
+    }
+}
+
+/// For each element of `left`, the `right` element it pairs with.
+fn partners(left: &[XmlElement], right: &[XmlElement], keys: &[KeyRule]) -> Vec<Option<usize>> {
+    let mut partner = vec![None; left.len()];
+    for step in match_siblings(left, right, keys) {
+        if let Step::Both(i, j) = step {
+            partner[i] = Some(j);
+        }
+    }
+    partner
+}
+
+/// The comments among an element's nodes, each with the index of the child
+/// it follows (`None` before the first child).
+fn comments(elem: &XmlElement) -> Vec<(Option<usize>, String)> {
+    let mut last = None;
+    let mut out = Vec::new();
+    for node in &elem.nodes {
+        match node {
+            NodeRef::Child(i) => last = Some(*i),
+            NodeRef::Comment { text, .. } => out.push((last, text.clone())),
+            NodeRef::Text(_) => {}
+        }
+    }
+    out
+}
+
+/// An element's name and attributes without content.
+fn bare(elem: &XmlElement) -> XmlElement {
+    let mut tag = XmlElement::new(elem.name.clone());
+    tag.attributes = elem.attributes.clone();
+    tag
+}
+
+struct Merger<'a> {
+    keys: &'a [KeyRule],
+    conflicts: usize,
+}
+
+impl Merger<'_> {
+    fn conflict(&mut self, ours: Option<&XmlElement>, theirs: Option<&XmlElement>) -> Merged {
+        self.conflicts += 1;
+        Merged::Conflict {
+            ours: ours.cloned(),
+            theirs: theirs.cloned(),
+        }
+    }
+
+    /// Merge two versions of one element, with their common ancestor if any.
+    fn element(
+        &mut self,
+        base: Option<&XmlElement>,
+        ours: &XmlElement,
+        theirs: &XmlElement,
+    ) -> Merged {
+        let (o, t) = (signature(ours), signature(theirs));
+        if o == t {
+            return Merged::Node(ours.clone());
+        }
+        if let Some(base) = base {
+            let b = signature(base);
+            if b == o {
+                return Merged::Node(theirs.clone());
+            }
+            if b == t {
+                return Merged::Node(ours.clone());
+            }
+        }
+
+        // Attributes merge value by value.
+        let names: BTreeSet<&String> = ours
+            .attributes
+            .keys()
+            .chain(theirs.attributes.keys())
+            .collect();
+        let mut attributes = Some(std::collections::HashMap::new());
+        for name in names {
+            let value = merge3(
+                base.and_then(|b| b.attributes.get(name)),
+                ours.attributes.get(name),
+                theirs.attributes.get(name),
+            );
+            match (value, attributes.as_mut()) {
+                (Some(Some(value)), Some(merged)) => {
+                    merged.insert(name.clone(), value);
+                }
+                (Some(None), _) => {}
+                _ => attributes = None,
+            }
+        }
+
+        let leaf = |e: &XmlElement| e.children.is_empty();
+        let mixed = ours.is_mixed() || theirs.is_mixed() || base.is_some_and(XmlElement::is_mixed);
+        if mixed || leaf(ours) != leaf(theirs) {
+            return self.conflict(Some(ours), Some(theirs));
+        }
+        if leaf(ours) {
+            // Leaf text merges like an attribute value; a leaf is small, so a
+            // conflict anywhere in it conflicts the whole element.
+            let text = |e: &XmlElement| e.text_content.trim().to_string();
+            let merged_text = merge3(
+                base.map(text).as_ref(),
+                Some(&text(ours)),
+                Some(&text(theirs)),
+            );
+            let (Some(attributes), Some(Some(merged_text))) = (attributes, merged_text) else {
+                return self.conflict(Some(ours), Some(theirs));
+            };
+            let mut merged = ours.clone();
+            merged.attributes = attributes;
+            if merged_text != text(ours) {
+                merged.set_leaf_text(merged_text);
+            }
+            return Merged::Node(merged);
+        }
+
+        let (tag, tag_conflict) = match attributes {
+            Some(attributes) => {
+                let mut tag = bare(ours);
+                tag.attributes = attributes;
+                (tag, None)
+            }
+            None => {
+                self.conflicts += 1;
+                (bare(ours), Some(Box::new((bare(ours), bare(theirs)))))
+            }
+        };
+        let empty = XmlElement::new(String::new());
+        let base = base.unwrap_or(&empty);
+        let content = self.children(base, ours, theirs);
+        Merged::Element {
+            tag,
+            tag_conflict,
+            content,
+        }
+    }
+
+    /// Merge the children (and comments) of three versions of an element.
+    fn children(
+        &mut self,
+        base: &XmlElement,
+        ours: &XmlElement,
+        theirs: &XmlElement,
+    ) -> Vec<Merged> {
+        let (b, o, t) = (&base.children, &ours.children, &theirs.children);
+        let base_ours = partners(b, o, self.keys);
+        let base_theirs = partners(b, t, self.keys);
+        let mut ours_base = vec![None; o.len()];
+        for (i, j) in base_ours.iter().enumerate() {
+            if let Some(j) = j {
+                ours_base[*j] = Some(i);
+            }
+        }
+        let mut theirs_base = vec![None; t.len()];
+        for (i, j) in base_theirs.iter().enumerate() {
+            if let Some(j) = j {
+                theirs_base[*j] = Some(i);
+            }
+        }
+
+        // The same element added on both sides: same name, and the same
+        // identity key or an identical subtree.
+        let mut added_pair: Vec<Option<usize>> = vec![None; t.len()];
+        let mut ours_taken = vec![false; o.len()];
+        for (j, theirs_child) in t.iter().enumerate() {
+            if theirs_base[j].is_some() {
+                continue;
+            }
+            let id = identity(theirs_child, self.keys);
+            let sig = signature(theirs_child);
+            added_pair[j] = (0..o.len()).find(|&i| {
+                !ours_taken[i]
+                    && ours_base[i].is_none()
+                    && o[i].name == theirs_child.name
+                    && (id.is_some() && identity(&o[i], self.keys) == id || signature(&o[i]) == sig)
+            });
+            if let Some(i) = added_pair[j] {
+                ours_taken[i] = true;
+            }
+        }
+
+        // The ours child a theirs child corresponds to, if any.
+        let in_ours = |j: usize| match theirs_base[j] {
+            Some(b) => base_ours[b],
+            None => added_pair[j],
+        };
+        // Theirs-only content goes after the ours counterpart of the nearest
+        // preceding theirs child (slot `i + 1`), or first (slot 0).
+        let slot_after = |j: usize| (0..j).rev().find_map(in_ours).map_or(0, |i| i + 1);
+
+        let mut merged: Vec<Vec<Merged>> = (0..o.len()).map(|_| Vec::new()).collect();
+        let mut inserted: Vec<Vec<Merged>> = (0..=o.len()).map(|_| Vec::new()).collect();
+        for (i, ours_child) in o.iter().enumerate() {
+            let result = match ours_base[i] {
+                Some(bi) => match base_theirs[bi] {
+                    Some(j) => Some(self.element(Some(&b[bi]), ours_child, &t[j])),
+                    // Deleted by theirs: fine unless ours edited it.
+                    None if signature(ours_child) == signature(&b[bi]) => None,
+                    None => Some(self.conflict(Some(ours_child), None)),
+                },
+                None => match added_pair.iter().position(|&p| p == Some(i)) {
+                    Some(j) => Some(self.element(None, ours_child, &t[j])),
+                    None => Some(Merged::Node(ours_child.clone())),
+                },
+            };
+            merged[i].extend(result);
+        }
+        for (j, theirs_child) in t.iter().enumerate() {
+            if in_ours(j).is_some() {
+                continue;
+            }
+            let result = match theirs_base[j] {
+                // Deleted by ours: fine unless theirs edited it.
+                Some(bi) if signature(theirs_child) == signature(&b[bi]) => continue,
+                Some(_) => self.conflict(None, Some(theirs_child)),
+                None => Merged::Node(theirs_child.clone()),
+            };
+            inserted[slot_after(j)].push(result);
+        }
+
+        // Comments come from the side that changed them, after the child they
+        // followed there.
+        let texts = |e: &XmlElement| {
+            comments(e)
+                .into_iter()
+                .map(|(_, text)| text)
+                .collect::<Vec<_>>()
+        };
+        if texts(ours) != texts(base) || texts(theirs) == texts(base) {
+            for (after, text) in comments(ours) {
+                inserted[after.map_or(0, |i| i + 1)].insert(0, Merged::Comment(text));
+            }
+        } else {
+            for (after, text) in comments(theirs) {
+                let slot = match after {
+                    Some(j) => in_ours(j).map_or_else(|| slot_after(j), |i| i + 1),
+                    None => 0,
+                };
+                inserted[slot].push(Merged::Comment(text));
+            }
+        }
+
+        let mut out = Vec::new();
+        let mut inserted = inserted.into_iter();
+        out.extend(inserted.next().unwrap_or_default());
+        for (own, after) in merged.into_iter().zip(inserted) {
+            out.extend(own);
+            out.extend(after);
+        }
+        out
+    }
+}
+
+fn write_merged(items: &[Merged], indent: usize, out: &mut String) {
+    let ind = "  ".repeat(indent);
+    for item in items {
+        match item {
+            Merged::Node(elem) => write_element(elem, indent, out),
+            Merged::Comment(text) => out.push_str(&format!("{ind}<!-- {text} -->\n")),
+            Merged::Conflict { ours, theirs } => {
+                out.push_str("<<<<<<< ours\n");
+                if let Some(elem) = ours {
+                    write_element(elem, indent, out);
+                }
+                out.push_str("=======\n");
+                if let Some(elem) = theirs {
+                    write_element(elem, indent, out);
+                }
+                out.push_str(">>>>>>> theirs\n");
+            }
+            Merged::Element {
+                tag,
+                tag_conflict,
+                content,
+            } => {
+                match tag_conflict.as_deref() {
+                    Some((ours, theirs)) => out.push_str(&format!(
+                        "<<<<<<< ours\n{ind}{}>\n=======\n{ind}{}>\n>>>>>>> theirs\n",
+                        open_tag(ours),
+                        open_tag(theirs)
+                    )),
+                    None => out.push_str(&format!("{ind}{}>\n", open_tag(tag))),
+                }
+                write_merged(content, indent + 1, out);
+                out.push_str(&format!("{ind}</{}>\n", tag.name));
+            }
+        }
+    }
+}
+
+/// Merge three parsed documents. Returns the merged XML and the number of
+/// conflicts marked in it.
+fn merge_documents(
+    base: &ParsedXml,
+    ours: &ParsedXml,
+    theirs: &ParsedXml,
+    keys: &[KeyRule],
+) -> (String, usize) {
+    let document = |roots: &[XmlElement]| {
+        let mut doc = XmlElement::new(String::new());
+        doc.children = roots.to_vec();
+        doc.nodes = (0..roots.len()).map(NodeRef::Child).collect();
+        doc
+    };
+    let mut merger = Merger { keys, conflicts: 0 };
+    let items = merger.children(
+        &document(&base.roots),
+        &document(&ours.roots),
+        &document(&theirs.roots),
+    );
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    for (at, text) in &ours.top_comments {
+        if *at == 0 {
+            out.push_str(&format!("<!-- {text} -->\n"));
+        }
+    }
+    write_merged(&items, 0, &mut out);
+    for (at, text) in &ours.top_comments {
+        if *at > 0 {
+            out.push_str(&format!("<!-- {text} -->\n"));
+        }
+    }
+    (out, merger.conflicts)
+}
+
+/// The textual merge git would have done, for inputs that aren't XML. Writes
+/// the result over `ours`; returns whether conflicts remain.
+fn merge_file(args: &MergeCli) -> Result<bool> {
+    let status = Command::new("git")
+        .args(["merge-file", "-L", "ours", "-L", "base", "-L", "theirs"])
+        .args([&args.ours, &args.base, &args.theirs])
+        .status()
+        .context("Failed to run `git merge-file` (is git installed and on PATH?)")?;
+    match status.code() {
+        Some(0) => Ok(false),
+        Some(code) if code > 0 => Ok(true),
+        _ => Err(anyhow::anyhow!("`git merge-file` failed")),
+    }
+}
+
+/// The first construct in `text` that `parse_xml` drops or rewrites, so a
+/// re-serialised merge would silently lose it. Whitespace between elements is
+/// layout, but around other text, or as an element's whole content, it is
+/// data the tree trims.
+fn unkept_construct(text: &str) -> Option<&'static str> {
+    let mut reader = Reader::from_str(text);
+    // Whether the last event opened an element, and whether whitespace alone
+    // has followed that since.
+    let (mut opened, mut blank) = (false, false);
+    loop {
+        let event = reader.read_event();
+        if let Ok(Event::Text(t)) = &event {
+            let text = String::from_utf8_lossy(t);
+            let trimmed = text.trim();
+            if !trimmed.is_empty() && trimmed.len() != text.len() {
+                return Some("text with leading or trailing whitespace");
+            }
+            blank = opened && trimmed.is_empty() && !text.is_empty();
+            opened = false;
+            continue;
+        }
+        if blank && matches!(event, Ok(Event::End(_))) {
+            return Some("whitespace-only text");
+        }
+        opened = matches!(event, Ok(Event::Start(_)));
+        blank = false;
+        match event {
+            Ok(Event::DocType(_)) => return Some("a DOCTYPE"),
+            Ok(Event::PI(_)) => return Some("a processing instruction"),
+            Ok(Event::CData(_)) => return Some("a CDATA section"),
+            Ok(Event::Start(e) | Event::Empty(e))
+                if e.attributes().flatten().any(|attr| {
+                    attr.key.as_ref() == b"xml:space" && attr.value.as_ref() == b"preserve"
+                }) =>
+            {
+                return Some("xml:space=\"preserve\" content");
+            }
+            Ok(Event::Eof) | Err(_) => return None,
+            Ok(_) => {}
+        }
+    }
+}
+
+/// `unxml merge-driver BASE OURS THEIRS`: merge into OURS. Returns whether
+/// conflicts were marked, for git's merge-driver exit status.
+pub(crate) fn run_merge_driver(args: &MergeCli) -> Result<bool> {
+    let config = if args.no_config {
+        Config::default()
+    } else {
+        Config::discover()?
+    };
+    let settings = config.settings_for(Some(args.path.as_deref().unwrap_or(&args.ours)));
+    let mut keys = Vec::new();
+    for spec in args.key.iter().chain(&settings.key) {
+        keys.push(KeyRule::parse(spec)?);
+    }
+    let base_text = read_file_lenient(&args.base)?;
+    let ours_text = read_file_lenient(&args.ours)?;
+    let theirs_text = read_file_lenient(&args.theirs)?;
+    // An add/add conflict has an empty base.
+    let base = match base_text.trim() {
+        "" => Ok(ParsedXml {
+            roots: Vec::new(),
+            top_comments: Vec::new(),
+        }),
+        _ => parse_xml(&base_text),
+    };
+    let (Ok(base), Ok(ours), Ok(theirs)) = (base, parse_xml(&ours_text), parse_xml(&theirs_text))
+    else {
+        return merge_file(args);
+    };
+
+    let whole = |doc: &ParsedXml| doc.roots.iter().map(signature).collect::<Vec<_>>();
+    if whole(&theirs) == whole(&base) || whole(&ours) == whole(&theirs) {
+        return Ok(false);
+    }
+    if whole(&ours) == whole(&base) {
+        std::fs::write(&args.ours, theirs_text.as_bytes())
+            .with_context(|| format!("Failed to write {}", args.ours))?;
+        return Ok(false);
+    }
+    if let Some(construct) = [&base_text, &ours_text, &theirs_text]
+        .into_iter()
+        .find_map(|text| unkept_construct(text))
+    {
+        eprintln!("unxml merge-driver: {construct} can't be merged structurally; merging lines");
+        return merge_file(args);
+    }
+    let (merged, conflicts) = merge_documents(&base, &ours, &theirs, &keys);
+    std::fs::write(&args.ours, merged).with_context(|| format!("Failed to write {}", args.ours))?;
+    Ok(conflicts > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_documents, unkept_construct};
+    use crate::parse::parse_xml;
+
+    fn merge(base: &str, ours: &str, theirs: &str) -> (String, usize) {
+        merge_documents(
+            &parse_xml(base).unwrap(),
+            &parse_xml(ours).unwrap(),
+            &parse_xml(theirs).unwrap(),
+            &[],
+        )
+    }
+
+    #[test]
+    fn combines_independent_edits() {
+        let base = r#"<config><item id="a" v="1"/><item id="b" v="1"/><name>x</name></config>"#;
+        let ours = r#"<config><item id="a" v="2"/><item id="b" v="1"/><name>x</name><new>o</new></config>"#;
+        let theirs = r#"<config><item id="b" v="3"/><item id="a" v="1"/><name>y</name></config>"#;
+        assert_eq!(
+            merge(base, ours, theirs),
+            (
+                concat!(
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+                    "<config>\n",
+                    "  <item id=\"a\" v=\"2\"/>\n",
+                    "  <item id=\"b\" v=\"3\"/>\n",
+                    "  <name>y</name>\n",
+                    "  <new>o</new>\n",
+                    "</config>\n",
+                )
+                .to_string(),
+                0
+            )
+        );
+    }
+
+    #[test]
+    fn marks_only_conflicting_nodes() {
+        let base = r#"<config a="1"><item id="a">1</item><item id="b">1</item></config>"#;
+        let ours = r#"<config a="2"><item id="a">2</item></config>"#;
+        let theirs =
+            r#"<config a="3"><item id="a">3</item><item id="b">1</item><item id="c"/></config>"#;
+        let (merged, conflicts) = merge(base, ours, theirs);
+        assert_eq!(conflicts, 2);
+        assert_eq!(
+            merged,
+            concat!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+                "<<<<<<< ours\n",
+                "<config a=\"2\">\n",
+                "=======\n",
+                "<config a=\"3\">\n",
+                ">>>>>>> theirs\n",
+                "<<<<<<< ours\n",
+                "  <item id=\"a\">2</item>\n",
+                "=======\n",
+                "  <item id=\"a\">3</item>\n",
+                ">>>>>>> theirs\n",
+                "  <item id=\"c\"/>\n",
+                "</config>\n",
+            )
+        );
+    }
+
+    #[test]
+    fn finds_constructs_a_merge_would_lose() {
+        assert_eq!(unkept_construct("<a><b x='1'/></a>"), None);
+        assert_eq!(
+            unkept_construct("<!DOCTYPE a SYSTEM 'a.dtd'><a/>"),
+            Some("a DOCTYPE")
+        );
+        assert_eq!(
+            unkept_construct("<?xml-stylesheet href='s.xsl'?><a/>"),
+            Some("a processing instruction")
+        );
+        assert_eq!(
+            unkept_construct("<a><script><![CDATA[x < y]]></script></a>"),
+            Some("a CDATA section")
+        );
+        assert_eq!(
+            unkept_construct("<a><pre xml:space='preserve'>  x </pre></a>"),
+            Some("xml:space=\"preserve\" content")
+        );
+        assert_eq!(unkept_construct("<a>\n  <b>x</b>\n  <c/>\n</a>\n"), None);
+        assert_eq!(
+            unkept_construct("<a><b> x</b></a>"),
+            Some("text with leading or trailing whitespace")
+        );
+        assert_eq!(
+            unkept_construct("<a><b>  </b></a>"),
+            Some("whitespace-only text")
+        );
+    }
+}
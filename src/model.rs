@@ -8,7 +8,7 @@ use crate::xslt::TemplateRegistry;
 /// The variant decides only where a chain may *start*; the descent through
 /// pass-through descendants is always structural (see `XmlElement::is_chain_link`).
 #[derive(Debug, Clone, Default)]
-pub(crate) enum Collapse {
+pub enum Collapse {
     /// `--collapse` absent: never collapse.
     #[default]
     Off,
@@ -20,15 +20,18 @@ pub(crate) enum Collapse {
     Only(HashSet<String>),
 }
 
+/// The rendering mode. Start from `default()` or `from_mode`; new modes may
+/// add fields.
 #[derive(Debug, Clone, Default)]
-pub(crate) struct FormatOpts {
-    pub(crate) special: bool,
-    pub(crate) xslt: bool,
-    pub(crate) schematron: bool,
-    pub(crate) xsd: bool,
-    pub(crate) wsdl: bool,
-    pub(crate) msbuild: bool,
-    pub(crate) collapse: Collapse,
+#[non_exhaustive]
+pub struct FormatOpts {
+    pub special: bool,
+    pub xslt: bool,
+    pub schematron: bool,
+    pub xsd: bool,
+    pub wsdl: bool,
+    pub msbuild: bool,
+    pub collapse: Collapse,
 }
 
 impl FormatOpts {
@@ -59,6 +62,27 @@ impl FormatOpts {
     pub(crate) fn has_mode(&self) -> bool {
         self.special || self.xslt || self.schematron || self.xsd || self.wsdl || self.msbuild
     }
+
+    /// The mode named like its CLI flag (`xslt`, `xsd`, `msbuild`, …); `xml`
+    /// is the plain default. For the bindings, which take the mode as a string.
+    pub fn from_mode(name: &str) -> anyhow::Result<Self> {
+        let mut opts = Self::default();
+        match name.to_lowercase().as_str() {
+            "xml" => {}
+            "special" => opts.special = true,
+            "xslt" => opts.xslt = true,
+            "schematron" => opts.schematron = true,
+            "xsd" => opts.xsd = true,
+            "wsdl" => opts.wsdl = true,
+            "msbuild" => opts.msbuild = true,
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Unsupported mode: {name}. Use 'xml', 'special', 'xslt', 'schematron', 'xsd', 'wsdl' or 'msbuild'"
+                ));
+            }
+        }
+        Ok(opts)
+    }
 }
 
 /// One item in an element's content in document order. `Text` is a literal text
@@ -66,7 +90,7 @@ impl FormatOpts {
 /// faithful interleaving of text and child elements (mixed content) that the
 /// flat `text_content` + `children` split alone cannot represent.
 #[derive(Debug, Clone)]
-pub(crate) enum NodeRef {
+pub enum NodeRef {
     Text(String),
     Child(usize),
     /// An XML comment's inner text (trimmed), kept in document order so it
@@ -82,26 +106,33 @@ pub(crate) enum NodeRef {
     },
 }
 
+/// A parsed element. Build one with `new`; fields may be added.
 #[derive(Debug, Clone)]
-pub(crate) struct XmlElement {
-    pub(crate) name: String,
-    pub(crate) attributes: HashMap<String, String>,
+#[non_exhaustive]
+pub struct XmlElement {
+    pub name: String,
+    pub attributes: HashMap<String, String>,
     /// All text runs concatenated — kept for the common scalar case
     /// (`<a>text</a>` → `a = text`) and for paths that don't need ordering.
-    pub(crate) text_content: String,
-    pub(crate) children: Vec<XmlElement>,
+    pub text_content: String,
+    pub children: Vec<XmlElement>,
     /// Document-order view of text runs and child elements. Used to render mixed
     /// content faithfully; empty on elements built outside the parsers.
-    pub(crate) nodes: Vec<NodeRef>,
+    pub nodes: Vec<NodeRef>,
     /// Verbatim source between this element's start and end tags, captured by the
     /// XML parser. Used to render shallow mixed content (prose with inline spans)
     /// as a single line of original XML rather than a stack of flattened nodes.
     /// `None` for elements built outside the XML parser (e.g. the HTML path).
-    pub(crate) inner_source: Option<String>,
+    pub inner_source: Option<String>,
+    /// Byte range of the element in the XML source, from the `<` of its start
+    /// tag to just past its end tag. `None` for elements built outside the
+    /// XML parser. Lets `unxml lsp` map outline entries and rendered lines
+    /// back to the source.
+    pub span: Option<std::ops::Range<usize>>,
 }
 
 impl XmlElement {
-    pub(crate) fn new(name: String) -> Self {
+    pub fn new(name: String) -> Self {
         Self {
             name,
             attributes: HashMap::new(),
@@ -109,9 +140,20 @@ impl XmlElement {
             children: Vec::new(),
             nodes: Vec::new(),
             inner_source: None,
+            span: None,
         }
     }
 
+    /// Replace a leaf element's text, keeping `nodes` in step: its text runs
+    /// are what `write_xml` and mixed-content rendering read. Comments stay
+    /// where they were.
+    pub(crate) fn set_leaf_text(&mut self, text: String) {
+        self.nodes.retain(|n| !matches!(n, NodeRef::Text(_)));
+        self.nodes.insert(0, NodeRef::Text(text.clone()));
+        self.text_content = text;
+        self.inner_source = None;
+    }
+
     /// True when this element interleaves non-empty text with child elements —
     /// the case the scalar `name = text` form cannot represent faithfully.
     pub(crate) fn is_mixed(&self) -> bool {
@@ -0,0 +1,531 @@
+//! Value normalisation under `--canonical` (`--normalize`).
+//!
+//! Exports that mean the same thing often spell values differently: `1.0` vs
+//! `1.00`, `true` vs `1`, `+02:00` vs `Z`, or a line-wrapped paragraph. The
+//! normalisers rewrite leaf text and attribute values to one spelling before
+//! the canonical sort, so such reformatting no longer shows up as a diff:
+//!
+//!  - `whitespace` trims and collapses whitespace runs to one space.
+//!  - `numbers` drops a leading `+`, trailing fraction zeros and `-0`.
+//!  - `booleans` spells xs:boolean values `true` / `false`.
+//!  - `datetimes` converts zoned date-times and times to UTC (`Z`) and drops
+//!    trailing zeros of fractional seconds.
+//!
+//! Without a schema the rules only touch values that can't mean anything
+//! else: a number is rewritten only when it has both a sign and a fraction
+//! (`+0.50`, `-0.0`), since `1.10` may be a version and `+44` a dialling
+//! code, and a boolean only has its case folded. With `--schema FILE.xsd`,
+//! elements and attributes whose simple type the schema declares are
+//! normalised by that type instead: `xs:decimal` drops trailing fraction
+//! zeros, `xs:int` strips leading zeros, `xs:boolean` maps `1` / `0`, and an
+//! `xs:string` is left alone however numeric it looks. Declarations are
+//! matched by local name; a name declared with conflicting types falls back to
+//! the schema-less rules.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+use crate::model::XmlElement;
+use crate::parse::parse_xml;
+#[cfg(feature = "cli")]
+use crate::parse::read_file_lenient;
+use crate::types::{is_xsd_date, is_xsd_datetime, split_timezone, xsd_local};
+
+/// The value class of a declared simple type, as far as normalisation cares.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Decimal,
+    Float,
+    Boolean,
+    DateTime,
+    Date,
+    Time,
+    Other,
+}
+
+impl Kind {
+    /// The class of a built-in XSD type's local name; `None` for the string,
+    /// binary and other types no rule applies to.
+    fn of_builtin(local: &str) -> Option<Self> {
+        Some(match local {
+            "decimal" | "integer" | "nonPositiveInteger" | "negativeInteger" | "long" | "int"
+            | "short" | "byte" | "nonNegativeInteger" | "unsignedLong" | "unsignedInt"
+            | "unsignedShort" | "unsignedByte" | "positiveInteger" => Self::Decimal,
+            "float" | "double" => Self::Float,
+            "boolean" => Self::Boolean,
+            "dateTime" | "dateTimeStamp" => Self::DateTime,
+            "date" => Self::Date,
+            "time" => Self::Time,
+            _ => return None,
+        })
+    }
+}
+
+/// Simple types an XSD declares for element and attribute local names.
+#[derive(Debug, Default)]
+pub(crate) struct SchemaTypes {
+    elements: HashMap<String, Kind>,
+    attributes: HashMap<String, Kind>,
+}
+
+impl SchemaTypes {
+    /// Read the declarations of one `.xsd` file.
+    #[cfg(feature = "cli")]
+    pub(crate) fn load(path: &str) -> Result<Self> {
+        Self::parse(&read_file_lenient(path)?, path)
+    }
+
+    /// The declarations of the schema text `content`, read from `path`.
+    pub(crate) fn parse(content: &str, path: &str) -> Result<Self> {
+        let roots = parse_xml(content)
+            .with_context(|| format!("Failed to parse schema: {path}"))?
+            .roots;
+
+        // Named simple types, and complex types with simple content (UBL's
+        // `AmountType`), map to the type they restrict or extend.
+        let mut bases = HashMap::new();
+        for root in &roots {
+            collect_bases(root, &mut bases);
+        }
+        let mut types = Self::default();
+        let mut conflicts = Vec::new();
+        for root in &roots {
+            types.collect_declarations(root, &bases, &mut conflicts);
+        }
+        for (is_attr, name) in conflicts {
+            let map = if is_attr {
+                &mut types.attributes
+            } else {
+                &mut types.elements
+            };
+            map.remove(&name);
+        }
+        Ok(types)
+    }
+
+    fn collect_declarations(
+        &mut self,
+        elem: &XmlElement,
+        bases: &HashMap<String, String>,
+        conflicts: &mut Vec<(bool, String)>,
+    ) {
+        let local = xsd_local(&elem.name);
+        if let ("element" | "attribute", Some(name)) = (local, elem.attributes.get("name"))
+            && let Some(base) = declared_base(elem)
+        {
+            let kind = resolve(&base, bases);
+            let map = if local == "attribute" {
+                &mut self.attributes
+            } else {
+                &mut self.elements
+            };
+            match map.get(name) {
+                Some(seen) if *seen != kind => {
+                    conflicts.push((local == "attribute", name.clone()));
+                }
+                _ => {
+                    map.insert(name.clone(), kind);
+                }
+            }
+        }
+        for child in &elem.children {
+            self.collect_declarations(child, bases, conflicts);
+        }
+    }
+}
+
+/// The type a declaration names: its `type` attribute, or the base of an
+/// anonymous `simpleType` / `simpleContent` defined inline.
+fn declared_base(decl: &XmlElement) -> Option<String> {
+    if let Some(t) = decl.attributes.get("type") {
+        return Some(t.clone());
+    }
+    decl.children.iter().find_map(derived_base)
+}
+
+/// The `base` of a `simpleType` / `complexType` that restricts or extends a
+/// simple type.
+fn derived_base(def: &XmlElement) -> Option<String> {
+    match xsd_local(&def.name) {
+        "simpleType" | "complexType" | "simpleContent" => {
+            def.children
+                .iter()
+                .find_map(|child| match xsd_local(&child.name) {
+                    "restriction" | "extension" => child.attributes.get("base").cloned(),
+                    _ => derived_base(child),
+                })
+        }
+        _ => None,
+    }
+}
+
+fn collect_bases(elem: &XmlElement, bases: &mut HashMap<String, String>) {
+    if let ("simpleType" | "complexType", Some(name)) =
+        (xsd_local(&elem.name), elem.attributes.get("name"))
+        && let Some(base) = derived_base(elem)
+    {
+        bases.insert(name.clone(), base);
+    }
+    for child in &elem.children {
+        collect_bases(child, bases);
+    }
+}
+
+/// Follow named types down to a built-in one. Unknown and string-like types
+/// are `Kind::Other`, which disables every typed rule for the name.
+fn resolve(type_name: &str, bases: &HashMap<String, String>) -> Kind {
+    let mut name = xsd_local(type_name);
+    for _ in 0..16 {
+        if let Some(base) = bases.get(name) {
+            name = xsd_local(base);
+        } else {
+            return Kind::of_builtin(name).unwrap_or(Kind::Other);
+        }
+    }
+    Kind::Other
+}
+
+/// The `--normalize` set, plus the schema types that steer it.
+#[derive(Debug, Default)]
+pub(crate) struct Normalizers {
+    pub(crate) numbers: bool,
+    pub(crate) booleans: bool,
+    pub(crate) whitespace: bool,
+    pub(crate) datetimes: bool,
+    pub(crate) schema: Option<SchemaTypes>,
+}
+
+impl Normalizers {
+    /// Enable one `--normalize` value.
+    pub(crate) fn enable(&mut self, name: &str) -> Result<()> {
+        match name {
+            "numbers" => self.numbers = true,
+            "booleans" => self.booleans = true,
+            "whitespace" => self.whitespace = true,
+            "datetimes" => self.datetimes = true,
+            "all" => {
+                self.numbers = true;
+                self.booleans = true;
+                self.whitespace = true;
+                self.datetimes = true;
+            }
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Unknown --normalize '{name}': use numbers, booleans, whitespace, datetimes or all"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        !(self.numbers || self.booleans || self.whitespace || self.datetimes)
+    }
+
+    /// The normalised spelling of one value, `None` when it is unchanged.
+    /// `declared` is the schema's type for the value's name, if any.
+    fn value(&self, declared: Option<Kind>, raw: &str) -> Option<String> {
+        let collapsed;
+        let value = if self.whitespace {
+            collapsed = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+            collapsed.as_str()
+        } else {
+            raw
+        };
+        let typed = match declared {
+            Some(Kind::Decimal) if self.numbers => normalize_decimal(value.trim(), true),
+            Some(Kind::Float) if self.numbers => {
+                normalize_decimal(value.trim(), true).or_else(|| normalize_float(value.trim()))
+            }
+            Some(Kind::Boolean) if self.booleans => match value.trim() {
+                "true" | "1" => Some("true".to_string()),
+                "false" | "0" => Some("false".to_string()),
+                _ => None,
+            },
+            Some(Kind::DateTime | Kind::Date | Kind::Time) if self.datetimes => {
+                normalize_temporal(value.trim())
+            }
+            Some(_) => None,
+            None => {
+                let v = value.trim();
+                if self.numbers && v.starts_with(['+', '-']) && v.contains('.') {
+                    normalize_decimal(v, false)
+                } else if self.booleans
+                    && ["true", "false"].iter().any(|b| v.eq_ignore_ascii_case(b))
+                {
+                    Some(v.to_ascii_lowercase())
+                } else if self.datetimes && (is_xsd_datetime(v) || is_xsd_date(v)) {
+                    normalize_temporal(v)
+                } else {
+                    None
+                }
+            }
+        };
+        let result = typed.unwrap_or_else(|| value.to_string());
+        (result != raw).then_some(result)
+    }
+
+    fn declared(&self, name: &str, is_attr: bool) -> Option<Kind> {
+        let schema = self.schema.as_ref()?;
+        let map = if is_attr {
+            &schema.attributes
+        } else {
+            &schema.elements
+        };
+        map.get(xsd_local(name)).copied()
+    }
+
+    /// Normalise an element subtree's attribute values and leaf text.
+    /// Namespace declarations and mixed-content prose are left alone.
+    pub(crate) fn normalize_element(&self, elem: &mut XmlElement) {
+        for (name, value) in elem.attributes.iter_mut() {
+            if name == "xmlns" || name.starts_with("xmlns:") {
+                continue;
+            }
+            if let Some(normalized) = self.value(self.declared(name, true), value) {
+                *value = normalized;
+            }
+        }
+        if elem.children.is_empty() {
+            if !elem.text_content.trim().is_empty()
+                && let Some(normalized) =
+                    self.value(self.declared(&elem.name, false), &elem.text_content)
+            {
+                elem.set_leaf_text(normalized);
+            }
+            return;
+        }
+        for child in &mut elem.children {
+            self.normalize_element(child);
+        }
+    }
+
+    /// Normalise a JSON value. Numbers and booleans are typed already, so
+    /// `numbers` only spells integral floats as integers (`1.0` → `1`);
+    /// strings get the `whitespace` and `datetimes` rules.
+    pub(crate) fn normalize_json(&self, value: &mut Value) {
+        match value {
+            Value::Object(object) => {
+                for member in object.values_mut() {
+                    self.normalize_json(member);
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.normalize_json(item);
+                }
+            }
+            Value::Number(n) if self.numbers => {
+                if let Some(f) = n.as_f64().filter(|_| n.is_f64())
+                    && f.fract() == 0.0
+                    && f.abs() < 9_007_199_254_740_992.0
+                {
+                    *value = Value::from(f as i64);
+                }
+            }
+            Value::String(s) => {
+                let rules = Self {
+                    whitespace: self.whitespace,
+                    datetimes: self.datetimes,
+                    ..Self::default()
+                };
+                if let Some(normalized) = rules.value(None, s) {
+                    *s = normalized;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// `[+-]digits[.digits]` in its shortest form. Leading zeros of the integer
+/// part are kept unless `strip_zeros` (for values known to be numeric).
+fn normalize_decimal(s: &str, strip_zeros: bool) -> Option<String> {
+    let (negative, unsigned) = match s.as_bytes().first()? {
+        b'-' => (true, &s[1..]),
+        b'+' => (false, &s[1..]),
+        _ => (false, s),
+    };
+    let (int, frac) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+    if int.len() + frac.len() == 0 || !int.bytes().chain(frac.bytes()).all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let int = if strip_zeros || int.is_empty() {
+        match int.trim_start_matches('0') {
+            "" => "0",
+            digits => digits,
+        }
+    } else {
+        int
+    };
+    let frac = frac.trim_end_matches('0');
+    let zero = int.bytes().all(|b| b == b'0') && frac.is_empty();
+    let mut out = String::new();
+    if negative && !zero {
+        out.push('-');
+    }
+    out.push_str(int);
+    if !frac.is_empty() {
+        out.push('.');
+        out.push_str(frac);
+    }
+    Some(out)
+}
+
+/// An `xs:double` in exponent form, spelled the way Rust prints the value.
+fn normalize_float(s: &str) -> Option<String> {
+    match s {
+        "INF" | "-INF" | "NaN" => None,
+        _ => s
+            .parse::<f64>()
+            .ok()
+            .filter(|f| f.is_finite())
+            .map(|f| f.to_string()),
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * (m + if m > 2 { -3 } else { 9 }) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// The inverse of `days_from_civil`.
+fn civil_from_days(z: i64) -> (i64, i64, i64) {
+    let z = z + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = mp + if mp < 10 { 3 } else { -9 };
+    (yoe + era * 400 + i64::from(m <= 2), m, d)
+}
+
+/// `hh:mm:ss[.frac]` as minutes of the day, seconds, and the trimmed fraction.
+fn parse_time(s: &str) -> Option<(i64, &str, &str)> {
+    let (hms, frac) = s.split_once('.').unwrap_or((s, ""));
+    let mut parts = hms.split(':');
+    let (h, m, sec) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() {
+        return None;
+    }
+    let minutes = h.parse::<i64>().ok()? * 60 + m.parse::<i64>().ok()?;
+    Some((minutes, sec, frac.trim_end_matches('0')))
+}
+
+fn format_time(minutes: i64, sec: &str, frac: &str) -> String {
+    let frac = if frac.is_empty() {
+        String::new()
+    } else {
+        format!(".{frac}")
+    };
+    format!("{:02}:{:02}:{sec}{frac}", minutes / 60, minutes % 60)
+}
+
+/// Zoned `xs:dateTime` / `xs:time` values in UTC, zoned dates with their zone
+/// spelled `Z` when it is UTC, fractional seconds without trailing zeros.
+fn normalize_temporal(s: &str) -> Option<String> {
+    let (local, zone) = split_timezone(s);
+    let zone_suffix = match zone {
+        Some(_) => "Z",
+        None => "",
+    };
+    let offset = zone.unwrap_or(0);
+    if let Some((date, time)) = local.split_once('T') {
+        let mut ymd = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+        let (y, m, d) = (ymd.next()??, ymd.next()??, ymd.next()??);
+        let (minutes, sec, frac) = parse_time(time)?;
+        let total = days_from_civil(y, m, d) * 1440 + minutes - offset;
+        let (y, m, d) = civil_from_days(total.div_euclid(1440));
+        let time = format_time(total.rem_euclid(1440), sec, frac);
+        return Some(format!("{y:04}-{m:02}-{d:02}T{time}{zone_suffix}"));
+    }
+    if let Some((minutes, sec, frac)) = parse_time(local) {
+        let time = format_time((minutes - offset).rem_euclid(1440), sec, frac);
+        return Some(format!("{time}{zone_suffix}"));
+    }
+    // A date's zone can't be shifted into the day; only UTC is respelled.
+    match zone {
+        Some(0) => Some(format!("{local}Z")),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Kind, Normalizers, normalize_decimal, normalize_temporal};
+    use crate::parse::parse_xml;
+
+    #[test]
+    fn spells_numbers_and_datetimes_one_way() {
+        assert_eq!(normalize_decimal("1.00", false).as_deref(), Some("1"));
+        assert_eq!(normalize_decimal("+0.50", false).as_deref(), Some("0.5"));
+        assert_eq!(normalize_decimal("-0.0", false).as_deref(), Some("0"));
+        assert_eq!(normalize_decimal("007", false).as_deref(), Some("007"));
+        assert_eq!(normalize_decimal("007", true).as_deref(), Some("7"));
+        assert_eq!(normalize_decimal("1e3", true), None);
+        assert_eq!(
+            normalize_temporal("2026-10-18T01:30:00.500+02:00").as_deref(),
+            Some("2026-10-17T23:30:00.5Z")
+        );
+        assert_eq!(
+            normalize_temporal("2026-12-31T23:00:00-01:00").as_deref(),
+            Some("2027-01-01T00:00:00Z")
+        );
+        assert_eq!(
+            normalize_temporal("2026-10-18+00:00").as_deref(),
+            Some("2026-10-18Z")
+        );
+        assert_eq!(
+            normalize_temporal("10:00:00+01:00").as_deref(),
+            Some("09:00:00Z")
+        );
+        // Extra `:` segments are not a time, so the value is left as-is.
+        assert_eq!(normalize_temporal("10:00:00:99+01:00"), None);
+        assert_eq!(normalize_temporal("2026-10-18T10:00:00:99Z"), None);
+        // Multibyte text, where a zone would start mid-character.
+        let mut datetimes = Normalizers::default();
+        datetimes.enable("datetimes").unwrap();
+        assert_eq!(datetimes.value(None, "€abcde"), None);
+        assert_eq!(datetimes.value(None, "2026-10-18T10:00:00€0:00"), None);
+    }
+
+    #[test]
+    fn schema_types_steer_the_rules() {
+        let mut normalizers = Normalizers::default();
+        normalizers.enable("all").unwrap();
+        assert_eq!(normalizers.value(None, "1"), None);
+        assert_eq!(normalizers.value(None, "1.10"), None);
+        assert_eq!(normalizers.value(None, "+44"), None);
+        assert_eq!(normalizers.value(None, "-0.0").as_deref(), Some("0"));
+        assert_eq!(
+            normalizers.value(Some(Kind::Decimal), "1.10").as_deref(),
+            Some("1.1")
+        );
+        assert_eq!(normalizers.value(None, "TRUE").as_deref(), Some("true"));
+        assert_eq!(
+            normalizers.value(Some(Kind::Boolean), "1").as_deref(),
+            Some("true")
+        );
+        assert_eq!(normalizers.value(Some(Kind::Other), "1.50"), None);
+        assert_eq!(
+            normalizers.value(Some(Kind::Other), "  a \n b ").as_deref(),
+            Some("a b")
+        );
+
+        let mut roots = parse_xml(r#"<r n="+1.50"><when>2026-10-18T10:00:00+02:00</when></r>"#)
+            .unwrap()
+            .roots;
+        normalizers.normalize_element(&mut roots[0]);
+        assert_eq!(roots[0].attributes["n"], "1.5");
+        assert_eq!(roots[0].children[0].text_content, "2026-10-18T08:00:00Z");
+    }
+}
@@ -1,6 +1,7 @@
 //! Input handling: lenient file reads, format detection, and the XML/HTML
 //! parsers that build the `XmlElement` tree.
 
+#[cfg(feature = "cli")]
 use std::fs;
 use std::path::Path;
 
@@ -18,6 +19,7 @@ use crate::model::{NodeRef, XmlElement};
 /// `fs::read_to_string` rejects any non-UTF-8 byte, so we read raw bytes and
 /// fall back to a Latin-1 decode (every byte 0x00-0xFF maps directly to the
 /// matching Unicode code point, so this never fails).
+#[cfg(feature = "cli")]
 pub(crate) fn read_file_lenient(file_path: &str) -> Result<String> {
     let bytes = fs::read(file_path).with_context(|| format!("Failed to read file: {file_path}"))?;
     Ok(match String::from_utf8(bytes) {
@@ -26,14 +28,41 @@ pub(crate) fn read_file_lenient(file_path: &str) -> Result<String> {
     })
 }
 
+/// Read all of stdin as text, with the same Latin-1 fallback as
+/// `read_file_lenient`.
+#[cfg(feature = "cli")]
+pub(crate) fn read_stdin_lenient() -> Result<String> {
+    let mut bytes = Vec::new();
+    std::io::Read::read_to_end(&mut std::io::stdin(), &mut bytes)
+        .context("Failed to read from stdin")?;
+    Ok(match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => e.into_bytes().into_iter().map(|b| b as char).collect(),
+    })
+}
+
 #[derive(Debug, PartialEq)]
-pub(crate) enum InputFormat {
+pub enum InputFormat {
     Xml,
     Html,
     Json,
 }
 
 impl InputFormat {
+    /// Parse a `--format` value.
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "html" => Ok(Self::Html),
+            "xml" => Ok(Self::Xml),
+            "json" => Ok(Self::Json),
+            _ => Err(anyhow::anyhow!(
+                "Unsupported format: {}. Use 'xml', 'html', or 'json'",
+                name
+            )),
+        }
+    }
+
+    #[cfg(feature = "cli")]
     pub(crate) fn syntax_name(&self) -> &'static str {
         match self {
             Self::Xml => "XML",
@@ -43,7 +72,9 @@ impl InputFormat {
     }
 }
 
-pub(crate) fn detect_format(content: &str, file_path: &str) -> InputFormat {
+/// Pick the input format from the extension of `file_path`, else from the
+/// content; anything unrecognised is XML.
+pub fn detect_format(content: &str, file_path: &str) -> InputFormat {
     // Check file extension first
     if let Some(extension) = Path::new(file_path).extension() {
         let ext = extension.to_string_lossy().to_lowercase();
@@ -151,7 +182,10 @@ pub(crate) fn convert_element_to_xml(element: ElementRef, format: &InputFormat)
     xml_element
 }
 
-pub(crate) fn parse_html(content: &str, format: &InputFormat) -> Result<Vec<XmlElement>> {
+/// Parse an HTML document (leniently, like a browser) into its root element;
+/// `class` attributes become `.class` name suffixes.
+pub fn parse_html(content: &str) -> Result<Vec<XmlElement>> {
+    let format = &InputFormat::Html;
     let document = Html::parse_document(content);
     let mut root_elements = Vec::new();
 
@@ -196,12 +230,13 @@ pub(crate) fn parse_html(content: &str, format: &InputFormat) -> Result<Vec<XmlE
 /// comments that live outside them (in the prolog or epilog). A top-level
 /// comment is paired with the number of roots that preceded it, i.e. its
 /// insertion point in document order, so the renderer can place it back.
-pub(crate) struct ParsedXml {
-    pub(crate) roots: Vec<XmlElement>,
-    pub(crate) top_comments: Vec<(usize, String)>,
+pub struct ParsedXml {
+    pub roots: Vec<XmlElement>,
+    pub top_comments: Vec<(usize, String)>,
 }
 
-pub(crate) fn parse_xml(content: &str) -> Result<ParsedXml> {
+/// Parse an XML document, keeping comments and the source of mixed content.
+pub fn parse_xml(content: &str) -> Result<ParsedXml> {
     let mut reader = Reader::from_str(content);
     reader.config_mut().trim_text(true);
 
@@ -243,9 +278,13 @@ pub(crate) fn parse_xml(content: &str) -> Result<ParsedXml> {
                     element.attributes.insert(key, value);
                 }
 
+                // Inner content starts right after the start tag we just read;
+                // the tag itself at the last `<` before that (attribute values
+                // can't hold a raw `<`).
+                let inner_start = reader.buffer_position() as usize;
+                element.span = content[..inner_start].rfind('<').map(|start| start..start);
                 elements_stack.push(element);
-                // Inner content starts right after the start tag we just read.
-                inner_start_stack.push(reader.buffer_position() as usize);
+                inner_start_stack.push(inner_start);
             }
             Ok(Event::End(_)) => {
                 if let Some(mut completed_element) = elements_stack.pop() {
@@ -255,6 +294,9 @@ pub(crate) fn parse_xml(content: &str) -> Result<ParsedXml> {
                         completed_element.inner_source =
                             content.get(inner_start..pos_before).map(str::to_string);
                     }
+                    if let Some(span) = &mut completed_element.span {
+                        span.end = reader.buffer_position() as usize;
+                    }
                     if let Some(parent) = elements_stack.last_mut() {
                         parent.nodes.push(NodeRef::Child(parent.children.len()));
                         parent.children.push(completed_element);
@@ -298,13 +340,15 @@ pub(crate) fn parse_xml(content: &str) -> Result<ParsedXml> {
                     element.attributes.insert(key, value);
                 }
 
+                let end = reader.buffer_position() as usize;
+                element.span = content[..end].rfind('<').map(|start| start..end);
                 if let Some(parent) = elements_stack.last_mut() {
                     parent.nodes.push(NodeRef::Child(parent.children.len()));
                     parent.children.push(element);
                 } else {
                     root_elements.push(element);
                 }
-                last_sibling_end = reader.buffer_position() as usize;
+                last_sibling_end = end;
             }
             Ok(Event::Comment(ref e)) => {
                 // Comments are content: keep them in document order so they
@@ -325,7 +369,7 @@ pub(crate) fn parse_xml(content: &str) -> Result<ParsedXml> {
                     // because `trim_text` drops the intervening whitespace, so
                     // `pos_before` alone would not locate the `<!--`.
                     let comment_start =
-                        (reader.buffer_position() as usize).saturating_sub(e.as_ref().len() + 7);
+                        (reader.buffer_position() as usize).saturating_sub(e.len() + 7);
                     let inline = last_sibling_end > 0
                         && content
                             .get(last_sibling_end..comment_start)
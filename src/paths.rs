@@ -30,7 +30,7 @@
 use std::cell::RefCell;
 use std::collections::{BTreeMap, BTreeSet};
 
-use crate::canonical::well_known_prefix;
+use crate::canonical::KnownPrefixes;
 use crate::model::XmlElement;
 
 /// What's seen on elements at one path: ordinary attribute names (values vary,
@@ -208,12 +208,14 @@ fn render_node(name: &str, node: &TreeNode, depth: usize, ctx: &RenderCtx, out:
 /// nesting levels emitted, root being level 1; `no_attrs` drops ordinary
 /// attribute names from each node, keeping only namespaces. With `fold`,
 /// repeated subtree shapes are hoisted into a `// shapes` legend and each
-/// occurrence is replaced by an `@Shape` reference.
-pub(crate) fn dump_paths(
+/// occurrence is replaced by an `@Shape` reference. `known` decides which
+/// bindings the legend can omit.
+pub fn dump_paths(
     roots: &[&XmlElement],
     max_depth: usize,
     no_attrs: bool,
     fold: bool,
+    known: &KnownPrefixes,
 ) -> String {
     let mut acc = BTreeMap::new();
     let mut legend = BTreeSet::new();
@@ -227,7 +229,7 @@ pub(crate) fn dump_paths(
     // their conventional prefix (`xsl`, `cac`, …): self-explanatory, so listing
     // them is noise. A non-standard prefix on a well-known URI still gets a line.
     for (prefix, uri) in &legend {
-        if well_known_prefix(uri) == Some(prefix.as_str()) {
+        if known.prefix(uri) == Some(prefix.as_str()) {
             continue;
         }
         out.push_str(&format!("// {prefix} = {uri}\n"));
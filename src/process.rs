@@ -2,18 +2,19 @@
 //! render, and emit (optionally through `bat`).
 
 use std::collections::HashSet;
-use std::io::{self, Read};
 
 use anyhow::{Context, Result};
 
-use crate::canonical::canonicalize;
+use crate::canonical::{CanonicalOptions, KnownPrefixes, canonicalize};
 use crate::document::{
-    HIDE_NS_ALL, hide_namespaces, is_cii_document, is_msbuild_document, is_ubl_document,
-    select_subtrees, sniff_hidden_prefixes,
+    HIDE_NS_ALL, detect_mode_from_ext, hide_namespaces, is_cii_document, is_ubl_document,
+    select_subtrees, sniff_hidden_prefixes, sniff_mode,
 };
 use crate::json::render_json;
 use crate::model::{Collapse, FormatOpts, XmlElement};
-use crate::parse::{InputFormat, detect_format, parse_html, parse_xml, read_file_lenient};
+use crate::parse::{InputFormat, detect_format, parse_html, parse_xml};
+#[cfg(feature = "cli")]
+use crate::parse::{read_file_lenient, read_stdin_lenient};
 use crate::paths::dump_paths;
 use crate::render::render_comment;
 use crate::xslt::TemplateRegistry;
@@ -21,21 +22,31 @@ use crate::xslt::TemplateRegistry;
 /// The cross-cutting, CLI-derived options shared by every input. Built once and
 /// passed by reference, so the process functions stay narrow even as flags grow.
 /// The per-file processing *mode* (`FormatOpts`) is passed separately because it
-/// can vary per file under `--auto`.
-pub(crate) struct ProcessOptions<'a> {
-    pub(crate) format_override: Option<&'a str>,
-    pub(crate) hide_ns: &'a HashSet<String>,
-    pub(crate) sniff: bool,
-    pub(crate) select: Option<&'a str>,
-    pub(crate) canonical: bool,
-    pub(crate) paths: bool,
-    pub(crate) depth: usize,
-    pub(crate) no_attrs: bool,
-    pub(crate) fold: bool,
-    pub(crate) expand: bool,
+/// can vary per file under `--auto`. Outside the crate, get one from
+/// `Renderer::options`; fields may be added.
+#[non_exhaustive]
+pub struct ProcessOptions<'a> {
+    pub format_override: Option<&'a str>,
+    pub hide_ns: &'a HashSet<String>,
+    pub sniff: bool,
+    pub select: Option<&'a str>,
+    pub canonical: bool,
+    /// `--key` and the other refinements applied under `--canonical`.
+    pub canonical_options: &'a CanonicalOptions,
+    /// Conventional prefixes, built-in and `--ns-prefix`, for sniffing and
+    /// the `--paths` legend.
+    pub known_prefixes: &'a KnownPrefixes,
+    pub paths: bool,
+    pub depth: usize,
+    pub no_attrs: bool,
+    pub fold: bool,
+    pub expand: bool,
 }
 
-pub(crate) fn process_content(
+/// Render one document to unxml text. The input format comes from
+/// `cfg.format_override`, else from `file_path`'s extension and the content
+/// itself; `registry` carries XSLT templates for `--expand`.
+pub fn process_content(
     content: &str,
     file_path: &str,
     opts: &FormatOpts,
@@ -44,17 +55,7 @@ pub(crate) fn process_content(
 ) -> Result<String> {
     // Determine input format
     let format = if let Some(format_str) = cfg.format_override {
-        match format_str.to_lowercase().as_str() {
-            "html" => InputFormat::Html,
-            "xml" => InputFormat::Xml,
-            "json" => InputFormat::Json,
-            _ => {
-                return Err(anyhow::anyhow!(
-                    "Unsupported format: {}. Use 'xml', 'html', or 'json'",
-                    format_str
-                ));
-            }
-        }
+        InputFormat::from_name(format_str)?
     } else {
         detect_format(content, file_path)
     };
@@ -65,7 +66,11 @@ pub(crate) fn process_content(
                 "--select and --paths are not yet supported for JSON"
             ));
         }
-        return render_json(content, cfg.canonical, cfg.sniff);
+        return render_json(
+            content,
+            cfg.canonical.then_some(cfg.canonical_options),
+            cfg.sniff,
+        );
     }
 
     // Parse the content based on detected/specified format. `top_comments` are
@@ -73,7 +78,7 @@ pub(crate) fn process_content(
     // no such concept here.
     let (mut elements, top_comments) = match format {
         InputFormat::Html => (
-            parse_html(content, &format).context("Failed to parse HTML")?,
+            parse_html(content).context("Failed to parse HTML")?,
             Vec::new(),
         ),
         InputFormat::Xml => {
@@ -83,12 +88,23 @@ pub(crate) fn process_content(
         InputFormat::Json => unreachable!("JSON returns before XML/HTML parsing"),
     };
 
+    // Under --auto/--bat, a dialect document (`xsl:stylesheet`, `xs:schema`,
+    // `wsdl:definitions`, `sch:schema`, an MSBuild `<Project>`) gets its mode
+    // even when its extension didn't already select it (e.g. stdin, or a
+    // `.xml` file) — unless the user already forced an explicit mode. Sniffed
+    // before --hide-ns can strip the root's namespace declaration.
+    let sniffed_mode = if cfg.sniff && !opts.has_mode() {
+        elements.iter().find_map(sniff_mode)
+    } else {
+        None
+    };
+
     // Build the effective set of prefixes to hide: those requested explicitly,
     // plus any inferred by sniffing the document type (only under --auto/--bat).
     // The `ALL` sentinel hides every prefix regardless of the rest of the set.
     let mut hidden = cfg.hide_ns.clone();
     if cfg.sniff {
-        hidden.extend(sniff_hidden_prefixes(&elements));
+        hidden.extend(sniff_hidden_prefixes(&elements, cfg.known_prefixes));
     }
     let hide_all = hidden.contains(HIDE_NS_ALL);
     if hide_all || !hidden.is_empty() {
@@ -99,15 +115,13 @@ pub(crate) fn process_content(
 
     // Build the effective mode/collapse opts before canonicalising, so a
     // content-sniffed mode also governs the sibling-sort decision below.
-    let mut effective = opts.clone();
-
-    // Under --auto/--bat, an MSBuild project/import file (`<Project>` root)
-    // gets --msbuild even when its extension didn't already select it (e.g.
-    // stdin, or an unrecognised extension) — unless the user already forced
-    // an explicit mode.
-    if cfg.sniff && !opts.has_mode() && elements.iter().any(is_msbuild_document) {
-        effective.msbuild = true;
-    }
+    let mut effective = match sniffed_mode {
+        Some(mode) => FormatOpts {
+            collapse: opts.collapse.clone(),
+            ..mode
+        },
+        None => opts.clone(),
+    };
 
     // Under --auto/--bat, a genuine UBL or CII instance folds its single-child
     // wrapper chains automatically (the same documents whose prefixes we hide),
@@ -130,7 +144,7 @@ pub(crate) fn process_content(
     // names, but only sort siblings in plain XML mode — in a dialect/`--special`
     // mode element order is significant, so sorting would misrepresent it.
     if cfg.canonical {
-        canonicalize(&mut elements, !opts.has_mode());
+        canonicalize(&mut elements, !opts.has_mode(), cfg.canonical_options);
     }
 
     // Determine the roots to emit: the whole document, or just the subtrees
@@ -147,7 +161,13 @@ pub(crate) fn process_content(
     // --select, render each matched subtree as a fragment separated by a blank
     // line; the whole-document case emits roots back-to-back.
     let output = if cfg.paths {
-        dump_paths(&roots, cfg.depth, cfg.no_attrs, cfg.fold)
+        dump_paths(
+            &roots,
+            cfg.depth,
+            cfg.no_attrs,
+            cfg.fold,
+            cfg.known_prefixes,
+        )
     } else if cfg.select.is_some() {
         // --select renders matched subtrees as fragments; the document prolog
         // (top-level comments) is not part of any selected subtree, so omit it.
@@ -184,11 +204,9 @@ pub(crate) fn process_content(
     Ok(output)
 }
 
-pub(crate) fn process_file(
-    file_path: &str,
-    opts: &FormatOpts,
-    cfg: &ProcessOptions,
-) -> Result<String> {
+/// Read and render one file, collecting XSLT templates first under `expand`.
+#[cfg(feature = "cli")]
+pub fn process_file(file_path: &str, opts: &FormatOpts, cfg: &ProcessOptions) -> Result<String> {
     // Build template registry if expand mode is enabled
     let registry = if cfg.expand && opts.xslt {
         Some(TemplateRegistry::build_from_file(file_path)?)
@@ -202,24 +220,167 @@ pub(crate) fn process_file(
     process_content(&content, file_path, opts, registry.as_ref(), cfg)
 }
 
+#[cfg(feature = "cli")]
 pub(crate) fn process_stdin(opts: &FormatOpts, cfg: &ProcessOptions) -> Result<String> {
     // Read from stdin, tolerating non-UTF-8 input (see read_file_lenient).
-    let mut bytes = Vec::new();
-    io::stdin()
-        .read_to_end(&mut bytes)
-        .context("Failed to read from stdin")?;
-    let content = match String::from_utf8(bytes) {
-        Ok(text) => text,
-        Err(e) => e.into_bytes().into_iter().map(|b| b as char).collect(),
-    };
+    let content = read_stdin_lenient()?;
 
     // Note: expand mode not supported for stdin since we need file paths for imports
     process_content(&content, "stdin", opts, None, cfg)
 }
 
+/// Owned, chainable settings for rendering from library code: the per-file
+/// mode (`FormatOpts`) and the `ProcessOptions` together, without the
+/// borrows the CLI threads through.
+///
+/// ```
+/// let out = unxml::Renderer::new()
+///     .canonical(true)
+///     .render("<a><b y='2' x='1'/></a>", "a.xml")
+///     .unwrap();
+/// assert_eq!(out, "a\n  b(x=\"1\", y=\"2\")\n");
+/// ```
+#[derive(Debug, Default)]
+pub struct Renderer {
+    mode: FormatOpts,
+    format: Option<String>,
+    hide_ns: HashSet<String>,
+    auto: bool,
+    select: Option<String>,
+    canonical: bool,
+    canonical_options: CanonicalOptions,
+    known_prefixes: KnownPrefixes,
+    paths: Option<usize>,
+    no_attrs: bool,
+    fold: bool,
+    expand: bool,
+}
+
+impl Renderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The processing mode (`--xslt`, `--xsd`, …, `--collapse`).
+    pub fn mode(mut self, mode: FormatOpts) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Force the input format (`xml`, `html` or `json`) instead of detecting it.
+    pub fn format(mut self, name: &str) -> Self {
+        self.format = Some(name.to_string());
+        self
+    }
+
+    /// Prefixes to strip from element names; `ALL` strips every prefix.
+    pub fn hide_ns<I, S>(mut self, prefixes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.hide_ns.extend(prefixes.into_iter().map(Into::into));
+        self
+    }
+
+    /// `--auto`: sniff the document type, and in `render_file` pick the mode
+    /// from the extension when none was set.
+    pub fn auto(mut self, auto: bool) -> Self {
+        self.auto = auto;
+        self
+    }
+
+    /// Render only the subtrees rooted at elements with this name.
+    pub fn select(mut self, name: &str) -> Self {
+        self.select = Some(name.to_string());
+        self
+    }
+
+    pub fn canonical(mut self, canonical: bool) -> Self {
+        self.canonical = canonical;
+        self
+    }
+
+    /// The `--key`/`--mask`/`--normalize` refinements used under `canonical`.
+    pub fn canonical_options(mut self, options: CanonicalOptions) -> Self {
+        self.canonical_options = options;
+        self
+    }
+
+    /// Conventional prefixes for sniffing and the `paths` legend.
+    pub fn known_prefixes(mut self, known: KnownPrefixes) -> Self {
+        self.known_prefixes = known;
+        self
+    }
+
+    /// List document paths (`--paths`) down to `depth` levels; 0 is unlimited.
+    pub fn paths(mut self, depth: usize) -> Self {
+        self.paths = Some(depth);
+        self
+    }
+
+    pub fn no_attrs(mut self, no_attrs: bool) -> Self {
+        self.no_attrs = no_attrs;
+        self
+    }
+
+    pub fn fold(mut self, fold: bool) -> Self {
+        self.fold = fold;
+        self
+    }
+
+    /// Expand `xsl:call-template`/`apply-templates` (XSLT mode, files only).
+    pub fn expand(mut self, expand: bool) -> Self {
+        self.expand = expand;
+        self
+    }
+
+    /// The borrowed `ProcessOptions` these settings stand for.
+    pub fn options(&self) -> ProcessOptions<'_> {
+        ProcessOptions {
+            format_override: self.format.as_deref(),
+            hide_ns: &self.hide_ns,
+            sniff: self.auto,
+            select: self.select.as_deref(),
+            canonical: self.canonical,
+            canonical_options: &self.canonical_options,
+            known_prefixes: &self.known_prefixes,
+            paths: self.paths.is_some(),
+            depth: self.paths.unwrap_or(0),
+            no_attrs: self.no_attrs,
+            fold: self.fold,
+            expand: self.expand,
+        }
+    }
+
+    /// Render `content`. `file_path` (which may be empty) is only a name: its
+    /// extension informs format detection and, under `auto`, the mode.
+    pub fn render(&self, content: &str, file_path: &str) -> Result<String> {
+        let mode = self.mode_for(file_path);
+        process_content(content, file_path, &mode, None, &self.options())
+    }
+
+    /// Read and render a file.
+    #[cfg(feature = "cli")]
+    pub fn render_file(&self, file_path: &str) -> Result<String> {
+        process_file(file_path, &self.mode_for(file_path), &self.options())
+    }
+
+    fn mode_for(&self, file_path: &str) -> FormatOpts {
+        if self.auto && !self.mode.has_mode() {
+            let mut detected = detect_mode_from_ext(file_path);
+            detected.collapse = self.mode.collapse.clone();
+            detected
+        } else {
+            self.mode.clone()
+        }
+    }
+}
+
 /// Emit rendered output, optionally through `bat` for syntax highlighting.
 /// When `use_bat` is set we pipe to `bat -l unxml`; if no `bat` binary is
 /// found we fall back to plain stdout so `--bat` degrades gracefully.
+#[cfg(feature = "cli")]
 pub(crate) fn emit(output: &str, use_bat: bool) {
     if use_bat && pipe_to_bat(output) {
         return;
@@ -230,6 +391,7 @@ pub(crate) fn emit(output: &str, use_bat: bool) {
 /// Try to display `output` via `bat -l unxml`. Returns true if a `bat` (or
 /// `batcat`, the Debian/Ubuntu name) process was launched and handed the
 /// output, false if no such binary exists.
+#[cfg(feature = "cli")]
 pub(crate) fn pipe_to_bat(output: &str) -> bool {
     use std::io::Write;
     use std::process::{Command, Stdio};
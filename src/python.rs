@@ -0,0 +1,106 @@
+//! The `import unxml` extension module (feature `python`), built by maturin
+//! into the `unxml-rs` wheel. It renders in-process; the wheel's `unxml`
+//! command is the native binary, shipped beside it.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::canonical::CanonicalOptions;
+use crate::model::FormatOpts;
+use crate::process::Renderer;
+
+fn value_error(e: anyhow::Error) -> PyErr {
+    PyValueError::new_err(format!("{e:#}"))
+}
+
+/// The options every function shares.
+fn renderer(
+    mode: Option<&str>,
+    format: Option<&str>,
+    hide_ns: Option<Vec<String>>,
+    select: Option<&str>,
+) -> PyResult<Renderer> {
+    let mut renderer = Renderer::new().hide_ns(hide_ns.unwrap_or_default());
+    if let Some(mode) = mode {
+        renderer = renderer.mode(FormatOpts::from_mode(mode).map_err(value_error)?);
+    }
+    if let Some(format) = format {
+        renderer = renderer.format(format);
+    }
+    if let Some(select) = select {
+        renderer = renderer.select(select);
+    }
+    Ok(renderer)
+}
+
+/// Render an XML, HTML or JSON document as unxml text.
+#[pyfunction]
+#[pyo3(signature = (text, mode=None, format=None, hide_ns=None, select=None, auto=false))]
+fn render(
+    text: &str,
+    mode: Option<&str>,
+    format: Option<&str>,
+    hide_ns: Option<Vec<String>>,
+    select: Option<&str>,
+    auto: bool,
+) -> PyResult<String> {
+    renderer(mode, format, hide_ns, select)?
+        .auto(auto)
+        .render(text, "")
+        .map_err(value_error)
+}
+
+/// The distinct element paths of a document (`--paths`).
+#[pyfunction]
+#[pyo3(signature = (text, depth=0, no_attrs=false, fold=false, format=None, hide_ns=None, select=None))]
+fn paths(
+    text: &str,
+    depth: usize,
+    no_attrs: bool,
+    fold: bool,
+    format: Option<&str>,
+    hide_ns: Option<Vec<String>>,
+    select: Option<&str>,
+) -> PyResult<String> {
+    renderer(None, format, hide_ns, select)?
+        .paths(depth)
+        .no_attrs(no_attrs)
+        .fold(fold)
+        .render(text, "")
+        .map_err(value_error)
+}
+
+/// The `--canonical` rendering, with the `--key`, `--mask` and `--normalize`
+/// specs as lists.
+#[pyfunction]
+#[pyo3(signature = (text, mode=None, format=None, hide_ns=None, key=None, mask=None, normalize=None))]
+fn canonical(
+    text: &str,
+    mode: Option<&str>,
+    format: Option<&str>,
+    hide_ns: Option<Vec<String>>,
+    key: Option<Vec<String>>,
+    mask: Option<Vec<String>>,
+    normalize: Option<Vec<String>>,
+) -> PyResult<String> {
+    let options = CanonicalOptions::parse(
+        &key.unwrap_or_default(),
+        &mask.unwrap_or_default(),
+        &normalize.unwrap_or_default(),
+        None,
+    )
+    .map_err(value_error)?;
+    renderer(mode, format, hide_ns, None)?
+        .canonical(true)
+        .canonical_options(options)
+        .render(text, "")
+        .map_err(value_error)
+}
+
+#[pymodule]
+fn unxml(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(render, m)?)?;
+    m.add_function(wrap_pyfunction!(paths, m)?)?;
+    m.add_function(wrap_pyfunction!(canonical, m)?)?;
+    Ok(())
+}
@@ -1,10 +1,80 @@
 //! Generic Pug-like rendering: width-aware attributes, text blocks, and the
 //! central `format_yaml_like` dispatcher that routes to each dialect.
 
+use std::cell::RefCell;
+
 use crate::document::name_matches_select;
 use crate::model::{Collapse, FormatOpts, NodeRef, XmlElement};
 use crate::xslt::TemplateRegistry;
 
+/// A rendered element's text with the source offsets of the elements that
+/// start in it, as `(line within text, offset)`, outermost first.
+struct Mapped {
+    text: String,
+    lines: Vec<(usize, usize)>,
+}
+
+impl Mapped {
+    /// Lay out the source maps of `pieces`, rendered while `text` was built, by
+    /// finding each piece's text in `text` in order. A piece its parent didn't
+    /// copy verbatim (rendered only to measure, or rewritten) isn't found and
+    /// contributes nothing.
+    fn place(text: &str, pieces: Vec<Mapped>, lines: &mut Vec<(usize, usize)>) {
+        let mut cursor = 0;
+        for piece in pieces {
+            if piece.text.is_empty() {
+                continue;
+            }
+            let Some(found) = text[cursor..].find(&piece.text) else {
+                continue;
+            };
+            let at = cursor + found;
+            let line = text[..at].matches('\n').count();
+            lines.extend(piece.lines.iter().map(|&(l, offset)| (line + l, offset)));
+            cursor = at + piece.text.len();
+        }
+    }
+}
+
+thread_local! {
+    /// Set while `render_with_source_map` runs: one frame per element being
+    /// rendered (innermost last) collecting the maps of what rendered inside
+    /// it, below a bottom frame for the top-level elements.
+    static SOURCE_MAP: RefCell<Option<Vec<Vec<Mapped>>>> = const { RefCell::new(None) };
+}
+
+/// Run `render` and return its text unchanged plus, for each output line, the
+/// source offset of the element the line belongs to: the outermost element
+/// starting on that line, else the one of the line above.
+#[cfg(feature = "cli")]
+pub(crate) fn render_with_source_map<E>(
+    render: impl FnOnce() -> Result<String, E>,
+) -> Result<(String, Vec<Option<usize>>), E> {
+    SOURCE_MAP.set(Some(vec![Vec::new()]));
+    let rendered = render();
+    let pieces = SOURCE_MAP
+        .take()
+        .and_then(|mut stack| stack.pop())
+        .unwrap_or_default();
+    let text = rendered?;
+
+    let mut lines = Vec::new();
+    Mapped::place(&text, pieces, &mut lines);
+    lines.sort_by_key(|&(line, _)| line);
+    let mut offsets = Vec::new();
+    let mut current = None;
+    let mut starts = lines.into_iter().peekable();
+    for index in 0..text.lines().count() {
+        let mut first = None;
+        while let Some((_, offset)) = starts.next_if(|&(line, _)| line == index) {
+            first = first.or(Some(offset));
+        }
+        current = first.or(current);
+        offsets.push(current);
+    }
+    Ok((text, offsets))
+}
+
 /// Maximum line width before a parenthesised list (attributes, or a folded
 /// `function`/`template` param signature) wraps to one item per line.
 pub(crate) const WRAP_WIDTH: usize = 100;
@@ -193,7 +263,41 @@ impl XmlElement {
         out
     }
 
-    pub(crate) fn format_yaml_like(
+    /// Render this element and its subtree as unxml text at `indent` levels.
+    pub fn format_yaml_like(
+        &self,
+        indent: usize,
+        opts: &FormatOpts,
+        registry: Option<&TemplateRegistry>,
+    ) -> String {
+        let recording = SOURCE_MAP.with_borrow_mut(|stack| match stack {
+            Some(stack) => {
+                stack.push(Vec::new());
+                true
+            }
+            None => false,
+        });
+        let out = self.format_element(indent, opts, registry);
+        if recording {
+            SOURCE_MAP.with_borrow_mut(|stack| {
+                let Some(stack) = stack else { return };
+                let pieces = stack.pop().unwrap_or_default();
+                let mut lines: Vec<_> = self.span.iter().map(|span| (0, span.start)).collect();
+                Mapped::place(&out, pieces, &mut lines);
+                if let Some(parent) = stack.last_mut() {
+                    parent.push(Mapped {
+                        text: out.clone(),
+                        lines,
+                    });
+                }
+            });
+        }
+        out
+    }
+
+    /// `format_yaml_like` without the source map bookkeeping: the dialect
+    /// dispatch.
+    fn format_element(
         &self,
         indent: usize,
         opts: &FormatOpts,
@@ -272,6 +376,7 @@ impl XmlElement {
                 children: self.children.clone(),
                 nodes: self.nodes.clone(),
                 inner_source: self.inner_source.clone(),
+                span: self.span.clone(),
             };
 
             // Always process the modified element normally (section should still appear)
@@ -296,6 +401,7 @@ impl XmlElement {
                 children: self.children.clone(),
                 nodes: self.nodes.clone(),
                 inner_source: self.inner_source.clone(),
+                span: self.span.clone(),
             };
 
             // Special handling for section elements after include processing
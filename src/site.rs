@@ -0,0 +1,320 @@
+//! `unxml site SRC_DIR OUT_DIR`: render every document under SRC_DIR, as
+//! `--auto --html` would, into a static gallery. Each file gets a viewer page
+//! showing the rendering and the original source in tabs or side by side,
+//! linked to the stylesheets and schemas it imports or includes (and those
+//! that import it). An index lists them all with how many lines and bytes
+//! the rendering saves.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::Renderer;
+use crate::batch::{self, Layout, Size, Written, absolute, relative_href, write_file};
+use crate::cli::SiteCli;
+use crate::highlight::{self, Css, html_css};
+use crate::model::XmlElement;
+use crate::parse::{InputFormat, detect_format, parse_xml, read_file_lenient};
+use crate::write::escape_attr;
+
+/// The extensions rendered: those `--init-git` binds, plus MSBuild's.
+const EXTENSIONS: &[&str] = &[
+    "xml", "xsl", "xslt", "xsd", "wsdl", "sch", "html", "htm", "json", "targets", "props",
+    "csproj", "vbproj", "fsproj", "sqlproj",
+];
+
+/// Suffixes of a file's three pages: the viewer the index links to, and the
+/// rendered and raw pages it frames.
+const VIEWER: &str = ".html";
+const RENDERED: &str = ".unxml.html";
+const RAW: &str = ".raw.html";
+
+/// Chrome for the viewer pages, matching the `--html` pages' dark theme.
+/// The tabs are radio buttons, so the site needs no script.
+const VIEWER_CSS: &str = "
+html, body { height: 100%; margin: 0; }
+body { display: flex; flex-direction: column; background: #0d1117; color: #c9d1d9;
+  font: 14px/1.5 ui-sans-serif, system-ui, sans-serif; }
+header { padding: 0.75rem 1.5rem; border-bottom: 1px solid #30363d; }
+h1 { display: inline; font-size: 1.1rem; margin: 0 1rem 0 0; }
+a { color: #58a6ff; text-decoration: none; }
+a:hover { text-decoration: underline; }
+.stats, .refs { color: #8b949e; margin: 0.25rem 0 0; }
+.tabs { padding: 0.5rem 1.5rem 0; border-bottom: 1px solid #30363d; }
+.tabs input { display: none; }
+.tabs label { display: inline-block; padding: 0.25rem 0.75rem; cursor: pointer;
+  border: 1px solid transparent; border-bottom: 0; border-radius: 6px 6px 0 0; }
+#rendered:checked ~ label[for=rendered], #raw:checked ~ label[for=raw],
+#both:checked ~ label[for=both] { border-color: #30363d; background: #161b22; }
+.panes { display: flex; flex: 1; min-height: 0; }
+.panes iframe { flex: 1; border: 0; min-width: 0; }
+body:has(#rendered:checked) .raw, body:has(#raw:checked) .rendered { display: none; }
+body:has(#both:checked) .raw { border-left: 1px solid #30363d; }
+";
+
+/// One document in the site.
+struct Page {
+    /// Its path under SRC_DIR, `/`-separated: the name pages show.
+    name: String,
+    /// Its path as read.
+    path: String,
+    source: String,
+    rendered: String,
+    /// Indices of the pages it imports or includes, and the other
+    /// references, which lead outside the site.
+    imports: Vec<usize>,
+    external: Vec<String>,
+}
+
+pub(crate) fn run_site(args: &SiteCli) -> Result<()> {
+    let src = Path::new(&args.src_dir);
+    let out = Path::new(&args.out_dir);
+    anyhow::ensure!(src.is_dir(), "Not a directory: {}", src.display());
+
+    let mut files = Vec::new();
+    collect_files(src, &absolute(out), &mut files)
+        .with_context(|| format!("Failed to list {}", src.display()))?;
+
+    // Render everything first: a page links the pages its references lead to.
+    let renderer = Renderer::new().auto(true);
+    let mut pages = Vec::new();
+    for file in &files {
+        let path = file.to_string_lossy().to_string();
+        let page = read_file_lenient(&path).and_then(|source| {
+            let rendered = renderer.render(&source, &path)?;
+            let name = file.strip_prefix(src).unwrap_or(file);
+            Ok(Page {
+                name: name.to_string_lossy().replace('\\', "/"),
+                path,
+                source,
+                rendered,
+                imports: Vec::new(),
+                external: Vec::new(),
+            })
+        });
+        match page {
+            Ok(page) => pages.push(page),
+            Err(e) => eprintln!("Error processing file '{}': {e}", file.display()),
+        }
+    }
+    link_references(&mut pages);
+
+    let layout = Layout::mirror(src, out, VIEWER);
+    batch::refuse_overwriting_inputs(
+        &files
+            .iter()
+            .map(|file| file.to_string_lossy().to_string())
+            .collect::<Vec<_>>(),
+        pages.iter().flat_map(|page| {
+            [VIEWER, RENDERED, RAW]
+                .map(|suffix| (page.path.as_str(), layout.target_with(&page.path, suffix)))
+        }),
+    )?;
+    let css_path = out.join("unxml.css");
+    let mut written = Vec::new();
+    for (i, page) in pages.iter().enumerate() {
+        let viewer = layout.target_with(&page.path, VIEWER);
+        let rendered = layout.target_with(&page.path, RENDERED);
+        let raw = layout.target_with(&page.path, RAW);
+        let css = relative_href(&viewer, &css_path, out);
+        let css = Css::Link(&css);
+
+        let syntax = detect_format(&page.source, &page.path).syntax_name();
+        write_file(&rendered, &highlight::html_page(&page.rendered, &css)?)?;
+        write_file(&raw, &highlight::html_page_raw(&page.source, syntax, &css)?)?;
+        let entry = Written {
+            input: page.name.clone(),
+            output: viewer,
+            original: Size::of(&page.source),
+            rendered: Size::of(&page.rendered),
+        };
+        write_file(&entry.output, &viewer_page(&pages, i, &entry, &layout))?;
+        written.push(entry);
+    }
+    write_file(&css_path, &html_css()?)?;
+    write_file(&out.join("index.html"), &batch::index_page(&written, out))?;
+    eprintln!("Wrote {} page(s) to {}", written.len(), out.display());
+    Ok(())
+}
+
+/// The renderable files under `dir`, sorted, skipping hidden entries and
+/// `out` (when the site is written inside its source).
+fn collect_files(dir: &Path, out: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+    for path in entries {
+        let hidden = path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if hidden || absolute(&path) == out {
+            continue;
+        }
+        if path.is_dir() {
+            collect_files(&path, out, files)?;
+        } else if path
+            .extension()
+            .is_some_and(|ext| EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str()))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// The documents an element tree pulls in: `href` of `xsl:import` and
+/// `xsl:include`, `schemaLocation` of `xs:import`, `xs:include`,
+/// `xs:redefine` and `xs:override`. Matched by local name, whatever the
+/// prefix.
+fn references(element: &XmlElement, found: &mut Vec<String>) {
+    let local = element.name.rsplit(':').next().unwrap_or_default();
+    if matches!(local, "import" | "include" | "redefine" | "override")
+        && let Some(location) = element
+            .attributes
+            .get("href")
+            .or_else(|| element.attributes.get("schemaLocation"))
+        && !found.contains(location)
+    {
+        found.push(location.clone());
+    }
+    for child in &element.children {
+        references(child, found);
+    }
+}
+
+/// Resolve each XML page's references against its directory: to the page
+/// for that file when the site has one, else kept as text.
+fn link_references(pages: &mut [Page]) {
+    let key = |path: &Path| std::fs::canonicalize(path).unwrap_or_else(|_| absolute(path));
+    let by_path: HashMap<PathBuf, usize> = pages
+        .iter()
+        .enumerate()
+        .map(|(i, page)| (key(Path::new(&page.path)), i))
+        .collect();
+    for page in pages.iter_mut() {
+        if detect_format(&page.source, &page.path) != InputFormat::Xml {
+            continue;
+        }
+        let mut found = Vec::new();
+        if let Ok(parsed) = parse_xml(&page.source) {
+            for root in &parsed.roots {
+                references(root, &mut found);
+            }
+        }
+        let dir = Path::new(&page.path).parent().unwrap_or(Path::new("."));
+        for location in found {
+            match by_path.get(&key(&dir.join(&location))) {
+                Some(&i) => page.imports.push(i),
+                None => page.external.push(location),
+            }
+        }
+    }
+}
+
+/// Links to `targets`' viewer pages from the viewer page at `from`.
+fn page_links(pages: &[Page], targets: &[usize], from: &Path, layout: &Layout) -> Vec<String> {
+    targets
+        .iter()
+        .map(|&i| {
+            let href = relative_href(from, &layout.target(&pages[i].path), layout.root());
+            format!(
+                "<a href=\"{}\">{}</a>",
+                escape_attr(&href),
+                escape_attr(&pages[i].name)
+            )
+        })
+        .collect()
+}
+
+/// The viewer for `pages[index]`: stats, references, and the rendered and
+/// raw pages in tabs.
+fn viewer_page(pages: &[Page], index: usize, entry: &Written, layout: &Layout) -> String {
+    let page = &pages[index];
+    let from = &entry.output;
+    let href = |path: &Path| escape_attr(&relative_href(from, path, layout.root()));
+
+    let mut imports = page_links(pages, &page.imports, from, layout);
+    imports.extend(page.external.iter().map(|location| escape_attr(location)));
+    let imported_by: Vec<usize> = (0..pages.len())
+        .filter(|&i| pages[i].imports.contains(&index))
+        .collect();
+    let imported_by = page_links(pages, &imported_by, from, layout);
+    let mut refs = String::new();
+    if !imports.is_empty() {
+        refs.push_str(&format!(
+            "<p class=\"refs\">Imports: {}</p>\n",
+            imports.join(", ")
+        ));
+    }
+    if !imported_by.is_empty() {
+        refs.push_str(&format!(
+            "<p class=\"refs\">Imported by: {}</p>\n",
+            imported_by.join(", ")
+        ));
+    }
+
+    let (original, rendered) = (entry.original, entry.rendered);
+    format!(
+        "<!doctype html>\n\
+         <html lang=\"en\">\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{name} — unxml</title>\n\
+         <style>{VIEWER_CSS}</style>\n\
+         </head>\n\
+         <body>\n\
+         <header>\n\
+         <a href=\"{index}\">Index</a> / <h1>{name}</h1>\n\
+         <p class=\"stats\">{} → {} lines ({}), {} → {} bytes ({})</p>\n\
+         {refs}\
+         </header>\n\
+         <div class=\"tabs\">\n\
+         <input type=\"radio\" name=\"view\" id=\"rendered\" checked>\n\
+         <input type=\"radio\" name=\"view\" id=\"raw\">\n\
+         <input type=\"radio\" name=\"view\" id=\"both\">\n\
+         <label for=\"rendered\">Rendered</label><label for=\"raw\">Raw</label><label for=\"both\">Side by side</label>\n\
+         </div>\n\
+         <div class=\"panes\">\n\
+         <iframe class=\"rendered\" src=\"{}\" title=\"Rendered\"></iframe>\n\
+         <iframe class=\"raw\" src=\"{}\" title=\"Raw\"></iframe>\n\
+         </div>\n\
+         </body>\n\
+         </html>\n",
+        original.lines,
+        rendered.lines,
+        batch::ratio(rendered.lines, original.lines),
+        original.bytes,
+        rendered.bytes,
+        batch::ratio(rendered.bytes, original.bytes),
+        href(&layout.target_with(&page.path, RENDERED)),
+        href(&layout.target_with(&page.path, RAW)),
+        name = escape_attr(&page.name),
+        index = href(&layout.root().join("index.html")),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::references;
+    use crate::parse::parse_xml;
+
+    #[test]
+    fn finds_stylesheet_and_schema_references() {
+        let xsd = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+            <xs:include schemaLocation="common.xsd"/>
+            <xs:import namespace="urn:x" schemaLocation="x.xsd"/>
+            <xs:import namespace="urn:y"/>
+        </xs:schema>"#;
+        let xsl = r#"<xsl:stylesheet xmlns:xsl="http://www.w3.org/1999/XSL/Transform">
+            <xsl:import href="base.xsl"/><xsl:include href="lib/util.xsl"/>
+        </xsl:stylesheet>"#;
+        let mut found = Vec::new();
+        for doc in [xsd, xsl] {
+            references(&parse_xml(doc).unwrap().roots[0], &mut found);
+        }
+        assert_eq!(found, ["common.xsd", "x.xsd", "base.xsl", "lib/util.xsl"]);
+    }
+}
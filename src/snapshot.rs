@@ -0,0 +1,150 @@
+//! Snapshot-test helpers: the `--canonical` rendering as the snapshot format.
+//!
+//! Canonical output is stable under attribute order, namespace prefixes and
+//! sibling order, so two documents that differ only in serialisation compare
+//! equal, and a real mismatch shows as a short line diff of the rendering
+//! instead of two XML blobs.
+//!
+//! ```no_run
+//! use unxml::{assert_unxml_eq, assert_unxml_snapshot};
+//!
+//! let response = "<order id=\"7\"><line qty=\"1\"/></order>";
+//! assert_unxml_eq!(response, "<order id='7'><line qty='1'></line></order>");
+//! assert_unxml_snapshot!(response, "tests/snapshots/order.unxml");
+//! ```
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use similar::{ChangeTag, TextDiff};
+
+use crate::process::Renderer;
+
+/// Environment variable that makes [`assert_unxml_snapshot!`] rewrite the
+/// snapshot file instead of comparing against it.
+pub const UPDATE_ENV: &str = "UNXML_TEST_UPDATE";
+
+/// Render `content` (XML, HTML or JSON, detected from the content) the way
+/// `unxml --canonical` does.
+pub fn render(content: &str) -> Result<String> {
+    Renderer::new().canonical(true).render(content, "")
+}
+
+/// Line diff of two renderings: `-` for lines only in `expected`, `+` for
+/// lines only in `actual`, a space for shared context.
+pub fn diff(expected: &str, actual: &str) -> String {
+    let mut buf = String::new();
+    for change in TextDiff::from_lines(expected, actual).iter_all_changes() {
+        let sign = match change.tag() {
+            ChangeTag::Delete => "-",
+            ChangeTag::Insert => "+",
+            ChangeTag::Equal => " ",
+        };
+        buf.push_str(sign);
+        buf.push_str(change.value());
+        if change.missing_newline() {
+            buf.push('\n');
+        }
+    }
+    buf
+}
+
+/// Render a document for an assertion, panicking with the parse error.
+#[track_caller]
+fn render_or_panic(side: &str, content: &str) -> String {
+    render(content).unwrap_or_else(|e| panic!("unxml: cannot render {side}: {e:#}"))
+}
+
+/// Backs [`assert_unxml_eq!`].
+#[doc(hidden)]
+#[track_caller]
+pub fn assert_eq(actual: &str, expected: &str, message: Option<String>) {
+    let actual = render_or_panic("left", actual);
+    let expected = render_or_panic("right", expected);
+    if actual != expected {
+        let context = message.map(|m| format!(": {m}")).unwrap_or_default();
+        panic!(
+            "documents differ{context}\n--- right\n+++ left\n{}",
+            diff(&expected, &actual)
+        );
+    }
+}
+
+/// Backs [`assert_unxml_snapshot!`].
+#[doc(hidden)]
+#[track_caller]
+pub fn assert_snapshot(actual: &str, path: &Path) {
+    let actual = render_or_panic("document", actual);
+    if std::env::var_os(UPDATE_ENV).is_some() {
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        fs::write(path, &actual)
+            .unwrap_or_else(|e| panic!("failed to write {}: {e}", path.display()));
+        return;
+    }
+    let expected = fs::read_to_string(path).unwrap_or_else(|_| {
+        panic!(
+            "missing snapshot {} — rerun with {UPDATE_ENV}=1 to create it",
+            path.display()
+        )
+    });
+    if actual != expected {
+        panic!(
+            "snapshot mismatch — rerun with {UPDATE_ENV}=1 to accept\n--- {} (snapshot)\n+++ (actual)\n{}",
+            path.display(),
+            diff(&expected, &actual)
+        );
+    }
+}
+
+/// Assert that two XML/HTML/JSON documents render the same under
+/// `--canonical`, printing a diff of the renderings when they don't. Takes
+/// an optional format message like `assert_eq!`.
+#[macro_export]
+macro_rules! assert_unxml_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::snapshot::assert_eq(
+            ::core::convert::AsRef::<str>::as_ref(&$left),
+            ::core::convert::AsRef::<str>::as_ref(&$right),
+            ::core::option::Option::None,
+        )
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        $crate::snapshot::assert_eq(
+            ::core::convert::AsRef::<str>::as_ref(&$left),
+            ::core::convert::AsRef::<str>::as_ref(&$right),
+            ::core::option::Option::Some(::std::format!($($arg)+)),
+        )
+    };
+}
+
+/// Assert that a document's canonical rendering matches the `.unxml` file at
+/// `path`. With `UNXML_TEST_UPDATE` set, the file is (re)written instead.
+#[macro_export]
+macro_rules! assert_unxml_snapshot {
+    ($actual:expr, $path:expr $(,)?) => {
+        $crate::snapshot::assert_snapshot(
+            ::core::convert::AsRef::<str>::as_ref(&$actual),
+            ::std::path::Path::new(&$path),
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn serialisation_differences_compare_equal() {
+        assert_unxml_eq!(
+            r#"<a xmlns:p="urn:x"><p:b y="2" x="1"/></a>"#,
+            r#"<a xmlns:q="urn:x"><q:b x="1" y="2"></q:b></a>"#,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "+  b = 1")]
+    fn mismatch_shows_the_rendering_diff() {
+        assert_unxml_eq!("<a><b>1</b></a>", "<a><b>2</b></a>");
+    }
+}
@@ -57,3 +57,107 @@ pub(crate) fn is_true(value: Option<&String>) -> bool {
 pub(crate) fn xsd_local(name: &str) -> &str {
     name.rsplit(':').next().unwrap_or(name)
 }
+
+/// True if `s` is exactly `n` ASCII digits.
+fn is_digits(s: &str, n: usize) -> bool {
+    s.len() == n && s.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Split an optional trailing timezone (`Z` or `±hh:mm`) off a date/time
+/// lexical form, with its offset in minutes. Anything that isn't a
+/// well-formed zone is left in place.
+pub(crate) fn split_timezone(s: &str) -> (&str, Option<i64>) {
+    if let Some(rest) = s.strip_suffix('Z') {
+        return (rest, Some(0));
+    }
+    // Byte arithmetic: the split must fall on a char boundary, and the zone's
+    // bytes are checked before any of it is sliced.
+    let n = s.len();
+    if n > 6 && s.is_char_boundary(n - 6) {
+        let (rest, zone) = s.split_at(n - 6);
+        let zb = zone.as_bytes();
+        let sign = match zb[0] {
+            b'+' => 1,
+            b'-' => -1,
+            _ => return (s, None),
+        };
+        let digits = |d: &[u8]| d.iter().all(u8::is_ascii_digit);
+        if zb[3] == b':' && digits(&zb[1..3]) && digits(&zb[4..]) {
+            let number = |d: &[u8]| i64::from(d[0] - b'0') * 10 + i64::from(d[1] - b'0');
+            return (
+                rest,
+                Some(sign * (number(&zb[1..3]) * 60 + number(&zb[4..]))),
+            );
+        }
+    }
+    (s, None)
+}
+
+/// `s` without its timezone, as `split_timezone` finds it.
+fn strip_timezone(s: &str) -> &str {
+    split_timezone(s).0
+}
+
+/// `YYYY-MM-DD` with plausible month/day ranges (no calendar validation).
+fn is_date_part(s: &str) -> bool {
+    let mut parts = s.split('-');
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(y), Some(m), Some(d), None) => {
+            is_digits(y, 4)
+                && is_digits(m, 2)
+                && is_digits(d, 2)
+                && (1..=12).contains(&m.parse::<u32>().unwrap_or(0))
+                && (1..=31).contains(&d.parse::<u32>().unwrap_or(0))
+        }
+        _ => false,
+    }
+}
+
+/// `hh:mm:ss` with optional fractional seconds.
+fn is_time_part(s: &str) -> bool {
+    let (hms, frac) = match s.split_once('.') {
+        Some((hms, frac)) => (hms, Some(frac)),
+        None => (s, None),
+    };
+    let mut parts = hms.split(':');
+    let ok = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(h), Some(m), Some(sec), None) => {
+            is_digits(h, 2)
+                && is_digits(m, 2)
+                && is_digits(sec, 2)
+                && h.parse::<u32>().unwrap_or(99) <= 24
+                && m.parse::<u32>().unwrap_or(99) <= 59
+                && sec.parse::<u32>().unwrap_or(99) <= 60
+        }
+        _ => false,
+    };
+    ok && frac.is_none_or(|f| !f.is_empty() && f.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// True if `s` is an `xs:date` lexical value: `YYYY-MM-DD`, optionally zoned.
+pub(crate) fn is_xsd_date(s: &str) -> bool {
+    is_date_part(strip_timezone(s))
+}
+
+/// True if `s` is an `xs:dateTime` lexical value:
+/// `YYYY-MM-DDThh:mm:ss[.fff]`, optionally zoned (`Z` or `±hh:mm`).
+pub(crate) fn is_xsd_datetime(s: &str) -> bool {
+    strip_timezone(s)
+        .split_once('T')
+        .is_some_and(|(date, time)| is_date_part(date) && is_time_part(time))
+}
+
+/// True if `s` ends in an explicit timezone (`Z` or `±hh:mm`).
+#[cfg(feature = "cli")]
+pub(crate) fn has_timezone(s: &str) -> bool {
+    strip_timezone(s).len() != s.len()
+}
+
+/// True if `s` is a UUID in the canonical 8-4-4-4-12 hex form.
+pub(crate) fn is_uuid(s: &str) -> bool {
+    s.len() == 36
+        && s.bytes().enumerate().all(|(i, b)| match i {
+            8 | 13 | 18 | 23 => b == b'-',
+            _ => b.is_ascii_hexdigit(),
+        })
+}
@@ -0,0 +1,75 @@
+//! JavaScript bindings (feature `wasm`), for rendering pasted documents in
+//! the browser. Built without `cli`, so nothing here touches files or
+//! processes:
+//!
+//! ```text
+//! cargo rustc --lib --crate-type cdylib --release --target wasm32-unknown-unknown --no-default-features --features wasm
+//! wasm-bindgen --target web --out-dir pkg target/wasm32-unknown-unknown/release/unxml.wasm
+//! ```
+
+use wasm_bindgen::prelude::*;
+
+use crate::canonical::CanonicalOptions;
+use crate::model::FormatOpts;
+use crate::process::Renderer;
+
+fn js_error(e: anyhow::Error) -> JsError {
+    JsError::new(&format!("{e:#}"))
+}
+
+/// The options every function shares; empty strings mean "not set".
+fn renderer(mode: &str, format: &str, hide_ns: Vec<String>) -> Result<Renderer, JsError> {
+    let mut renderer = Renderer::new().hide_ns(hide_ns);
+    if !mode.is_empty() {
+        renderer = renderer.mode(FormatOpts::from_mode(mode).map_err(js_error)?);
+    }
+    if !format.is_empty() {
+        renderer = renderer.format(format);
+    }
+    Ok(renderer)
+}
+
+/// Render an XML, HTML or JSON document as unxml text. `mode` is a mode
+/// flag's name (`xslt`, `xsd`, …); `auto` sniffs the document type.
+#[wasm_bindgen]
+pub fn render(
+    text: &str,
+    mode: &str,
+    format: &str,
+    hide_ns: Vec<String>,
+    auto: bool,
+) -> Result<String, JsError> {
+    renderer(mode, format, hide_ns)?
+        .auto(auto)
+        .render(text, "")
+        .map_err(js_error)
+}
+
+/// The distinct element paths of a document (`--paths`); `depth` 0 is
+/// unlimited.
+#[wasm_bindgen]
+pub fn paths(text: &str, format: &str, depth: usize, fold: bool) -> Result<String, JsError> {
+    renderer("", format, Vec::new())?
+        .paths(depth)
+        .fold(fold)
+        .render(text, "")
+        .map_err(js_error)
+}
+
+/// The `--canonical` rendering, with `--key`, `--mask` and `--normalize`
+/// specs.
+#[wasm_bindgen]
+pub fn canonical(
+    text: &str,
+    format: &str,
+    key: Vec<String>,
+    mask: Vec<String>,
+    normalize: Vec<String>,
+) -> Result<String, JsError> {
+    let options = CanonicalOptions::parse(&key, &mask, &normalize, None).map_err(js_error)?;
+    renderer("", format, Vec::new())?
+        .canonical(true)
+        .canonical_options(options)
+        .render(text, "")
+        .map_err(js_error)
+}
@@ -0,0 +1,76 @@
+//! `--watch`: render, then re-render whenever an input changes — or, under
+//! `--expand`, any stylesheet one imports or includes — until interrupted.
+//!
+//! Changes are found by polling modification times, which works the same on
+//! every platform and for editors that save by replacing the file.
+
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+
+use crate::xslt::TemplateRegistry;
+
+/// How often the watched files are checked.
+const POLL: Duration = Duration::from_millis(250);
+
+/// How long to wait after a change before rendering, so an editor's save
+/// (often several writes, or a write then a rename) has finished.
+const SETTLE: Duration = Duration::from_millis(50);
+
+/// Call `render` now and after every change to the watched files. A failing
+/// render is reported and watching goes on, since the next save may fix it.
+pub(crate) fn watch(files: &[String], expand: bool, mut render: impl FnMut() -> Result<()>) -> ! {
+    loop {
+        // The import set is re-read each time: an edit may add an import.
+        let watched = watched_files(files, expand);
+        let before = stamps(&watched);
+        if let Err(e) = render() {
+            eprintln!("Error: {e:#}");
+        }
+        while stamps(&watched) == before {
+            std::thread::sleep(POLL);
+        }
+        std::thread::sleep(SETTLE);
+    }
+}
+
+/// The inputs, plus under `expand` the stylesheets each one pulls in.
+fn watched_files(files: &[String], expand: bool) -> Vec<String> {
+    let mut watched = files.to_vec();
+    if expand {
+        for file in files {
+            // A file that isn't a readable stylesheet has no imports; its own
+            // render reports the problem.
+            if let Ok(registry) = TemplateRegistry::build_from_file(file) {
+                for source in registry.sources() {
+                    if !watched.contains(source) {
+                        watched.push(source.clone());
+                    }
+                }
+            }
+        }
+    }
+    watched
+}
+
+/// Each file's modification time; `None` while it doesn't exist (mid-save).
+fn stamps(files: &[String]) -> Vec<Option<SystemTime>> {
+    files
+        .iter()
+        .map(|file| std::fs::metadata(file).and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::watched_files;
+
+    #[test]
+    fn expand_watches_imported_stylesheets() {
+        let files = ["tests/fixtures/main.xsl".to_string()];
+        assert_eq!(watched_files(&files, false), files);
+        let watched = watched_files(&files, true);
+        assert_eq!(watched.len(), 2);
+        assert!(watched[1].ends_with("shared.xsl"));
+    }
+}
@@ -0,0 +1,128 @@
+//! Serialise an `XmlElement` tree back to XML text.
+//!
+//! The inverse of `parse_xml`, for the features that *produce* XML rather than
+//! render it (e.g. `--infer-xsd --emit-schema`). Output is indented two spaces
+//! per level; a leaf's text stays on its element's line, and mixed content is
+//! written inline from `nodes` so its interleaving survives. Attributes come out
+//! namespace declarations first, then the rest, each group sorted by name —
+//! `XmlElement` keeps them in a map, so source order is not available.
+
+use crate::model::{NodeRef, XmlElement};
+
+/// Escape text content: `&`, `<` and `>`.
+fn escape_text(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Escape an attribute value for a double-quoted attribute.
+pub(crate) fn escape_attr(s: &str) -> String {
+    escape_text(s).replace('"', "&quot;")
+}
+
+/// Attributes in output order: `xmlns`, then `xmlns:*`, then everything else,
+/// each group sorted by name.
+fn ordered_attrs(elem: &XmlElement) -> Vec<(&String, &String)> {
+    let mut attrs: Vec<(&String, &String)> = elem.attributes.iter().collect();
+    attrs.sort_by_key(|(key, _)| {
+        let rank = if key.as_str() == "xmlns" {
+            0
+        } else if key.starts_with("xmlns:") {
+            1
+        } else {
+            2
+        };
+        (rank, key.as_str())
+    });
+    attrs
+}
+
+/// The start tag (without the closing `>` / `/>`).
+pub(crate) fn open_tag(elem: &XmlElement) -> String {
+    let mut tag = format!("<{}", elem.name);
+    for (key, value) in ordered_attrs(elem) {
+        tag.push_str(&format!(" {key}=\"{}\"", escape_attr(value)));
+    }
+    tag
+}
+
+/// Write one element's content inline (no indentation or newlines), used for
+/// mixed content where added whitespace would change the text.
+fn write_inline(elem: &XmlElement, out: &mut String) {
+    out.push_str(&open_tag(elem));
+    if elem.nodes.is_empty() && elem.children.is_empty() && elem.text_content.is_empty() {
+        out.push_str("/>");
+        return;
+    }
+    out.push('>');
+    if elem.nodes.is_empty() {
+        out.push_str(&escape_text(&elem.text_content));
+        for child in &elem.children {
+            write_inline(child, out);
+        }
+    } else {
+        for node in &elem.nodes {
+            match node {
+                NodeRef::Text(text) => out.push_str(&escape_text(text)),
+                NodeRef::Child(i) => write_inline(&elem.children[*i], out),
+                NodeRef::Comment { text, .. } => out.push_str(&format!("<!-- {text} -->")),
+            }
+        }
+    }
+    out.push_str(&format!("</{}>", elem.name));
+}
+
+/// Write one element as indented lines at `indent` levels.
+pub(crate) fn write_element(elem: &XmlElement, indent: usize, out: &mut String) {
+    let ind = "  ".repeat(indent);
+    if elem.is_mixed() {
+        out.push_str(&ind);
+        write_inline(elem, out);
+        out.push('\n');
+        return;
+    }
+
+    out.push_str(&ind);
+    out.push_str(&open_tag(elem));
+    let has_comment = elem
+        .nodes
+        .iter()
+        .any(|n| matches!(n, NodeRef::Comment { .. }));
+    if elem.children.is_empty() && !has_comment {
+        let text = elem.text_content.trim();
+        if text.is_empty() {
+            out.push_str("/>\n");
+        } else {
+            out.push_str(&format!(">{}</{}>\n", escape_text(text), elem.name));
+        }
+        return;
+    }
+
+    out.push_str(">\n");
+    if has_comment {
+        for node in &elem.nodes {
+            match node {
+                NodeRef::Child(i) => write_element(&elem.children[*i], indent + 1, out),
+                NodeRef::Comment { text, .. } => {
+                    out.push_str(&format!("{ind}  <!-- {text} -->\n"));
+                }
+                NodeRef::Text(_) => {}
+            }
+        }
+    } else {
+        for child in &elem.children {
+            write_element(child, indent + 1, out);
+        }
+    }
+    out.push_str(&format!("{ind}</{}>\n", elem.name));
+}
+
+/// Serialise `roots` as an XML document with an XML declaration.
+pub(crate) fn write_xml(roots: &[XmlElement]) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    for root in roots {
+        write_element(root, 0, &mut out);
+    }
+    out
+}
@@ -0,0 +1,807 @@
+//! Semantic diff of two XML Schemas (`unxml diff --xsd OLD NEW`).
+//!
+//! A structural diff of two schema files reports markup. What a consumer of
+//! the schema wants to know is how the set of valid *instances* moved. Both
+//! schemas are read into their components (global elements and attributes,
+//! named types, groups), each with its content model flattened into members
+//! by path (`Line/Qty`, `@unit`, `ref cbc:ID`), and the components are
+//! compared. Every change is classified:
+//!
+//!  - **breaking** when an instance valid under OLD may be rejected by NEW: a
+//!    removed element or attribute, a new required one, fewer allowed
+//!    occurrences, a narrowed or changed type, a removed enumeration value, a
+//!    tightened facet, a new `fixed` value;
+//!  - **compatible** otherwise: new optional members, more occurrences, a
+//!    widened type, new enumeration values, relaxed facets.
+//!
+//! The report lists breaking changes, then compatible ones, in the vocabulary
+//! of the `--xsd` rendering: `element N : T`, `@name : T (required)`,
+//! `?`/`*`/`+` occurrences, `| value` enumerations. QNames are compared by
+//! namespace, so `xsd:string` and `xs:string` (or `tns:T` and `T`) agree.
+//!
+//! Only the two files are compared; `xs:include` and `xs:import` are not
+//! followed. A member of an `xs:choice` counts as optional, and occurrences of
+//! nested compositors multiply into their members.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::diff::{Change, SchemaReport};
+use crate::model::XmlElement;
+use crate::types::{is_true, xsd_local};
+
+const XSD_NS: &str = "http://www.w3.org/2001/XMLSchema";
+
+/// Built-in types and the type each restricts, so a change between two of
+/// them can be told apart as widening or narrowing.
+const BUILTIN_BASES: &[(&str, &str)] = &[
+    ("xs:normalizedString", "xs:string"),
+    ("xs:token", "xs:normalizedString"),
+    ("xs:language", "xs:token"),
+    ("xs:NMTOKEN", "xs:token"),
+    ("xs:Name", "xs:token"),
+    ("xs:NCName", "xs:Name"),
+    ("xs:ID", "xs:NCName"),
+    ("xs:IDREF", "xs:NCName"),
+    ("xs:ENTITY", "xs:NCName"),
+    ("xs:integer", "xs:decimal"),
+    ("xs:nonPositiveInteger", "xs:integer"),
+    ("xs:negativeInteger", "xs:nonPositiveInteger"),
+    ("xs:long", "xs:integer"),
+    ("xs:int", "xs:long"),
+    ("xs:short", "xs:int"),
+    ("xs:byte", "xs:short"),
+    ("xs:nonNegativeInteger", "xs:integer"),
+    ("xs:positiveInteger", "xs:nonNegativeInteger"),
+    ("xs:unsignedLong", "xs:nonNegativeInteger"),
+    ("xs:unsignedInt", "xs:unsignedLong"),
+    ("xs:unsignedShort", "xs:unsignedInt"),
+    ("xs:unsignedByte", "xs:unsignedShort"),
+];
+
+/// Facets whose value is an upper bound: lowering it is breaking.
+const UPPER_FACETS: &[&str] = &[
+    "maxLength",
+    "maxInclusive",
+    "maxExclusive",
+    "totalDigits",
+    "fractionDigits",
+];
+
+/// Facets whose value is a lower bound: raising it is breaking.
+const LOWER_FACETS: &[&str] = &["minLength", "minInclusive", "minExclusive"];
+
+/// One declaration: a component's own header, or one member of its content.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Decl {
+    /// `element`, `ref`, `attribute`, `type`, `group`, `any`, ….
+    kind: &'static str,
+    /// The declared type, the base type, or the list item / union members.
+    ty: Option<String>,
+    /// How `ty` is used: `extends` / `restricts` (complex types), `list`,
+    /// `union`; `None` for a plain type or simple restriction.
+    derivation: Option<&'static str>,
+    min: u64,
+    /// `None` for `unbounded`.
+    max: Option<u64>,
+    enums: BTreeSet<String>,
+    facets: BTreeMap<String, String>,
+    /// `abstract`, `mixed`, `nillable` (empty value), `default` and `fixed`.
+    flags: BTreeMap<&'static str, String>,
+}
+
+impl Decl {
+    fn new(kind: &'static str, min: u64, max: Option<u64>) -> Self {
+        Self {
+            kind,
+            min,
+            max,
+            ..Self::default()
+        }
+    }
+}
+
+/// A global element or attribute, named type or group, with its members.
+#[derive(Debug, Default)]
+struct Component {
+    decl: Decl,
+    members: BTreeMap<String, Decl>,
+}
+
+/// The components of one schema.
+#[derive(Debug, Default)]
+struct Schema {
+    /// Keyed `element NAME`, `type NAME`, `group NAME`, ….
+    components: BTreeMap<String, Component>,
+    /// Named simple types and the type each restricts.
+    bases: HashMap<String, String>,
+}
+
+/// Reads one `xs:schema` element into a `Schema`.
+struct Reader<'a> {
+    /// The schema element's prefix -> URI bindings.
+    bindings: HashMap<&'a str, &'a str>,
+    tns: &'a str,
+}
+
+/// The non-annotation children of an XSD element.
+fn content(elem: &XmlElement) -> impl Iterator<Item = &XmlElement> {
+    elem.children
+        .iter()
+        .filter(|c| xsd_local(&c.name) != "annotation")
+}
+
+fn occurs(elem: &XmlElement) -> (u64, Option<u64>) {
+    let min = elem
+        .attributes
+        .get("minOccurs")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(1);
+    let max = match elem.attributes.get("maxOccurs").map(String::as_str) {
+        Some("unbounded") => None,
+        Some(v) => Some(v.parse().unwrap_or(1)),
+        None => Some(1),
+    };
+    (min, max)
+}
+
+/// Insert `decl` under `key`, numbering repeated keys (`Note#2`) so a content
+/// model naming one element twice keeps both.
+fn insert(members: &mut BTreeMap<String, Decl>, key: String, decl: Decl) {
+    let mut unique = key.clone();
+    let mut n = 1;
+    while members.contains_key(&unique) {
+        n += 1;
+        unique = format!("{key}#{n}");
+    }
+    members.insert(unique, decl);
+}
+
+impl<'a> Reader<'a> {
+    fn new(schema: &'a XmlElement) -> Self {
+        let mut bindings = HashMap::new();
+        for (key, value) in &schema.attributes {
+            if key == "xmlns" {
+                bindings.insert("", value.as_str());
+            } else if let Some(prefix) = key.strip_prefix("xmlns:") {
+                bindings.insert(prefix, value.as_str());
+            }
+        }
+        let tns = schema
+            .attributes
+            .get("targetNamespace")
+            .map_or("", String::as_str);
+        Self { bindings, tns }
+    }
+
+    /// Spell a QName reference independently of the schema's prefixes: XSD
+    /// built-ins as `xs:…`, the schema's own components by local name.
+    fn qname(&self, raw: &str) -> String {
+        let (prefix, local) = raw.split_once(':').unwrap_or(("", raw));
+        match self.bindings.get(prefix) {
+            Some(&XSD_NS) => format!("xs:{local}"),
+            Some(&uri) if uri == self.tns => local.to_string(),
+            None if prefix.is_empty() => local.to_string(),
+            _ => raw.to_string(),
+        }
+    }
+
+    fn read(&self, root: &XmlElement, out: &mut Schema) {
+        for child in content(root) {
+            let local = xsd_local(&child.name);
+            let Some(name) = child.attributes.get("name") else {
+                continue;
+            };
+            let kind = match local {
+                "complexType" | "simpleType" => "type",
+                "element" => "element",
+                "attribute" => "attribute",
+                "group" => "group",
+                _ => "attributeGroup",
+            };
+            let mut component = Component::default();
+            match local {
+                "element" => {
+                    component.decl = self.element(child, "", (1, Some(1)), &mut component.members);
+                }
+                "attribute" => {
+                    component.decl = self.attribute(child);
+                }
+                "complexType" => {
+                    component.decl = Decl::new("type", 1, Some(1));
+                    self.complex_type(child, "", &mut component.decl, &mut component.members);
+                }
+                "simpleType" => {
+                    component.decl = Decl::new("type", 1, Some(1));
+                    self.simple_type(child, &mut component.decl);
+                    if component.decl.derivation.is_none()
+                        && let Some(base) = &component.decl.ty
+                    {
+                        out.bases.insert(name.clone(), base.clone());
+                    }
+                }
+                "group" | "attributeGroup" => {
+                    component.decl = Decl::new(kind, 1, Some(1));
+                    self.model(child, "", (1, Some(1)), false, &mut component.members);
+                }
+                _ => continue,
+            }
+            out.components.insert(format!("{kind} {name}"), component);
+        }
+    }
+
+    /// An element declaration; the members of an anonymous complex type go
+    /// into `members` with their paths prefixed by `prefix`.
+    fn element(
+        &self,
+        elem: &XmlElement,
+        prefix: &str,
+        (min, max): (u64, Option<u64>),
+        members: &mut BTreeMap<String, Decl>,
+    ) -> Decl {
+        let mut decl = Decl::new("element", min, max);
+        let attrs = &elem.attributes;
+        if is_true(attrs.get("nillable")) {
+            decl.flags.insert("nillable", String::new());
+        }
+        if is_true(attrs.get("abstract")) {
+            decl.flags.insert("abstract", String::new());
+        }
+        for flag in ["default", "fixed"] {
+            if let Some(value) = attrs.get(flag) {
+                decl.flags.insert(flag, value.clone());
+            }
+        }
+        if let Some(ty) = attrs.get("type") {
+            decl.ty = Some(self.qname(ty));
+            return decl;
+        }
+        for child in content(elem) {
+            match xsd_local(&child.name) {
+                "simpleType" => self.simple_type(child, &mut decl),
+                "complexType" => self.complex_type(child, prefix, &mut decl, members),
+                _ => {}
+            }
+        }
+        decl
+    }
+
+    fn attribute(&self, attr: &XmlElement) -> Decl {
+        let (min, max) = match attr.attributes.get("use").map(String::as_str) {
+            Some("required") => (1, Some(1)),
+            Some("prohibited") => (0, Some(0)),
+            _ => (0, Some(1)),
+        };
+        let mut decl = Decl::new("attribute", min, max);
+        for flag in ["default", "fixed"] {
+            if let Some(value) = attr.attributes.get(flag) {
+                decl.flags.insert(flag, value.clone());
+            }
+        }
+        if let Some(ty) = attr.attributes.get("type") {
+            decl.ty = Some(self.qname(ty));
+        } else if let Some(st) = content(attr).find(|c| xsd_local(&c.name) == "simpleType") {
+            self.simple_type(st, &mut decl);
+        }
+        decl
+    }
+
+    /// A complex type's flags, derivation and content.
+    fn complex_type(
+        &self,
+        ct: &XmlElement,
+        prefix: &str,
+        decl: &mut Decl,
+        members: &mut BTreeMap<String, Decl>,
+    ) {
+        for flag in ["abstract", "mixed"] {
+            if is_true(ct.attributes.get(flag)) {
+                decl.flags.insert(flag, String::new());
+            }
+        }
+        for child in content(ct) {
+            let local = xsd_local(&child.name);
+            if local != "complexContent" && local != "simpleContent" {
+                self.particle(child, prefix, (1, Some(1)), false, members);
+                continue;
+            }
+            if is_true(child.attributes.get("mixed")) {
+                decl.flags.insert("mixed", String::new());
+            }
+            for derivation in content(child) {
+                let kw = match xsd_local(&derivation.name) {
+                    "extension" => "extends",
+                    "restriction" => "restricts",
+                    _ => continue,
+                };
+                decl.derivation = Some(kw);
+                decl.ty = derivation.attributes.get("base").map(|b| self.qname(b));
+                self.facets(derivation, decl);
+                self.model(derivation, prefix, (1, Some(1)), false, members);
+            }
+        }
+    }
+
+    /// A simple type's base, list item or union members, and facets.
+    fn simple_type(&self, st: &XmlElement, decl: &mut Decl) {
+        for child in content(st) {
+            match xsd_local(&child.name) {
+                "restriction" => {
+                    decl.ty = child.attributes.get("base").map(|b| self.qname(b));
+                    self.facets(child, decl);
+                }
+                "list" => {
+                    decl.derivation = Some("list");
+                    decl.ty = child.attributes.get("itemType").map(|t| self.qname(t));
+                }
+                "union" => {
+                    decl.derivation = Some("union");
+                    decl.ty = child.attributes.get("memberTypes").map(|types| {
+                        let mut types: Vec<String> =
+                            types.split_whitespace().map(|t| self.qname(t)).collect();
+                        types.sort();
+                        types.join(" ")
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn facets(&self, restriction: &XmlElement, decl: &mut Decl) {
+        for facet in content(restriction) {
+            let local = xsd_local(&facet.name);
+            let Some(value) = facet.attributes.get("value") else {
+                continue;
+            };
+            match local {
+                "enumeration" => {
+                    decl.enums.insert(value.clone());
+                }
+                "pattern" => {
+                    let patterns = decl.facets.entry("pattern".to_string()).or_default();
+                    if !patterns.is_empty() {
+                        patterns.push_str(" | ");
+                    }
+                    patterns.push_str(value);
+                }
+                "length" | "minLength" | "maxLength" | "minInclusive" | "maxInclusive"
+                | "minExclusive" | "maxExclusive" | "totalDigits" | "fractionDigits"
+                | "whiteSpace" => {
+                    decl.facets.insert(local.to_string(), value.clone());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The particles and attributes directly inside `container`.
+    fn model(
+        &self,
+        container: &XmlElement,
+        prefix: &str,
+        outer: (u64, Option<u64>),
+        in_choice: bool,
+        members: &mut BTreeMap<String, Decl>,
+    ) {
+        for child in content(container) {
+            self.particle(child, prefix, outer, in_choice, members);
+        }
+    }
+
+    /// One member of a content model. `outer` is the product of the enclosing
+    /// compositors' occurrences; `in_choice` makes the member optional.
+    fn particle(
+        &self,
+        child: &XmlElement,
+        prefix: &str,
+        outer: (u64, Option<u64>),
+        in_choice: bool,
+        members: &mut BTreeMap<String, Decl>,
+    ) {
+        let (min, max) = occurs(child);
+        let min = if in_choice {
+            0
+        } else {
+            outer.0.saturating_mul(min)
+        };
+        let max = outer.1.zip(max).map(|(a, b)| a.saturating_mul(b));
+        let attrs = &child.attributes;
+        match xsd_local(&child.name) {
+            "element" => {
+                if let Some(r) = attrs.get("ref") {
+                    insert(
+                        members,
+                        format!("{prefix}{}", self.qname(r)),
+                        Decl::new("ref", min, max),
+                    );
+                } else if let Some(name) = attrs.get("name") {
+                    let inner = format!("{prefix}{name}/");
+                    let decl = self.element(child, &inner, (min, max), members);
+                    insert(members, format!("{prefix}{name}"), decl);
+                }
+            }
+            "sequence" | "all" => self.model(child, prefix, (min, max), false, members),
+            "choice" => self.model(child, prefix, (min, max), true, members),
+            "group" | "attributeGroup" => {
+                if let Some(r) = attrs.get("ref") {
+                    let kind = if xsd_local(&child.name) == "group" {
+                        "group"
+                    } else {
+                        "attributeGroup"
+                    };
+                    let key = format!("{prefix}{kind} ref {}", self.qname(r));
+                    insert(members, key, Decl::new(kind, min, max));
+                }
+            }
+            "any" => {
+                let ns = attrs
+                    .get("namespace")
+                    .map(|ns| format!(" {ns}"))
+                    .unwrap_or_default();
+                insert(
+                    members,
+                    format!("{prefix}any{ns}"),
+                    Decl::new("any", min, max),
+                );
+            }
+            "anyAttribute" => {
+                insert(
+                    members,
+                    format!("{prefix}@any"),
+                    Decl::new("anyAttribute", 0, Some(1)),
+                );
+            }
+            "attribute" => {
+                let name = attrs
+                    .get("name")
+                    .cloned()
+                    .or_else(|| attrs.get("ref").map(|r| self.qname(r)));
+                if let Some(name) = name {
+                    insert(members, format!("{prefix}@{name}"), self.attribute(child));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Schema {
+    fn read(roots: &[XmlElement]) -> Self {
+        let mut schema = Self::default();
+        for root in roots.iter().filter(|r| xsd_local(&r.name) == "schema") {
+            Reader::new(root).read(root, &mut schema);
+        }
+        schema
+    }
+
+    /// `ty` and the types it restricts, nearest first.
+    fn ancestors<'a>(&'a self, mut ty: &'a str) -> Vec<&'a str> {
+        let mut chain = vec![ty];
+        while let Some(base) = self.bases.get(ty).map(String::as_str).or_else(|| {
+            BUILTIN_BASES
+                .iter()
+                .find(|(t, _)| *t == ty)
+                .map(|(_, base)| *base)
+        }) {
+            if chain.contains(&base) {
+                break;
+            }
+            chain.push(base);
+            ty = base;
+        }
+        chain
+    }
+}
+
+/// An occurrence range the way the `--xsd` rendering suffixes it.
+fn occurs_suffix(min: u64, max: Option<u64>) -> String {
+    match (min, max) {
+        (1, Some(1)) => String::new(),
+        (0, Some(1)) => " ?".to_string(),
+        (0, None) => " *".to_string(),
+        (1, None) => " +".to_string(),
+        (m, Some(n)) => format!(" [{m}..{n}]"),
+        (m, None) => format!(" [{m}..*]"),
+    }
+}
+
+/// An occurrence range on its own, for `old → new` lines.
+fn occurs_label(decl: &Decl) -> String {
+    if matches!(decl.kind, "attribute" | "anyAttribute") {
+        return match (decl.min, decl.max) {
+            (_, Some(0)) => "prohibited",
+            (0, _) => "optional",
+            _ => "required",
+        }
+        .to_string();
+    }
+    match occurs_suffix(decl.min, decl.max).trim() {
+        "" => "1".to_string(),
+        suffix => suffix.to_string(),
+    }
+}
+
+fn render_ty(decl: &Decl) -> Option<String> {
+    let ty = decl.ty.as_deref()?;
+    Some(match decl.derivation {
+        Some(kw) => format!("{kw} {ty}"),
+        None => ty.to_string(),
+    })
+}
+
+fn render_flags(decl: &Decl) -> String {
+    decl.flags
+        .iter()
+        .map(|(flag, value)| match *flag {
+            "default" => format!(" = {value}"),
+            "fixed" => format!(" == {value}"),
+            flag => format!(" {flag}"),
+        })
+        .collect()
+}
+
+/// A component's header line: `element Invoice : InvoiceType`,
+/// `type LineType extends BaseType`, `type Code : xs:token`.
+fn render_header(key: &str, decl: &Decl) -> String {
+    let ty = match (decl.derivation, render_ty(decl)) {
+        (Some("extends" | "restricts"), Some(ty)) => format!(" {ty}"),
+        (_, Some(ty)) => format!(" : {ty}"),
+        (_, None) => String::new(),
+    };
+    let required = if decl.kind == "attribute" && decl.min > 0 {
+        " (required)"
+    } else {
+        ""
+    };
+    format!("{key}{ty}{required}{}", render_flags(decl))
+}
+
+/// A member line: `Line/Qty : xs:int ?`, `@unit : xs:token (required)`,
+/// `ref cbc:ID`, `group ref Address *`.
+fn render_member(path: &str, decl: &Decl) -> String {
+    let ty = render_ty(decl)
+        .map(|ty| format!(" : {ty}"))
+        .unwrap_or_default();
+    let flags = render_flags(decl);
+    match decl.kind {
+        "attribute" | "anyAttribute" => {
+            let usage = match occurs_label(decl).as_str() {
+                "optional" => String::new(),
+                usage => format!(" ({usage})"),
+            };
+            format!("{path}{ty}{usage}{flags}")
+        }
+        "ref" => format!("ref {path}{}{flags}", occurs_suffix(decl.min, decl.max)),
+        _ => format!("{path}{ty}{}{flags}", occurs_suffix(decl.min, decl.max)),
+    }
+}
+
+/// Compares two versions of a component, collecting classified changes.
+struct Comparer<'a> {
+    old: &'a Schema,
+    new: &'a Schema,
+    changes: Vec<Change>,
+}
+
+impl Comparer<'_> {
+    fn push(&mut self, breaking: bool, line: String) {
+        self.changes.push(Change { breaking, line });
+    }
+
+    /// Whether moving from type `old` to `new` can reject an old instance:
+    /// `Some(false)` when `new` is a base of `old` (widened), `Some(true)`
+    /// when `old` is a base of `new` (narrowed), `None` when unrelated.
+    fn narrows(&self, old: &str, new: &str) -> Option<bool> {
+        if self.old.ancestors(old).contains(&new) {
+            Some(false)
+        } else if self.new.ancestors(new).contains(&old) {
+            Some(true)
+        } else {
+            None
+        }
+    }
+
+    /// Compare two declarations of one member (`label` is its path and a
+    /// space, empty for a component's header).
+    fn decl(&mut self, label: &str, old: &Decl, new: &Decl) {
+        if old.kind != new.kind {
+            self.push(true, format!("~ {label}{} → {}", old.kind, new.kind));
+            return;
+        }
+
+        if old.ty != new.ty || old.derivation != new.derivation {
+            let (from, to) = (
+                render_ty(old).unwrap_or_else(|| "(anonymous)".to_string()),
+                render_ty(new).unwrap_or_else(|| "(anonymous)".to_string()),
+            );
+            let relation = match (&old.ty, &new.ty) {
+                (Some(o), Some(n)) if old.derivation == new.derivation => self.narrows(o, n),
+                _ => None,
+            };
+            let (breaking, note) = match relation {
+                Some(false) => (false, " (widened)"),
+                Some(true) => (true, " (narrowed)"),
+                None => (true, ""),
+            };
+            self.push(breaking, format!("~ {label}: {from} → {to}{note}"));
+        }
+
+        if (old.min, old.max) != (new.min, new.max) {
+            let fewer = |o: Option<u64>, n: Option<u64>| match (o, n) {
+                (_, None) => false,
+                (None, Some(_)) => true,
+                (Some(o), Some(n)) => n < o,
+            };
+            let breaking = new.min > old.min || fewer(old.max, new.max);
+            self.push(
+                breaking,
+                format!("~ {label}{} → {}", occurs_label(old), occurs_label(new)),
+            );
+        }
+
+        // A new enumeration restricts a value space that had none.
+        let restricts = old.enums.is_empty();
+        for value in old.enums.difference(&new.enums) {
+            self.push(!new.enums.is_empty(), format!("- {label}| {value}"));
+        }
+        for value in new.enums.difference(&old.enums) {
+            self.push(restricts, format!("+ {label}| {value}"));
+        }
+
+        let facets: BTreeSet<&String> = old.facets.keys().chain(new.facets.keys()).collect();
+        for facet in facets {
+            match (old.facets.get(facet), new.facets.get(facet)) {
+                (Some(o), None) => self.push(false, format!("- {label}{facet} {o}")),
+                (None, Some(n)) => self.push(true, format!("+ {label}{facet} {n}")),
+                (Some(o), Some(n)) if o != n => {
+                    let tighter = match (o.parse::<f64>(), n.parse::<f64>()) {
+                        (Ok(o), Ok(n)) if UPPER_FACETS.contains(&facet.as_str()) => n < o,
+                        (Ok(o), Ok(n)) if LOWER_FACETS.contains(&facet.as_str()) => n > o,
+                        _ => true,
+                    };
+                    self.push(tighter, format!("~ {label}{facet} {o} → {n}"));
+                }
+                _ => {}
+            }
+        }
+
+        let flags: BTreeSet<&&str> = old.flags.keys().chain(new.flags.keys()).collect();
+        for &&flag in &flags {
+            let (o, n) = (old.flags.get(flag), new.flags.get(flag));
+            // Which direction can reject an old instance.
+            let breaking = match (flag, o.is_some(), n.is_some()) {
+                ("default", ..) => false,
+                ("fixed", _, true) | ("abstract", false, true) => true,
+                ("nillable" | "mixed", true, false) => true,
+                _ => false,
+            };
+            let line = match (o, n) {
+                (Some(o), Some(n)) if o != n => format!("~ {label}{flag} {o} → {n}"),
+                (Some(o), None) => format!("- {label}{}", flag_text(flag, o)),
+                (None, Some(n)) => format!("+ {label}{}", flag_text(flag, n)),
+                _ => continue,
+            };
+            self.push(breaking, line);
+        }
+    }
+
+    fn component(&mut self, old: &Component, new: &Component) {
+        self.decl("", &old.decl, &new.decl);
+        let paths: BTreeSet<&String> = old.members.keys().chain(new.members.keys()).collect();
+        for path in paths {
+            match (old.members.get(path), new.members.get(path)) {
+                (Some(o), None) => self.push(true, format!("- {}", render_member(path, o))),
+                (None, Some(n)) => {
+                    // A new member an old instance lacks rejects it if required.
+                    let breaking = n.min > 0 && n.kind != "attributeGroup";
+                    self.push(breaking, format!("+ {}", render_member(path, n)));
+                }
+                (Some(o), Some(n)) if o != n => self.decl(&format!("{path} "), o, n),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn flag_text(flag: &str, value: &str) -> String {
+    match flag {
+        "default" => format!("= {value}"),
+        "fixed" => format!("== {value}"),
+        flag => flag.to_string(),
+    }
+}
+
+/// Compare two parsed schemas; empty when no component changed.
+pub(crate) fn diff_schemas(old: &[XmlElement], new: &[XmlElement]) -> String {
+    let (old, new) = (Schema::read(old), Schema::read(new));
+    let mut report = SchemaReport::default();
+    let keys: BTreeSet<&String> = old.components.keys().chain(new.components.keys()).collect();
+    for key in keys {
+        match (old.components.get(key), new.components.get(key)) {
+            (Some(o), None) => report.removed(&render_header(key, &o.decl)),
+            (None, Some(n)) => report.added(&render_header(key, &n.decl)),
+            (Some(o), Some(n)) => {
+                let mut comparer = Comparer {
+                    old: &old,
+                    new: &new,
+                    changes: Vec::new(),
+                };
+                comparer.component(o, n);
+                report.changed(&render_header(key, &n.decl), &comparer.changes);
+            }
+            (None, None) => {}
+        }
+    }
+    report.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::diff_schemas;
+    use crate::parse::parse_xml;
+
+    fn schema(body: &str) -> Vec<crate::model::XmlElement> {
+        parse_xml(&format!(
+            r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">{body}</xs:schema>"#
+        ))
+        .unwrap()
+        .roots
+    }
+
+    #[test]
+    fn classifies_changes() {
+        let old = schema(concat!(
+            r#"<xs:complexType name="LineType"><xs:sequence>"#,
+            r#"<xs:element name="Qty" type="xs:int"/>"#,
+            r#"<xs:element name="Note" type="xs:string" minOccurs="0"/>"#,
+            r#"</xs:sequence><xs:attribute name="unit" type="xs:token"/></xs:complexType>"#,
+            r#"<xs:simpleType name="Code"><xs:restriction base="xs:token">"#,
+            r#"<xs:enumeration value="A"/><xs:enumeration value="B"/>"#,
+            r#"<xs:maxLength value="3"/></xs:restriction></xs:simpleType>"#,
+        ));
+        let new = schema(concat!(
+            r#"<xs:complexType name="LineType"><xs:sequence>"#,
+            r#"<xs:element name="Qty" type="xs:long" maxOccurs="unbounded"/>"#,
+            r#"<xs:element name="Price" type="xs:decimal"/>"#,
+            r#"</xs:sequence><xs:attribute name="unit" type="xs:token" use="required"/></xs:complexType>"#,
+            r#"<xs:simpleType name="Code"><xs:restriction base="xs:token">"#,
+            r#"<xs:enumeration value="A"/><xs:enumeration value="C"/>"#,
+            r#"<xs:maxLength value="5"/></xs:restriction></xs:simpleType>"#,
+        ));
+        assert_eq!(
+            diff_schemas(&old, &new),
+            concat!(
+                "breaking\n",
+                "  ~ type Code : xs:token\n",
+                "      - | B\n",
+                "  ~ type LineType\n",
+                "      ~ @unit optional → required\n",
+                "      - Note : xs:string ?\n",
+                "      + Price : xs:decimal\n",
+                "compatible\n",
+                "  ~ type Code : xs:token\n",
+                "      + | C\n",
+                "      ~ maxLength 3 → 5\n",
+                "  ~ type LineType\n",
+                "      ~ Qty : xs:int → xs:long (widened)\n",
+                "      ~ Qty 1 → +\n",
+            )
+        );
+        assert!(diff_schemas(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn saturates_nested_occurrences() {
+        let doc = |max: &str| {
+            schema(&format!(
+                r#"<xs:complexType name="T"><xs:sequence minOccurs="{max}" maxOccurs="{max}">
+                   <xs:element name="A" minOccurs="3" maxOccurs="3"/></xs:sequence></xs:complexType>"#
+            ))
+        };
+        let huge = u64::MAX.to_string();
+        assert_eq!(
+            diff_schemas(&doc("2"), &doc(&huge)),
+            format!("breaking\n  ~ type T\n      ~ A [6..6] → [{huge}..{huge}]\n"),
+        );
+    }
+}
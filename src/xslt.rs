@@ -2,11 +2,14 @@
 //! and the import-following template registry used by `--expand`.
 
 use std::collections::HashMap;
+#[cfg(feature = "cli")]
 use std::path::Path;
 
+#[cfg(feature = "cli")]
 use anyhow::{Context, Result};
 
 use crate::model::{FormatOpts, NodeRef, XmlElement};
+#[cfg(feature = "cli")]
 use crate::parse::{parse_xml, read_file_lenient};
 use crate::render::{WRAP_WIDTH, current_col, render_attrs};
 use crate::types::simplify_type;
@@ -502,18 +505,30 @@ impl XmlElement {
 
 /// Registry of templates collected from XSLT files for expansion
 #[derive(Debug, Default)]
-pub(crate) struct TemplateRegistry {
+pub struct TemplateRegistry {
     /// Map from match pattern to template element
     templates: HashMap<String, XmlElement>,
+    /// The stylesheets read, the root one first, for `--watch`.
+    #[cfg(feature = "cli")]
+    sources: Vec<String>,
 }
 
 impl TemplateRegistry {
+    #[cfg(feature = "cli")]
     pub(crate) fn new() -> Self {
         Self {
             templates: HashMap::new(),
+            sources: Vec::new(),
         }
     }
 
+    /// The files the registry was built from: the stylesheet and everything
+    /// it imports or includes, transitively.
+    #[cfg(feature = "cli")]
+    pub(crate) fn sources(&self) -> &[String] {
+        &self.sources
+    }
+
     /// Get a template by its match pattern
     /// Handles XSLT union patterns like "PAYEE|RECEIVER" matching "PAYEE"
     /// Also handles path selects like "Input/Header" matching template "Header"
@@ -542,6 +557,7 @@ impl TemplateRegistry {
     }
 
     /// Collect templates from an XmlElement tree (looks for xsl:template elements)
+    #[cfg(feature = "cli")]
     pub(crate) fn collect_from_element(&mut self, element: &XmlElement) {
         if element.name == "xsl:template"
             && let Some(match_attr) = element.attributes.get("match")
@@ -554,6 +570,7 @@ impl TemplateRegistry {
     }
 
     /// Collect xsl:import hrefs from an element tree
+    #[cfg(feature = "cli")]
     pub(crate) fn collect_imports(element: &XmlElement) -> Vec<String> {
         let mut imports = Vec::new();
         if (element.name == "xsl:import" || element.name == "xsl:include")
@@ -568,6 +585,7 @@ impl TemplateRegistry {
     }
 
     /// Build registry from a file, following imports recursively
+    #[cfg(feature = "cli")]
     pub(crate) fn build_from_file(file_path: &str) -> Result<Self> {
         let mut registry = Self::new();
         let mut processed = std::collections::HashSet::new();
@@ -575,6 +593,7 @@ impl TemplateRegistry {
         Ok(registry)
     }
 
+    #[cfg(feature = "cli")]
     pub(crate) fn process_file_recursive(
         &mut self,
         file_path: &str,
@@ -588,6 +607,7 @@ impl TemplateRegistry {
             return Ok(());
         }
         processed.insert(canonical_str);
+        self.sources.push(file_path.to_string());
 
         let content = read_file_lenient(file_path)
             .with_context(|| format!("Failed to read file for template expansion: {file_path}"))?;
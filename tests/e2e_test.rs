@@ -4,7 +4,6 @@
 //! Run with `UNXML_TEST_UPDATE=1 cargo test --test e2e_test` to refresh the
 //! golden files after an intentional output change.
 
-use similar::{ChangeTag, TextDiff};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
@@ -54,7 +53,11 @@ fn extra_args(path: &Path) -> Vec<&'static str> {
         args.push("item");
     }
 
-    if name.starts_with("ubl-") || name.starts_with("cii-") || name.starts_with("msbuild-sniff-") {
+    if name.starts_with("ubl-")
+        || name.starts_with("cii-")
+        || name.starts_with("msbuild-sniff-")
+        || name.starts_with("dialect-sniff-")
+    {
         args.push("--auto");
     }
 
@@ -69,7 +72,13 @@ fn extra_args(path: &Path) -> Vec<&'static str> {
 
 fn run_unxml(input: &Path, extra: &[&str]) -> String {
     let bin = env!("CARGO_BIN_EXE_unxml");
+    // Keep the user's and the project's config files out of the goldens.
     let output = Command::new(bin)
+        .env(
+            "XDG_CONFIG_HOME",
+            Path::new(env!("CARGO_TARGET_TMPDIR")).join("no-user-config"),
+        )
+        .arg("--no-config")
         .args(extra)
         .arg(input)
         .output()
@@ -91,21 +100,6 @@ fn expected_output_path(input: &Path) -> PathBuf {
     PathBuf::from("expected-output").join(format!("{name}.unxml"))
 }
 
-fn render_diff(expected: &str, actual: &str) -> String {
-    let diff = TextDiff::from_lines(expected, actual);
-    let mut buf = String::new();
-    for change in diff.iter_all_changes() {
-        let sign = match change.tag() {
-            ChangeTag::Delete => "-",
-            ChangeTag::Insert => "+",
-            ChangeTag::Equal => " ",
-        };
-        buf.push_str(sign);
-        buf.push_str(change.value());
-    }
-    buf
-}
-
 #[test]
 fn golden_files_match() {
     let update = std::env::var_os("UNXML_TEST_UPDATE").is_some();
@@ -136,7 +130,7 @@ fn golden_files_match() {
                 "{}:\n--- {} (expected)\n+++ (actual)\n{}",
                 input.display(),
                 expected_path.display(),
-                render_diff(&expected, &actual)
+                unxml::snapshot::diff(&expected, &actual)
             ));
         }
     }
@@ -1,8 +1,23 @@
 use std::process::Command;
 
-fn run_unxml(args: &[&str]) -> String {
-    let output = Command::new("cargo")
+/// `unxml` through `cargo run`, with a config directory of its own so the
+/// user's config and namespaces files can't change the output.
+fn unxml() -> Command {
+    let mut command = Command::new("cargo");
+    command
         .args(["run", "--quiet", "--"])
+        .env("XDG_CONFIG_HOME", no_user_config());
+    command
+}
+
+/// A config directory with nothing in it.
+fn no_user_config() -> std::path::PathBuf {
+    std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("no-user-config")
+}
+
+fn run_unxml(args: &[&str]) -> String {
+    let output = unxml()
+        .arg("--no-config")
         .args(args)
         .output()
         .expect("Failed to execute unxml");
@@ -124,6 +139,123 @@ fn test_canonical_well_known_prefixes() {
     assert!(out.contains("xs:element"), "got: {out}");
 }
 
+// `--ns-hash` and `--ns-map` name a URI the same in every file, whatever other
+// namespaces a file declares; plain numbering follows each file's URI set.
+#[test]
+fn test_canonical_stable_ns_prefixes() {
+    let dir = std::env::temp_dir().join("unxml-canonical-test");
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let one = dir.join("ns-one.xml");
+    let two = dir.join("ns-two.xml");
+    std::fs::write(&one, r#"<o:order xmlns:o="urn:b:order"/>"#).unwrap();
+    std::fs::write(
+        &two,
+        r#"<o:order xmlns:o="urn:b:order"><a:x xmlns:a="urn:a:audit"/></o:order>"#,
+    )
+    .unwrap();
+    let map = dir.join("ns-map.txt");
+    std::fs::write(&map, "# house vocabularies\nurn:b:order = ord\n").unwrap();
+
+    let numbered = |f: &std::path::Path| run_unxml(&["--canonical", f.to_str().unwrap()]);
+    assert!(numbered(&one).starts_with("ns1:order"));
+    assert!(numbered(&two).starts_with("ns2:order"));
+
+    let hashed = |f: &std::path::Path| {
+        let out = run_unxml(&["--canonical", "--ns-hash", f.to_str().unwrap()]);
+        out.split(':').next().unwrap().to_string()
+    };
+    assert_eq!(hashed(&one), hashed(&two));
+    assert_eq!(hashed(&one).len(), "ns".len() + 4);
+
+    let mapped = run_unxml(&[
+        "--canonical",
+        "--ns-map",
+        map.to_str().unwrap(),
+        two.to_str().unwrap(),
+    ]);
+    assert!(mapped.starts_with("ord:order"), "got: {mapped}");
+    assert!(mapped.contains("ns1:x"), "got: {mapped}");
+}
+
+// User vocabularies from the config file and `--ns-prefix` extend the
+// well-known prefix table for --canonical, the --paths legend and --auto hiding.
+#[test]
+fn test_user_known_prefixes() {
+    let dir = std::env::temp_dir().join("unxml-known-prefixes-test");
+    std::fs::create_dir_all(dir.join("unxml")).unwrap();
+    std::fs::write(
+        dir.join("unxml").join("namespaces"),
+        "# in-house\nurn:acme:core=core hide\n",
+    )
+    .unwrap();
+    let f = dir.join("doc.xml");
+    std::fs::write(
+        &f,
+        r#"<core:doc xmlns:core="urn:acme:core" xmlns:b="urn:acme:ext"><core:x/><b:y/></core:doc>"#,
+    )
+    .unwrap();
+
+    let run = |args: &[&str]| {
+        let output = unxml()
+            .arg("--no-config")
+            .args(args)
+            .arg(&f)
+            .env("XDG_CONFIG_HOME", &dir)
+            .output()
+            .expect("Failed to execute unxml");
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let canonical = run(&["--canonical", "--ns-prefix", "urn:acme:ext=acx"]);
+    assert!(canonical.starts_with("core:doc"), "got: {canonical}");
+    assert!(canonical.contains("acx:y"), "got: {canonical}");
+
+    let paths = run(&["--paths"]);
+    assert!(!paths.contains("// core ="), "got: {paths}");
+    assert!(paths.contains("// b = urn:acme:ext"), "got: {paths}");
+
+    let auto = run(&["--auto"]);
+    assert!(auto.starts_with("doc"), "got: {auto}");
+    assert!(auto.contains("b:y"), "got: {auto}");
+}
+
+// One prefix never names two namespaces: a user prefix can't take a built-in
+// one from its vocabulary, and two URIs of one family (UBL 2.0 and 2.1) get
+// the conventional prefix once, the other a numbered one.
+#[test]
+fn test_known_prefix_clash() {
+    let dir = std::env::temp_dir().join("unxml-prefix-clash-test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let f = dir.join("ns.xml");
+    std::fs::write(
+        &f,
+        r#"<r xmlns:s="http://www.w3.org/2001/XMLSchema" xmlns:a="urn:acme"><s:x/><a:y/></r>"#,
+    )
+    .unwrap();
+    let output = unxml()
+        .args(["--no-config", "--canonical", "--ns-prefix", "urn:acme=xs"])
+        .arg(&f)
+        .output()
+        .unwrap();
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("prefix 'xs' is built in for http://www.w3.org/2001/XMLSchema"),
+        "got: {stderr}"
+    );
+
+    let f = dir.join("ubl.xml");
+    std::fs::write(
+        &f,
+        r#"<r xmlns:a="urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2" xmlns:b="urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-3"><a:x/><b:y/></r>"#,
+    )
+    .unwrap();
+    let out = run_unxml(&["--canonical", f.to_str().unwrap()]);
+    assert!(out.contains("cbc:x"), "got: {out}");
+    assert!(out.contains("ns1:y"), "got: {out}");
+}
+
 // `--paths` dumps distinct element paths, sorted, each annotated with the union
 // of attribute names seen at that path (across all occurrences).
 #[test]
@@ -314,3 +446,778 @@ fn test_paths_no_attrs_keeps_namespaces() {
     assert!(out.lines().any(|l| l.trim() == "line"), "got: {out}");
     assert!(out.lines().any(|l| l.trim() == "qty"), "got: {out}");
 }
+
+// `unxml diff` prints only what changed, marked `+`/`-`/`~`, and exits like
+// diff(1): 0 when the documents match, 1 when they differ.
+#[test]
+fn test_diff_subcommand() {
+    let dir = std::env::temp_dir().join("unxml-diff-test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let a = dir.join("a.json");
+    let b = dir.join("b.json");
+    std::fs::write(&a, r#"{"name":"svc","port":80,"tags":["x"]}"#).unwrap();
+    std::fs::write(&b, r#"{"tags":["x","y"],"name":"svc","port":8080}"#).unwrap();
+
+    let run = |old: &std::path::Path, new: &std::path::Path| {
+        unxml()
+            .arg("diff")
+            .args([old, new])
+            .output()
+            .expect("Failed to execute unxml")
+    };
+
+    let changed = run(&a, &b);
+    assert_eq!(changed.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&changed.stdout),
+        "+ tags[] = y\n~ port = 80 → 8080\n"
+    );
+
+    let same = run(&a, &a);
+    assert_eq!(same.status.code(), Some(0));
+    assert!(same.stdout.is_empty());
+}
+
+// `--key` sorts keyed siblings by their identity, so editing a record's other
+// fields doesn't move it in the --canonical output.
+#[test]
+fn test_canonical_key_keeps_records_in_place() {
+    let dir = std::env::temp_dir().join("unxml-key-test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let f = dir.join("rows.xml");
+    std::fs::write(
+        &f,
+        r#"<rows><row><amount>5</amount><id>1</id></row><row><amount>3</amount><id>2</id></row></rows>"#,
+    )
+    .unwrap();
+
+    // By signature the cheaper row sorts first...
+    let by_signature = run_unxml(&["--canonical", f.to_str().unwrap()]);
+    assert!(by_signature.find("id = 2").unwrap() < by_signature.find("id = 1").unwrap());
+
+    // ...by key, row 1 stays first whatever its amount.
+    let by_key = run_unxml(&["--canonical", "--key", "row=id", f.to_str().unwrap()]);
+    assert!(by_key.find("id = 1").unwrap() < by_key.find("id = 2").unwrap());
+}
+
+// `--normalize` with a schema rewrites values by their declared type, so a
+// numeric re-export diffs clean while a string that looks numeric is kept.
+#[test]
+fn test_normalize_by_schema_type() {
+    let dir = std::env::temp_dir().join("unxml-normalize-test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let xsd = dir.join("order.xsd");
+    let old = dir.join("old.xml");
+    let new = dir.join("new.xml");
+    std::fs::write(
+        &xsd,
+        r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+  <xs:element name="qty" type="xs:int"/>
+  <xs:element name="paid" type="xs:boolean"/>
+  <xs:element name="code" type="xs:string"/>
+</xs:schema>"#,
+    )
+    .unwrap();
+    std::fs::write(
+        &old,
+        "<order><qty>7</qty><paid>true</paid><code>1.0</code></order>",
+    )
+    .unwrap();
+    std::fs::write(
+        &new,
+        "<order><qty>007</qty><paid>1</paid><code>1.00</code></order>",
+    )
+    .unwrap();
+
+    let output = unxml()
+        .args(["diff", "--normalize", "all"])
+        .args(["--schema".as_ref(), xsd.as_os_str()])
+        .args([&old, &new])
+        .output()
+        .expect("Failed to execute unxml");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "  order\n~   code = 1.0 → 1.00\n"
+    );
+}
+
+// `--exc-c14n --select` emits the canonical bytes of a signed subtree: only the
+// namespaces it uses, sorted attributes, no trailing newline.
+#[test]
+fn test_exclusive_c14n_of_selected_subtree() {
+    let dir = std::env::temp_dir().join("unxml-c14n-test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let f = dir.join("signed.xml");
+    std::fs::write(
+        &f,
+        concat!(
+            "<?xml version=\"1.0\"?>\n",
+            "<Invoice xmlns=\"urn:inv\" xmlns:cbc=\"urn:cbc\" xmlns:cac=\"urn:cac\">\n",
+            "  <cac:Party b=\"2\" a=\"1\"><cbc:Name/></cac:Party>\n",
+            "</Invoice>\n",
+        ),
+    )
+    .unwrap();
+
+    let output = run_unxml(&["--exc-c14n", "--select", "Party", f.to_str().unwrap()]);
+    assert_eq!(
+        output,
+        r#"<cac:Party xmlns:cac="urn:cac" a="1" b="2"><cbc:Name xmlns:cbc="urn:cbc"></cbc:Name></cac:Party>"#
+    );
+}
+
+// `--ignore-attr` / `--ignore-elem` strip noise before diffing, so documents
+// that only differ there compare equal.
+#[test]
+fn test_diff_ignores_noise() {
+    let dir = std::env::temp_dir().join("unxml-ignore-test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let old = dir.join("old.html");
+    let new = dir.join("new.html");
+    std::fs::write(
+        &old,
+        r#"<html><body><div id="ctl00_a">Hi</div><p data-rev="1">ok</p></body></html>"#,
+    )
+    .unwrap();
+    std::fs::write(
+        &new,
+        r#"<html><body><div id="ctl00_b">Hi</div><p data-rev="2">ok</p></body></html>"#,
+    )
+    .unwrap();
+
+    let run = |extra: &[&str]| {
+        unxml()
+            .arg("diff")
+            .args(extra)
+            .args([&old, &new])
+            .output()
+            .expect("Failed to execute unxml")
+    };
+    assert_eq!(run(&[]).status.code(), Some(1));
+    let ignored = run(&["--ignore-preset", "--ignore-attr", "p@data-rev"]);
+    assert_eq!(ignored.status.code(), Some(0));
+    assert!(ignored.stdout.is_empty());
+}
+
+#[test]
+fn test_merge_driver() {
+    let dir = std::env::temp_dir().join("unxml-merge-test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let base = dir.join("base.xml");
+    let ours = dir.join("ours.xml");
+    let theirs = dir.join("theirs.xml");
+    let merge = |o: &str, t: &str| {
+        std::fs::write(
+            &base,
+            r#"<order><line id="1" qty="1"/><line id="2" qty="1"/></order>"#,
+        )
+        .unwrap();
+        std::fs::write(&ours, o).unwrap();
+        std::fs::write(&theirs, t).unwrap();
+        let output = unxml()
+            .args(["merge-driver", "--no-config"])
+            .args([&base, &ours, &theirs])
+            .output()
+            .expect("Failed to execute unxml");
+        (
+            output.status.code(),
+            std::fs::read_to_string(&ours).unwrap(),
+        )
+    };
+
+    let (code, merged) = merge(
+        r#"<order><line id="1" qty="5"/><line id="2" qty="1"/></order>"#,
+        r#"<order><line id="1" qty="1"/><line id="2" qty="7"/></order>"#,
+    );
+    assert_eq!(code, Some(0));
+    assert!(merged.contains(r#"<line id="1" qty="5"/>"#), "{merged}");
+    assert!(merged.contains(r#"<line id="2" qty="7"/>"#), "{merged}");
+    assert!(!merged.contains("<<<<<<<"));
+
+    let (code, merged) = merge(
+        r#"<order><line id="1" qty="5"/><line id="2" qty="1"/></order>"#,
+        r#"<order><line id="1" qty="6"/><line id="2" qty="1"/></order>"#,
+    );
+    assert_eq!(code, Some(1));
+    assert!(merged.contains("<<<<<<< ours"), "{merged}");
+    assert_eq!(merged.matches("<<<<<<<").count(), 1);
+    assert!(merged.contains(r#"<line id="2" qty="1"/>"#), "{merged}");
+}
+
+#[test]
+fn test_merge_driver_keeps_constructs_the_tree_drops() {
+    let dir = std::env::temp_dir().join("unxml-merge-lossless-test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let doc = |a: &str, b: &str| {
+        format!(
+            r#"<?xml version="1.0"?>
+<!DOCTYPE page SYSTEM "page.dtd">
+<?xml-stylesheet type="text/xsl" href="page.xsl"?>
+<page>
+  <item id="a">{a}</item>
+  <script><![CDATA[if (x < y) run();]]></script>
+  <pre xml:space="preserve">  keep   this  </pre>
+  <item id="b">{b}</item>
+</page>
+"#
+        )
+    };
+    let (base, ours, theirs) = (
+        dir.join("base.xml"),
+        dir.join("ours.xml"),
+        dir.join("theirs.xml"),
+    );
+    std::fs::write(&base, doc("1", "1")).unwrap();
+    std::fs::write(&ours, doc("2", "1")).unwrap();
+    std::fs::write(&theirs, doc("1", "3")).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_unxml"))
+        .env("XDG_CONFIG_HOME", no_user_config())
+        .args(["merge-driver", "--no-config"])
+        .args([&base, &ours, &theirs])
+        .output()
+        .expect("Failed to execute unxml");
+
+    // Each side edited a different item, so git's own line merge is clean
+    // and keeps everything a re-serialised tree would have dropped.
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(std::fs::read_to_string(&ours).unwrap(), doc("2", "3"));
+}
+
+// The driver takes `key` rules from `.unxml.toml`, matching `[[files]]`
+// patterns against the repository path git passes as `--path`: keyed by
+// `code`, a record one side edited and the other replaced is a conflict, not
+// a silent rename.
+#[test]
+fn test_merge_driver_reads_project_keys() {
+    let dir = std::env::temp_dir().join("unxml-merge-config-test");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join(".unxml.toml"),
+        "[[files]]\npattern = \"orders/*.xml\"\nkey = [\"rec=code\"]\n",
+    )
+    .unwrap();
+    let merge = |path: &str| {
+        for (name, code, v) in [("base", "A", "1"), ("ours", "A", "2"), ("theirs", "D", "1")] {
+            std::fs::write(
+                dir.join(format!(".merge_{name}")),
+                format!("<list><rec><code>{code}</code><v>{v}</v></rec></list>"),
+            )
+            .unwrap();
+        }
+        Command::new(env!("CARGO_BIN_EXE_unxml"))
+            .env("XDG_CONFIG_HOME", no_user_config())
+            .current_dir(&dir)
+            .args(["merge-driver", "--path", path])
+            .args([".merge_base", ".merge_ours", ".merge_theirs"])
+            .status()
+            .unwrap()
+            .code()
+    };
+
+    assert_eq!(merge("other/list.xml"), Some(0));
+    assert_eq!(merge("orders/list.xml"), Some(1));
+    let merged = std::fs::read_to_string(dir.join(".merge_ours")).unwrap();
+    assert!(merged.contains("<<<<<<< ours"), "{merged}");
+}
+
+#[test]
+fn test_diff_xsd_classifies_changes() {
+    let dir = std::env::temp_dir().join("unxml-xsd-diff-test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let old = dir.join("v1.xsd");
+    let new = dir.join("v2.xsd");
+    std::fs::write(
+        &old,
+        r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+  <xs:element name="Order">
+    <xs:complexType><xs:sequence>
+      <xs:element name="Id" type="xs:string"/>
+      <xs:element name="Note" type="xs:string" minOccurs="0"/>
+    </xs:sequence></xs:complexType>
+  </xs:element>
+</xs:schema>"#,
+    )
+    .unwrap();
+    std::fs::write(
+        &new,
+        r#"<xsd:schema xmlns:xsd="http://www.w3.org/2001/XMLSchema">
+  <xsd:element name="Order">
+    <xsd:complexType><xsd:sequence>
+      <xsd:element name="Id" type="xsd:token"/>
+      <xsd:element name="Note" type="xsd:string" minOccurs="0"/>
+      <xsd:element name="Memo" type="xsd:string" minOccurs="0"/>
+    </xsd:sequence></xsd:complexType>
+  </xsd:element>
+</xsd:schema>"#,
+    )
+    .unwrap();
+
+    let output = unxml()
+        .args(["diff", "--xsd"])
+        .args([&old, &new])
+        .output()
+        .expect("Failed to execute unxml");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        concat!(
+            "breaking\n",
+            "  ~ element Order\n",
+            "      ~ Id : xs:string → xs:token (narrowed)\n",
+            "compatible\n",
+            "  ~ element Order\n",
+            "      + Memo : xs:string ?\n",
+        )
+    );
+}
+
+#[test]
+fn test_diff_json_schema_classifies_changes() {
+    let dir = std::env::temp_dir().join("unxml-json-schema-diff-test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let old = dir.join("v1.json");
+    let new = dir.join("v2.json");
+    std::fs::write(
+        &old,
+        r#"{"$schema":"https://json-schema.org/draft/2020-12/schema","type":"object",
+            "properties":{"id":{"type":"integer"},"tags":{"type":"array","items":{"type":"string"}}}}"#,
+    )
+    .unwrap();
+    std::fs::write(
+        &new,
+        r#"{"$schema":"https://json-schema.org/draft/2020-12/schema","type":"object","required":["id"],
+            "properties":{"id":{"type":"integer"},"tags":{"type":"array","items":{"type":"string","enum":["a","b"]}}}}"#,
+    )
+    .unwrap();
+
+    let output = unxml()
+        .args(["diff", "--json-schema"])
+        .args([&old, &new])
+        .output()
+        .expect("Failed to execute unxml");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        concat!(
+            "breaking\n",
+            "  ~ schema : object\n",
+            "      ~ id optional → required\n",
+            "      + tags[] | a\n",
+            "      + tags[] | b\n",
+        )
+    );
+}
+
+// `unxml mcp` answers JSON-RPC requests line by line on stdio and skips
+// notifications.
+#[test]
+fn test_mcp_server() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let mut child = unxml()
+        .arg("mcp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute unxml");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(
+            concat!(
+                r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2025-06-18"}}"#,
+                "\n",
+                r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+                "\n",
+                r#"{"jsonrpc":"2.0","id":2,"method":"tools/list"}"#,
+                "\n",
+                r#"{"jsonrpc":"2.0","id":3,"method":"tools/call","params":{"name":"select","arguments":{"path":"test-input/data.xml","element":"skill","max_lines":2}}}"#,
+                "\n",
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let responses: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+    assert_eq!(responses.len(), 3);
+    assert_eq!(responses[0]["result"]["serverInfo"]["name"], "unxml");
+    let tools: Vec<&str> = responses[1]["result"]["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tool| tool["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(tools, ["render", "paths", "select", "diff", "stats"]);
+    assert_eq!(
+        responses[2]["result"]["content"][0]["text"],
+        concat!(
+            "skill(level=\"expert\") = JavaScript\n",
+            "\n",
+            "… 7 of 9 lines not shown (raise max_lines, or narrow with paths/select)\n",
+        )
+    );
+}
+
+// The `diff` tool applies the project's `.unxml.toml`, as `unxml diff` would.
+#[test]
+fn test_mcp_diff_reads_project_config() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let dir = std::env::temp_dir().join("unxml-mcp-config-test");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join(".unxml.toml"), "ignore-attr = [\"stamp\"]\n").unwrap();
+    std::fs::write(dir.join("old.xml"), r#"<a stamp="1"><b>x</b></a>"#).unwrap();
+    std::fs::write(dir.join("new.xml"), r#"<a stamp="2"><b>x</b></a>"#).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_unxml"))
+        .env("XDG_CONFIG_HOME", no_user_config())
+        .current_dir(&dir)
+        .arg("mcp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute unxml");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(
+            concat!(
+                r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"diff","arguments":{"old":"old.xml","new":"new.xml"}}}"#,
+                "\n",
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let response: serde_json::Value =
+        serde_json::from_str(String::from_utf8_lossy(&output.stdout).trim()).unwrap();
+    assert_eq!(
+        response["result"]["content"][0]["text"], "No differences.",
+        "{response}"
+    );
+}
+
+#[test]
+fn test_lsp_server() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let uri = format!(
+        "file://{}/test-input/xsd-constructs.xsd",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    let messages = [
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#.to_string(),
+        format!(
+            r#"{{"jsonrpc":"2.0","id":2,"method":"textDocument/documentSymbol","params":{{"textDocument":{{"uri":"{uri}"}}}}}}"#
+        ),
+        r#"{"jsonrpc":"2.0","id":3,"method":"shutdown"}"#.to_string(),
+        r#"{"jsonrpc":"2.0","method":"exit"}"#.to_string(),
+    ];
+    let mut child = unxml()
+        .arg("lsp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute unxml");
+    let mut stdin = child.stdin.take().unwrap();
+    for message in &messages {
+        write!(stdin, "Content-Length: {}\r\n\r\n{message}", message.len()).unwrap();
+    }
+    drop(stdin);
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let responses: Vec<serde_json::Value> = stdout
+        .split("Content-Length: ")
+        .filter(|frame| !frame.is_empty())
+        .map(|frame| serde_json::from_str(frame.split_once("\r\n\r\n").unwrap().1).unwrap())
+        .collect();
+
+    assert_eq!(responses.len(), 3);
+    let capabilities = &responses[0]["result"]["capabilities"];
+    assert_eq!(capabilities["documentSymbolProvider"], true);
+    assert_eq!(capabilities["foldingRangeProvider"], true);
+    let symbols: Vec<&str> = responses[1]["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|symbol| symbol["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        symbols[..6],
+        [
+            "include",
+            "import",
+            "Order",
+            "AbstractItem",
+            "DiscountedItem",
+            "OptionalNote"
+        ]
+    );
+    let order_type = responses[1]["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|symbol| symbol["name"] == "OrderType")
+        .unwrap();
+    assert_eq!(order_type["children"][0]["name"], "OrderId");
+    assert_eq!(order_type["children"][0]["detail"], "xs:string");
+    assert_eq!(responses[2]["result"], serde_json::Value::Null);
+}
+
+// `.unxml.toml` is found from a subdirectory; its `[[files]]` mode and its
+// top-level --hide-ns apply, and command-line flags override them.
+#[test]
+fn test_project_config() {
+    let dir = std::env::temp_dir().join("unxml-config-test");
+    let sub = dir.join("sub");
+    std::fs::create_dir_all(&sub).unwrap();
+    std::fs::write(
+        dir.join(".unxml.toml"),
+        "hide-ns = [\"p\"]\n\n[[files]]\npattern = \"*.config\"\nmode = \"msbuild\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        sub.join("web.config"),
+        r#"<Project xmlns:p="urn:p"><PropertyGroup Condition="'$(A)' == 'b'"><p:Out>x</p:Out></PropertyGroup></Project>"#,
+    )
+    .unwrap();
+
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_unxml"))
+            .args(args)
+            .current_dir(&sub)
+            .env("XDG_CONFIG_HOME", &dir)
+            .output()
+            .expect("Failed to execute unxml");
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    assert_eq!(
+        run(&["web.config"]),
+        "Project\n  if '$(A)' == 'b':\n    PropertyGroup\n      Out = x\n"
+    );
+    assert_eq!(
+        run(&["--no-config", "web.config"]),
+        "Project(xmlns:p=\"urn:p\")\n  PropertyGroup(Condition=\"'$(A)' == 'b'\")\n    p:Out = x\n"
+    );
+    assert_eq!(
+        run(&["--special", "web.config"]),
+        "Project\n  PropertyGroup(Condition=\"'$(A)' == 'b'\")\n    Out = x\n"
+    );
+}
+
+// `--map` renders an unknown extension in a mode, and `--init-git` binds the
+// mapped pattern and keeps the mapping in the textconv command.
+#[test]
+fn test_map_pattern_to_mode() {
+    let dir = std::env::temp_dir().join("unxml-map-test");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::copy("test-input/xslt-constructs.xsl", dir.join("a.xslt2")).unwrap();
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_unxml"))
+            .args(args)
+            .current_dir(&dir)
+            .env("XDG_CONFIG_HOME", &dir)
+            .output()
+            .expect("Failed to execute unxml");
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let mapped = run(&["--map", "*.xslt2=xslt", "a.xslt2"]);
+    assert_eq!(mapped, run(&["--xslt", "a.xslt2"]));
+    assert_ne!(mapped, run(&["a.xslt2"]));
+
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .current_dir(&dir)
+            .output()
+            .unwrap()
+    };
+    git(&["init", "--quiet"]);
+    run(&["--init-git", "--map", "*.xslt2=xslt"]);
+    let attributes = std::fs::read_to_string(dir.join(".git/info/attributes")).unwrap();
+    assert!(attributes.contains("*.xslt2 diff=unxml\n"));
+    let textconv = git(&["config", "diff.unxml.textconv"]).stdout;
+    assert_eq!(
+        String::from_utf8_lossy(&textconv),
+        "unxml --canonical --auto --map '*.xslt2=xslt'\n"
+    );
+}
+
+// `--auto` sniffs the dialect from the document element, so a WSDL piped to
+// stdin renders as it does from a `.wsdl` file.
+#[test]
+fn test_auto_sniffs_dialect_on_stdin() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let mut child = unxml()
+        .args(["--no-config", "--auto", "--stdin"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute unxml");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(&std::fs::read("test-input/wsdl-stockquote.wsdl").unwrap())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        run_unxml(&["--auto", "test-input/wsdl-stockquote.wsdl"])
+    );
+}
+
+// `-o` writes what would have been printed.
+#[test]
+fn test_output_file() {
+    let path = std::env::temp_dir().join("unxml-output-test.unxml");
+    let _ = std::fs::remove_file(&path);
+    let printed = run_unxml(&["test-input/simple.xml"]);
+    let written = run_unxml(&["-o", path.to_str().unwrap(), "test-input/simple.xml"]);
+    assert_eq!(written, "");
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), printed);
+}
+
+#[test]
+fn test_out_dir_mirrors_inputs() {
+    let dir = std::env::temp_dir().join("unxml-out-dir-test");
+    let _ = std::fs::remove_dir_all(&dir);
+    let src = dir.join("src");
+    std::fs::create_dir_all(src.join("nested")).unwrap();
+    std::fs::copy("test-input/simple.xml", src.join("a.xml")).unwrap();
+    std::fs::copy("test-input/simple.xml", src.join("nested/b.xml")).unwrap();
+    let out = dir.join("out");
+
+    let printed = run_unxml(&["test-input/simple.xml"]);
+    let src_glob = format!("{}/**/*.xml", src.display());
+    run_unxml(&["--out-dir", out.to_str().unwrap(), &src_glob]);
+    assert_eq!(
+        std::fs::read_to_string(out.join("a.xml.unxml")).unwrap(),
+        printed
+    );
+    assert_eq!(
+        std::fs::read_to_string(out.join("nested/b.xml.unxml")).unwrap(),
+        printed
+    );
+
+    // --html: a page per file linking the shared stylesheet, and an index.
+    let html = dir.join("html");
+    run_unxml(&["--html", "--out-dir", html.to_str().unwrap(), &src_glob]);
+    let page = std::fs::read_to_string(html.join("nested/b.xml.html")).unwrap();
+    assert!(page.contains("href=\"../unxml.css\""), "{page}");
+    assert!(html.join("unxml.css").exists());
+    let index = std::fs::read_to_string(html.join("index.html")).unwrap();
+    assert!(index.contains("href=\"a.xml.html\""), "{index}");
+    assert!(index.contains("href=\"nested/b.xml.html\""), "{index}");
+
+    // --suffix alone writes beside each input.
+    run_unxml(&["--suffix", ".txt", src.join("a.xml").to_str().unwrap()]);
+    assert_eq!(
+        std::fs::read_to_string(src.join("a.xml.txt")).unwrap(),
+        printed
+    );
+
+    // --html --suffix: the index and stylesheet go at the inputs' common
+    // directory, as the out dir's root would.
+    run_unxml(&["--html", "--suffix", ".page.html", &src_glob]);
+    assert!(src.join("unxml.css").exists());
+    let index = std::fs::read_to_string(src.join("index.html")).unwrap();
+    assert!(index.contains("href=\"a.xml.page.html\""), "{index}");
+    assert!(index.contains("href=\"nested/b.xml.page.html\""), "{index}");
+
+    // Nothing is written when an output would replace an input: with an
+    // empty suffix, or where one input is another's output.
+    let input = src.join("a.xml");
+    let earlier = src.join("a.xml.unxml");
+    std::fs::write(&earlier, "<kept/>").unwrap();
+    let (input, earlier) = (input.to_str().unwrap(), earlier.to_str().unwrap());
+    let source = std::fs::read_to_string(input).unwrap();
+    let src_dir = src.to_str().unwrap();
+    for args in [
+        vec!["--suffix", "", input],
+        vec!["--suffix", "", "--out-dir", src_dir, input],
+        vec!["--out-dir", src_dir, input, earlier],
+    ] {
+        let status = Command::new(env!("CARGO_BIN_EXE_unxml"))
+            .env("XDG_CONFIG_HOME", no_user_config())
+            .arg("--no-config")
+            .args(&args)
+            .status()
+            .unwrap();
+        assert!(!status.success(), "{args:?}");
+        assert_eq!(std::fs::read_to_string(input).unwrap(), source, "{args:?}");
+        assert_eq!(
+            std::fs::read_to_string(earlier).unwrap(),
+            "<kept/>",
+            "{args:?}"
+        );
+    }
+}
+
+#[test]
+fn test_site() {
+    let dir = std::env::temp_dir().join("unxml-site-test");
+    let _ = std::fs::remove_dir_all(&dir);
+    let src = dir.join("src");
+    std::fs::create_dir_all(src.join("common")).unwrap();
+    std::fs::write(
+        src.join("order.xsd"),
+        r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+  <xs:include schemaLocation="common/types.xsd"/>
+  <xs:element name="order" type="xs:string"/>
+</xs:schema>"#,
+    )
+    .unwrap();
+    std::fs::write(
+        src.join("common/types.xsd"),
+        r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+  <xs:simpleType name="code"><xs:restriction base="xs:string"/></xs:simpleType>
+</xs:schema>"#,
+    )
+    .unwrap();
+    std::fs::write(src.join("notes.txt"), "not rendered").unwrap();
+    let out = dir.join("out");
+
+    let status = unxml().arg("site").args([&src, &out]).status().unwrap();
+    assert!(status.success());
+
+    let index = std::fs::read_to_string(out.join("index.html")).unwrap();
+    assert!(index.contains("href=\"order.xsd.html\""), "{index}");
+    assert!(index.contains("href=\"common/types.xsd.html\""), "{index}");
+    assert!(!index.contains("notes.txt"), "{index}");
+    assert!(out.join("unxml.css").exists());
+
+    // The viewer frames both renderings and links the include both ways.
+    let order = std::fs::read_to_string(out.join("order.xsd.html")).unwrap();
+    assert!(order.contains("src=\"order.xsd.unxml.html\""), "{order}");
+    assert!(order.contains("src=\"order.xsd.raw.html\""), "{order}");
+    assert!(
+        order.contains("Imports: <a href=\"common/types.xsd.html\">"),
+        "{order}"
+    );
+    let types = std::fs::read_to_string(out.join("common/types.xsd.html")).unwrap();
+    assert!(
+        types.contains("Imported by: <a href=\"../order.xsd.html\">"),
+        "{types}"
+    );
+    assert!(types.contains("href=\"../index.html\""), "{types}");
+    let rendered = std::fs::read_to_string(out.join("common/types.xsd.unxml.html")).unwrap();
+    assert!(rendered.contains("href=\"../unxml.css\""), "{rendered}");
+}